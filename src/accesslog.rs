@@ -0,0 +1,340 @@
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::http::header::{HeaderMap, HeaderName, REFERER, USER_AGENT};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{WebRequest, WebResponse};
+
+use crate::i18n::{Msg, tf};
+use crate::proxy::{self, TrustProxy};
+use crate::requestid::RequestId;
+use crate::tls;
+
+// 访问日志格式
+#[derive(Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "json" => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+// 轮转时保留的历史文件数，即 access.log.1 到 access.log.5
+const MAX_BACKUPS: usize = 5;
+
+// 访问日志文件，所有工作线程共享；写入和轮转在同一把锁内完成，并发请求时不会丢失日志
+#[derive(Clone)]
+pub struct LogFile {
+    writer: Arc<Mutex<FileWriter>>,
+}
+
+struct FileWriter {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+}
+
+fn open_append(path: &PathBuf) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl LogFile {
+    // max_size 为 0 时不轮转
+    pub fn open(path: &str, max_size: u64) -> io::Result<Self> {
+        let path = PathBuf::from(path);
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+            writer: Arc::new(Mutex::new(FileWriter { path, file, size, max_size })),
+        })
+    }
+
+    fn write_line(&self, line: &str) {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let len = line.len() as u64 + 1;
+        if writer.max_size > 0
+            && writer.size > 0
+            && writer.size + len > writer.max_size
+            && let Err(e) = writer.rotate()
+        {
+            log::error!("{}", tf(Msg::RotateAccessLogFailed, &[&writer.path.display(), &e]));
+        }
+        match writeln!(writer.file, "{}", line) {
+            Ok(()) => writer.size += len,
+            Err(e) => log::error!("{}", tf(Msg::WriteAccessLogFailed, &[&writer.path.display(), &e])),
+        }
+    }
+}
+
+impl FileWriter {
+    // access.log.4 -> access.log.5 ... access.log -> access.log.1，再新建 access.log
+    fn rotate(&mut self) -> io::Result<()> {
+        let backup = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        for n in (1..MAX_BACKUPS).rev() {
+            if backup(n).exists() {
+                std::fs::rename(backup(n), backup(n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, backup(1))?;
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+// 扫描器大量请求不存在的路径时，4xx 响应每 N 条只记录一条(--log-sample)；
+// 其他响应总是记录，计数器在所有工作线程之间共享
+#[derive(Clone)]
+pub struct Sampler {
+    every: u64,
+    count: Arc<AtomicU64>,
+}
+
+impl Sampler {
+    // every 为 1 时记录全部请求
+    pub fn new(every: u64) -> Self {
+        Sampler {
+            every: every.max(1),
+            count: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn keep(&self, status: u16) -> bool {
+        if self.every == 1 || !(400..500).contains(&status) {
+            return true;
+        }
+        self.count.fetch_add(1, Ordering::Relaxed).is_multiple_of(self.every)
+    }
+}
+
+// 一次请求的访问记录，响应体发送完毕或连接断开时输出
+struct Record {
+    // 是否写访问日志；为 false 时只用于检查慢请求
+    log: bool,
+    // 超过该耗时的请求另外输出一条警告(--slow-request-threshold)
+    slow_threshold: Option<Duration>,
+    format: LogFormat,
+    file: Option<LogFile>,
+    start: Instant,
+    method: String,
+    uri: String,
+    path: String,
+    version: String,
+    status: u16,
+    remote_ip: Option<IpAddr>,
+    client_cn: Option<String>,
+    request_id: Option<String>,
+    referer: String,
+    user_agent: String,
+}
+
+impl Record {
+    fn finish(&self, bytes: u64) {
+        let elapsed = self.start.elapsed();
+        if self.log {
+            self.write(bytes, elapsed);
+        }
+        if self.slow_threshold.is_some_and(|threshold| elapsed > threshold) {
+            log::warn!("{}", tf(Msg::SlowRequest, &[&self.method, &self.path, &self.status, &bytes, &format!("{:.3}", elapsed.as_secs_f64())]));
+        }
+    }
+
+    fn write(&self, bytes: u64, elapsed: Duration) {
+        // 双向TLS时附上客户端证书的 CN，以及请求ID，文本格式放在末尾
+        let line = match self.format {
+            // 与 ntex Logger 默认格式一致: %a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T
+            LogFormat::Text => {
+                let mut line = format!(
+                    "{} \"{} {} {}\" {} {} \"{}\" \"{}\" {:.6}",
+                    self.remote_ip.map_or_else(|| "-".to_string(), |ip| ip.to_string()),
+                    self.method,
+                    self.uri,
+                    self.version,
+                    self.status,
+                    bytes,
+                    self.referer,
+                    self.user_agent,
+                    elapsed.as_secs_f64()
+                );
+                if let Some(ref cn) = self.client_cn {
+                    line.push_str(&format!(" cn=\"{}\"", cn));
+                }
+                if let Some(ref id) = self.request_id {
+                    line.push_str(&format!(" id={}", id));
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut value = serde_json::json!({
+                    "timestamp": humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+                    "method": self.method,
+                    "path": self.path,
+                    "status": self.status,
+                    "bytes": bytes,
+                    "duration_ms": elapsed.as_secs_f64() * 1000.0,
+                    "remote_ip": self.remote_ip,
+                });
+                if let Some(ref cn) = self.client_cn {
+                    value["client_cn"] = cn.as_str().into();
+                }
+                if let Some(ref id) = self.request_id {
+                    value["request_id"] = id.as_str().into();
+                }
+                value.to_string()
+            }
+        };
+        log::info!("{}", line);
+
+        // 文本格式写入文件时补上时间，JSON 自带 timestamp 字段
+        if let Some(ref file) = self.file {
+            match self.format {
+                LogFormat::Text => file.write_line(&format!("[{}] {}", humantime::format_rfc3339_seconds(SystemTime::now()), line)),
+                LogFormat::Json => file.write_line(&line),
+            }
+        }
+    }
+}
+
+// 取出请求头的值，不存在时记为 -，不记录 Authorization 头，不会泄露凭据
+fn header(headers: &HeaderMap, name: HeaderName) -> String {
+    headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or("-").to_string()
+}
+
+// 统计实际发送的字节数，释放时输出访问日志
+struct LoggedBody {
+    body: ResponseBody<Body>,
+    record: Record,
+    bytes: u64,
+}
+
+impl MessageBody for LoggedBody {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        let poll = self.body.poll_next_chunk(cx);
+        if let Poll::Ready(Some(Ok(ref chunk))) = poll {
+            self.bytes += chunk.len() as u64;
+        }
+        poll
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        self.record.finish(self.bytes);
+    }
+}
+
+// 访问日志中间件，替代 ntex 自带的 Logger，支持文本和 JSON 两种格式；
+// 同时记录每个请求的耗时，超过 --slow-request-threshold 时输出警告，不受日志采样和 info 级别的影响
+#[derive(Clone)]
+pub struct AccessLog {
+    format: LogFormat,
+    trust_proxy: TrustProxy,
+    file: Option<LogFile>,
+    sampler: Sampler,
+    slow_threshold: Option<Duration>,
+}
+
+impl AccessLog {
+    // file 不为空时访问日志同时写入该文件
+    pub fn new(format: LogFormat, trust_proxy: TrustProxy, file: Option<LogFile>, sampler: Sampler, slow_threshold: Option<Duration>) -> Self {
+        AccessLog {
+            format,
+            trust_proxy,
+            file,
+            sampler,
+            slow_threshold,
+        }
+    }
+}
+
+impl<S> Middleware<S> for AccessLog {
+    type Service = AccessLogMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        AccessLogMiddleware {
+            service,
+            format: self.format,
+            trust_proxy: self.trust_proxy.clone(),
+            file: self.file.clone(),
+            sampler: self.sampler.clone(),
+            slow_threshold: self.slow_threshold,
+        }
+    }
+}
+
+pub struct AccessLogMiddleware<S> {
+    service: S,
+    format: LogFormat,
+    trust_proxy: TrustProxy,
+    file: Option<LogFile>,
+    sampler: Sampler,
+    slow_threshold: Option<Duration>,
+}
+
+impl<S, E> Service<WebRequest<E>> for AccessLogMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let log = log::log_enabled!(log::Level::Info);
+        let slow_threshold = self.slow_threshold.filter(|_| log::log_enabled!(log::Level::Warn));
+        if !log && slow_threshold.is_none() {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let mut record = Record {
+            log,
+            slow_threshold,
+            format: self.format,
+            file: self.file.clone(),
+            start: Instant::now(),
+            method: req.method().to_string(),
+            uri: req.uri().to_string(),
+            path: req.path().to_string(),
+            version: format!("{:?}", req.version()),
+            status: 0,
+            remote_ip: proxy::client_ip(req.headers(), req.peer_addr(), &self.trust_proxy),
+            client_cn: tls::client_common_name(req.io()),
+            request_id: req.extensions().get::<RequestId>().map(|id| id.0.clone()),
+            referer: header(req.headers(), REFERER),
+            user_agent: header(req.headers(), USER_AGENT),
+        };
+
+        let res = ctx.call(&self.service, req).await?;
+        record.status = res.status().as_u16();
+        record.log = record.log && self.sampler.keep(record.status);
+        if !record.log && record.slow_threshold.is_none() {
+            return Ok(res);
+        }
+        Ok(res.map_body(move |_, body| ResponseBody::Other(Body::from_message(LoggedBody { body, record, bytes: 0 }))))
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use futures::SinkExt;
+use futures::channel::mpsc;
+use ntex::http::Method;
+use ntex::http::header::CONTENT_DISPOSITION;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::types::State;
+use ntex::web::{HttpRequest, HttpResponse, WebRequest, WebResponse};
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::disposition;
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 递归打包的最大目录深度，防止符号链接成环导致无限遍历
+const MAX_DEPTH: usize = 32;
+
+// 每次发送给客户端的数据块大小
+const CHUNK_SIZE: usize = 64 * 1024;
+
+type ZipStream = ZipWriter<StreamWriter<ChannelWriter>>;
+
+pub const ZIP_COMPRESSIONS: [&str; 3] = ["stored", "deflate", "auto"];
+
+// 本身已经压缩过的格式，auto 模式下直接存储，再压缩只浪费 CPU
+const COMPRESSED_EXTENSIONS: [&str; 32] = [
+    "zip", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "jar", "apk", "docx", "xlsx", "pptx", "odt", "ods", "odp", "jpg", "jpeg", "png", "gif", "webp", "heic", "avif", "mp3", "m4a", "aac", "ogg", "opus", "flac", "mp4", "mkv", "webm",
+];
+
+// 打包时每个文件的压缩方式(--zip-compression)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZipCompression {
+    Stored,
+    Deflate,
+    Auto,
+}
+
+impl ZipCompression {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "stored" => ZipCompression::Stored,
+            "auto" => ZipCompression::Auto,
+            _ => ZipCompression::Deflate,
+        }
+    }
+
+    fn method(self, name: &str) -> CompressionMethod {
+        let compressed = || {
+            Path::new(name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| COMPRESSED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        };
+        match self {
+            ZipCompression::Stored => CompressionMethod::Stored,
+            ZipCompression::Deflate => CompressionMethod::Deflated,
+            ZipCompression::Auto if compressed() => CompressionMethod::Stored,
+            ZipCompression::Auto => CompressionMethod::Deflated,
+        }
+    }
+}
+
+// 目录请求带 ?zip=1 时，实时打包整个目录为 ZIP 下载
+#[derive(Clone)]
+pub struct ZipDownload {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    enabled: bool,
+    compression: ZipCompression,
+}
+
+impl ZipDownload {
+    pub fn new(mounts: Vec<Mount>, enabled: bool, compression: ZipCompression) -> Self {
+        ZipDownload {
+            inner: Rc::new(Inner { mounts, enabled, compression }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for ZipDownload {
+    type Service = ZipDownloadMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ZipDownloadMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct ZipDownloadMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ZipDownloadMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let wants_zip = self.inner.enabled && *req.method() == Method::GET && query::flag(req.query_string(), "zip");
+
+        let resolved = mount::find(&self.inner.mounts, req.path()).and_then(|mount| Some((mount.resolve(req.path())?, mount.clone())));
+        let (dir, mount) = match resolved {
+            Some((dir, mount)) if wants_zip && mount.listing && dir.is_dir() => (dir, mount),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let name = archive_name(&dir);
+        let response = HttpResponse::Ok()
+            .content_type("application/zip")
+            .header(CONTENT_DISPOSITION, disposition::attachment(&name))
+            .streaming(stream_dir(dir, mount, self.inner.compression));
+        Ok(req.into_response(response))
+    }
+}
+
+// 压缩包文件名取目录名
+fn archive_name(dir: &Path) -> String {
+    let name = dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "files".to_string());
+    format!("{}.zip", name)
+}
+
+// 在独立线程中遍历目录并写入 ZIP，通过有界通道把数据块交给响应体，
+// 客户端读取慢时写入端会阻塞等待，不会把整个压缩包缓存在内存里
+fn stream_dir(dir: PathBuf, mount: Mount, compression: ZipCompression) -> mpsc::Receiver<Result<Bytes, io::Error>> {
+    let (tx, rx) = mpsc::channel(4);
+    std::thread::spawn(move || {
+        let mut zip = ZipWriter::new_stream(ChannelWriter::new(tx));
+        let result = write_dir(&mut zip, &mount, compression, &dir, "", 0)
+            .and_then(|_| zip.finish().map_err(io::Error::other))
+            .and_then(|writer| writer.into_inner().flush());
+        if let Err(e) = result {
+            log::debug!("{}", tf(Msg::ZipDirAborted, &[&dir.display(), &e]));
+        }
+    });
+    rx
+}
+
+fn write_dir(zip: &mut ZipStream, mount: &Mount, compression: ZipCompression, dir: &Path, prefix: &str, depth: usize) -> io::Result<()> {
+    if depth > MAX_DEPTH {
+        log::warn!("{}", tf(Msg::ZipTooDeep, &[&dir.display(), &MAX_DEPTH]));
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !mount.is_visible(&name) {
+            continue;
+        }
+        let path = entry.path();
+        // 与列表相同，未启用 --follow-symlinks 时跳过指向共享目录之外的符号链接
+        let is_symlink = std::fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if is_symlink && !mount.follow_symlinks && mount.escapes(&path) {
+            continue;
+        }
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if !mount.allows(&path, metadata.is_dir()) {
+            continue;
+        }
+        write_entry(zip, mount, compression, &path, &metadata, &format!("{}{}", prefix, name), depth)?;
+    }
+    Ok(())
+}
+
+// 写入一个文件，或目录及其中的全部内容
+fn write_entry(
+    zip: &mut ZipStream,
+    mount: &Mount,
+    compression: ZipCompression,
+    path: &Path,
+    metadata: &std::fs::Metadata,
+    entry_name: &str,
+    depth: usize,
+) -> io::Result<()> {
+    let options = SimpleFileOptions::default()
+        .compression_method(compression.method(entry_name))
+        .large_file(metadata.len() >= u32::MAX as u64)
+        .last_modified_time(zip_time(metadata.modified().ok()));
+
+    if metadata.is_dir() {
+        zip.add_directory(entry_name, options).map_err(io::Error::other)?;
+        write_dir(zip, mount, compression, path, &format!("{}/", entry_name), depth + 1)
+    } else {
+        zip.start_file(entry_name, options).map_err(io::Error::other)?;
+        io::copy(&mut std::fs::File::open(path)?, zip)?;
+        Ok(())
+    }
+}
+
+// 打包所选文件的接口，每个挂载点一份
+pub struct ZipTarget {
+    pub mount: Mount,
+    pub compression: ZipCompression,
+}
+
+// GET {URL路径}/zip?file=a.txt&file=docs 或以表单 POST 同样的参数，打包所选的文件和目录。
+// 路径相对于挂载点根目录，压缩包中保留这一相对路径；包含 .. 或指向共享目录之外的路径拒绝整个请求，
+// 所选目录中指向共享目录之外的符号链接与打包整个目录时一样跳过
+pub async fn zip_selection(req: HttpRequest, target: State<ZipTarget>, body: Bytes) -> HttpResponse {
+    let mount = &target.mount;
+    let params = if req.method() == Method::POST { &body[..] } else { req.query_string().as_bytes() };
+    // 按相对路径排序去重，已选目录中的文件不再单独写入
+    let mut selected = BTreeMap::new();
+    for (_, relative) in form_urlencoded::parse(params).filter(|(key, _)| key == "file") {
+        let path = match mount.resolve_relative(&relative) {
+            Some(path) if path != mount.dir => path,
+            _ => return HttpResponse::BadRequest().body(tf(Msg::InvalidPathNamed, &[&relative])),
+        };
+        if !mount.follow_symlinks && mount.escapes(&path) {
+            log::warn!("{}", tf(Msg::ZipSymlinkRejected, &[&path.display()]));
+            return HttpResponse::Forbidden().body(t(Msg::OutsideShare));
+        }
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) if mount.allows(&path, metadata.is_dir()) => metadata,
+            _ => return HttpResponse::NotFound().body(tf(Msg::NotExists, &[&relative])),
+        };
+        let name = relative.split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("/");
+        selected.insert(name, (path, metadata));
+    }
+    if selected.is_empty() {
+        return HttpResponse::BadRequest().body(t(Msg::NoFilesSelected));
+    }
+    let mut entries: Vec<(String, PathBuf, std::fs::Metadata)> = Vec::new();
+    for (name, (path, metadata)) in selected {
+        if !entries.iter().any(|(dir, _, dir_metadata)| dir_metadata.is_dir() && name.starts_with(&format!("{}/", dir))) {
+            entries.push((name, path, metadata));
+        }
+    }
+
+    HttpResponse::Ok()
+        .content_type("application/zip")
+        .header(CONTENT_DISPOSITION, disposition::attachment(&archive_name(&mount.dir)))
+        .streaming(stream_selection(entries, mount.clone(), target.compression))
+}
+
+fn stream_selection(entries: Vec<(String, PathBuf, std::fs::Metadata)>, mount: Mount, compression: ZipCompression) -> mpsc::Receiver<Result<Bytes, io::Error>> {
+    let (tx, rx) = mpsc::channel(4);
+    std::thread::spawn(move || {
+        let mut zip = ZipWriter::new_stream(ChannelWriter::new(tx));
+        let result = entries
+            .iter()
+            .try_for_each(|(name, path, metadata)| write_entry(&mut zip, &mount, compression, path, metadata, name, 0))
+            .and_then(|_| zip.finish().map_err(io::Error::other))
+            .and_then(|writer| writer.into_inner().flush());
+        if let Err(e) = result {
+            log::debug!("{}", tf(Msg::ZipSelectionAborted, &[&e]));
+        }
+    });
+    rx
+}
+
+// ZIP 使用本地时间记录修改时间
+fn zip_time(modified: Option<SystemTime>) -> zip::DateTime {
+    modified
+        .and_then(|time| jiff::Timestamp::try_from(time).ok())
+        .and_then(|ts| zip::DateTime::try_from(ts.to_zoned(jiff::tz::TimeZone::system()).datetime()).ok())
+        .unwrap_or_default()
+}
+
+// 将写入的数据按块发送到通道，接收端关闭(客户端断开)时返回错误以终止打包；文件清单也用它流式输出
+pub struct ChannelWriter {
+    tx: mpsc::Sender<Result<Bytes, io::Error>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    pub fn new(tx: mpsc::Sender<Result<Bytes, io::Error>>) -> Self {
+        ChannelWriter {
+            tx,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    fn send_buf(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE)));
+        futures::executor::block_on(self.tx.send(Ok(chunk))).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.send_buf()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[test]
+    fn auto_stores_already_compressed_files() {
+        assert_eq!(ZipCompression::Auto.method("photo.JPG"), CompressionMethod::Stored);
+        assert_eq!(ZipCompression::Auto.method("backup.tar.gz"), CompressionMethod::Stored);
+        assert_eq!(ZipCompression::Auto.method("notes.txt"), CompressionMethod::Deflated);
+        assert_eq!(ZipCompression::Auto.method("Makefile"), CompressionMethod::Deflated);
+        assert_eq!(ZipCompression::Stored.method("notes.txt"), CompressionMethod::Stored);
+        assert_eq!(ZipCompression::Deflate.method("photo.jpg"), CompressionMethod::Deflated);
+    }
+
+    #[cfg(unix)]
+    #[ntex::test]
+    async fn skips_symlinks_escaping_the_share() {
+        let dir = TempDir::new();
+        let outside = TempDir::new();
+        dir.write("a.txt", "a");
+        outside.write("passwd", "root");
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("passwd"), dir.path().join("passwd.txt")).unwrap();
+        let mut mount = dir.mount("/download/files");
+        mount.zip = true;
+        let app = test::init_service(
+            web::App::new()
+                .wrap(ZipDownload::new(vec![mount.clone()], true, ZipCompression::Stored))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+
+        let body = test::read_response(&app, test::TestRequest::with_uri("/download/files/?zip=1").to_request()).await;
+        let contains = |name: &str| body.windows(name.len()).any(|window| window == name.as_bytes());
+        assert!(contains("a.txt"));
+        assert!(!contains("link/") && !contains("passwd") && !contains("root"));
+    }
+
+    #[ntex::test]
+    async fn zips_selected_files_only() {
+        let dir = TempDir::new();
+        dir.write("a.txt", "a");
+        dir.write("skip.txt", "skip");
+        dir.write("docs/b.txt", "b");
+        dir.write("docs/c.txt", "c");
+        dir.write(".secret", "secret");
+        let mut mount = dir.mount("/download/files");
+        mount.zip = true;
+        let app = test::init_service(
+            web::App::new()
+                .service(
+                    web::resource("/download/files/zip")
+                        .state(ZipTarget {
+                            mount: mount.clone(),
+                            compression: ZipCompression::Stored,
+                        })
+                        .route(web::get().to(zip_selection))
+                        .route(web::post().to(zip_selection)),
+                )
+                .service(testutil::files(&mount)),
+        )
+        .await;
+
+        // 目录中的文件已随目录写入，不会重复
+        let req = test::TestRequest::with_uri("/download/files/zip?file=a.txt&file=docs&file=docs%2Fb.txt").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = test::read_body(res).await;
+        let contains = |name: &str| body.windows(name.len()).any(|window| window == name.as_bytes());
+        assert!(body.starts_with(b"PK"));
+        assert!(contains("a.txt") && contains("docs/b.txt") && contains("docs/c.txt"));
+        assert!(!contains("skip.txt"));
+
+        // 所选目录中指向共享目录之外的符号链接同样跳过
+        #[cfg(unix)]
+        {
+            let outside = TempDir::new();
+            outside.write("passwd", "root");
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("docs/link")).unwrap();
+            let req = test::TestRequest::with_uri("/download/files/zip?file=docs").to_request();
+            let body = test::read_body(test::call_service(&app, req).await).await;
+            let contains = |name: &str| body.windows(name.len()).any(|window| window == name.as_bytes());
+            assert!(contains("docs/b.txt"));
+            assert!(!contains("docs/link") && !contains("passwd"));
+        }
+
+        let req = test::TestRequest::with_uri("/download/files/zip")
+            .method(Method::POST)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .set_payload("file=docs%2Fc.txt")
+            .to_request();
+        let body = test::read_body(test::call_service(&app, req).await).await;
+        assert!(body.windows(10).any(|window| window == b"docs/c.txt"));
+
+        for (query, status) in [
+            ("file=..%2F..%2Fetc%2Fpasswd", StatusCode::BAD_REQUEST),
+            ("file=docs%2F..%2F..%2Fx", StatusCode::BAD_REQUEST),
+            ("file=.secret", StatusCode::BAD_REQUEST),
+            ("file=missing.txt", StatusCode::NOT_FOUND),
+            ("", StatusCode::BAD_REQUEST),
+        ] {
+            let req = test::TestRequest::with_uri(&format!("/download/files/zip?{}", query)).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status, "{}", query);
+        }
+
+        // 子目录的列表中复选框的值为相对于挂载点根目录的路径
+        let body = test::read_body(test::call_service(&app, test::TestRequest::with_uri("/download/files/docs/").to_request()).await).await;
+        let html = String::from_utf8_lossy(&body);
+        assert!(html.contains("value=\"docs/b.txt\" form=\"selection\""));
+        assert!(html.contains("action=\"/download/files/zip\""));
+    }
+}
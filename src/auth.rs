@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ntex::http::error::BlockingError;
+use ntex::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use sha2::{Digest, Sha256};
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::{favicon, health, share, ui, version};
+
+// HTTP Basic Auth 中间件，只保护挂载点前缀下的路径；挂载点单独配置的账号优先于全局账号，
+// 两者都未配置时直接放行
+#[derive(Clone)]
+pub struct BasicAuth {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    credentials: Option<Vec<u8>>,
+}
+
+impl BasicAuth {
+    pub fn new(mounts: &[Mount], username: Option<&str>, password: Option<&str>) -> Self {
+        let credentials = match (username, password) {
+            (Some(user), Some(pass)) => Some(format!("{}:{}", user, pass).into_bytes()),
+            _ => None,
+        };
+        BasicAuth {
+            inner: Rc::new(Inner {
+                mounts: mounts.to_vec(),
+                credentials,
+            }),
+        }
+    }
+}
+
+// 健康检查供负载均衡探测使用，版本信息用于排查部署，界面资源是公开的内置文件，
+// 即使挂载在 / 下也不需要认证
+fn is_public(path: &str) -> bool {
+    path == health::PATH || path == version::PATH || path == favicon::PATH || mount::strip_url_prefix(ui::PREFIX, path).is_some()
+}
+
+impl Inner {
+    // 请求路径需要的账号
+    fn expected(&self, path: &str) -> Option<&[u8]> {
+        if is_public(path) {
+            return None;
+        }
+        let mount = mount::find(&self.mounts, path)?;
+        mount.credentials.as_deref().or(self.credentials.as_deref())
+    }
+}
+
+impl<S> Middleware<S> for BasicAuth {
+    type Service = BasicAuthMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        BasicAuthMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct BasicAuthMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for BasicAuthMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        // 已通过分享链接授权的请求不再要求账号密码
+        let expected = match self.inner.expected(req.path()) {
+            Some(expected) if req.extensions().get::<share::Granted>().is_none() => expected,
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let provided = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(decode_basic);
+
+        match provided {
+            Some(provided) if constant_time_eq(&provided, expected) => ctx.call(&self.service, req).await,
+            _ => Ok(req.into_response(
+                HttpResponse::Unauthorized()
+                    .header(WWW_AUTHENTICATE, "Basic realm=\"FileShare\"")
+                    .finish(),
+            )),
+        }
+    }
+}
+
+// 认证程序的最长运行时间，超时按出错处理
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 认证通过的凭据在这段时间内不再调用认证程序
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+// 缓存的凭据条数上限，超过后先清理过期的，仍超过则清空
+const CACHE_CAPACITY: usize = 1024;
+
+// 认证通过的凭据及通过时间，按 Authorization 头的 SHA-256 索引，不在内存中保留明文；在所有工作线程间共享
+#[derive(Clone, Default)]
+pub struct AuthCache {
+    entries: Arc<Mutex<HashMap<[u8; 32], Instant>>>,
+}
+
+impl AuthCache {
+    pub fn new() -> Self {
+        AuthCache::default()
+    }
+
+    fn contains(&self, key: &[u8; 32]) -> bool {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.get(key).is_some_and(|granted| granted.elapsed() < CACHE_TTL)
+    }
+
+    fn insert(&self, key: [u8; 32]) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= CACHE_CAPACITY {
+            entries.retain(|_, granted| granted.elapsed() < CACHE_TTL);
+            if entries.len() >= CACHE_CAPACITY {
+                entries.clear();
+            }
+        }
+        entries.insert(key, Instant::now());
+    }
+}
+
+// --auth-command 指定的外部认证程序：挂载点下的请求把 Authorization 头(没有时为空)通过环境变量
+// HTTP_AUTHORIZATION 和标准输入交给该程序，退出码为 0 时放行，非 0 时返回 401；
+// 程序无法启动、被信号终止或超时时拒绝请求并返回 503。认证通过的凭据缓存一段时间，结果只取决于凭据
+#[derive(Clone)]
+pub struct CommandAuth {
+    inner: Rc<CommandInner>,
+}
+
+struct CommandInner {
+    command: Option<PathBuf>,
+    mounts: Vec<Mount>,
+    cache: AuthCache,
+}
+
+impl CommandAuth {
+    pub fn new(command: Option<&str>, mounts: &[Mount], cache: AuthCache) -> Self {
+        CommandAuth {
+            inner: Rc::new(CommandInner {
+                command: command.map(PathBuf::from),
+                mounts: mounts.to_vec(),
+                cache,
+            }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for CommandAuth {
+    type Service = CommandAuthMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CommandAuthMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct CommandAuthMiddleware<S> {
+    service: S,
+    inner: Rc<CommandInner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for CommandAuthMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        // 已通过分享链接授权的请求不再调用认证程序
+        let command = match self.inner.command {
+            Some(ref command) if !is_public(req.path()) && mount::find(&self.inner.mounts, req.path()).is_some() => command,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        if req.extensions().get::<share::Granted>().is_some() {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let authorization = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let key: [u8; 32] = Sha256::digest(authorization.as_bytes()).into();
+        if self.inner.cache.contains(&key) {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let program = command.clone();
+        let status = web::block(move || run_command(&program, &authorization)).await.map_err(|e| match e {
+            BlockingError::Error(e) => e,
+            BlockingError::Canceled => io::Error::other(t(Msg::ThreadPoolClosed)),
+        });
+        match status {
+            Ok(status) if status.success() => {
+                self.inner.cache.insert(key);
+                ctx.call(&self.service, req).await
+            }
+            // 与 Basic 认证一样要求浏览器弹出登录框，认证程序可以只接受 Basic 凭据
+            Ok(status) if status.code().is_some() => Ok(req.into_response(
+                HttpResponse::Unauthorized()
+                    .header(WWW_AUTHENTICATE, "Basic realm=\"FileShare\"")
+                    .finish(),
+            )),
+            Ok(status) => {
+                log::warn!("{}", tf(Msg::AuthCommandExited, &[&command.display(), &status]));
+                Ok(req.into_response(HttpResponse::ServiceUnavailable().finish()))
+            }
+            Err(e) => {
+                log::warn!("{}", tf(Msg::AuthCommandFailed, &[&command.display(), &e]));
+                Ok(req.into_response(HttpResponse::ServiceUnavailable().finish()))
+            }
+        }
+    }
+}
+
+// 运行认证程序并等待其退出，超时后结束该程序；会阻塞，需在线程池中调用
+fn run_command(command: &Path, authorization: &str) -> io::Result<ExitStatus> {
+    let mut child = Command::new(command)
+        .env("HTTP_AUTHORIZATION", authorization)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    // 程序不读取标准输入就退出时写入会失败，不影响结果
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "{}", authorization);
+    }
+    let deadline = Instant::now() + COMMAND_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(io::ErrorKind::TimedOut, t(Msg::AuthCommandTimedOut)));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+// 解析 "Basic xxx" 头，返回解码后的 "用户名:密码"
+fn decode_basic(value: &str) -> Option<Vec<u8>> {
+    let (scheme, encoded) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    STANDARD.decode(encoded.trim()).ok()
+}
+
+// 常量时间比较，避免通过响应耗时猜测凭据
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let mut diff = a.len() ^ b.len();
+    for i in 0..a.len().max(b.len()) {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= (x ^ y) as usize;
+    }
+    diff == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn protects_only_mounts_with_credentials() {
+        let public_dir = TempDir::new();
+        public_dir.write("a.txt", "public");
+        let private_dir = TempDir::new();
+        private_dir.write("b.txt", "private");
+        let public = public_dir.mount("/public");
+        let mut private = private_dir.mount("/private");
+        private.credentials = Some(b"user:pass".to_vec());
+        let mounts = vec![public.clone(), private.clone()];
+        let app = test::init_service(
+            web::App::new()
+                .wrap(BasicAuth::new(&mounts, None, None))
+                .service(testutil::files(&public))
+                .service(testutil::files(&private)),
+        )
+        .await;
+        let get = |uri: &str, auth: Option<&str>| {
+            let req = test::TestRequest::with_uri(uri);
+            match auth {
+                Some(auth) => req.header(AUTHORIZATION, format!("Basic {}", STANDARD.encode(auth))).to_request(),
+                None => req.to_request(),
+            }
+        };
+
+        let res = test::call_service(&app, get("/public/a.txt", None)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = test::call_service(&app, get("/private/b.txt", None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = test::call_service(&app, get("/private/b.txt", Some("user:wrong"))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = test::call_service(&app, get("/private/b.txt", Some("user:pass"))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[cfg(unix)]
+    #[ntex::test]
+    async fn command_decides_and_caches_by_credential() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new();
+        dir.write("a.txt", "a");
+        let tools = TempDir::new();
+        // 每次调用在 calls 中记一行，只接受 Bearer good
+        let script = tools.write(
+            "auth.sh",
+            "#!/bin/sh\necho >> \"$(dirname \"$0\")/calls\"\n[ \"$HTTP_AUTHORIZATION\" = \"Bearer good\" ]\n",
+        );
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let broken = tools.write("broken.sh", "not executable");
+        let mount = dir.mount("/download/files");
+        let app = |command: &Path| {
+            test::init_service(
+                web::App::new()
+                    .wrap(CommandAuth::new(command.to_str(), std::slice::from_ref(&mount), AuthCache::new()))
+                    .service(testutil::files(&mount)),
+            )
+        };
+        let get = |auth: Option<&str>| {
+            let req = test::TestRequest::with_uri("/download/files/a.txt");
+            match auth {
+                Some(auth) => req.header(AUTHORIZATION, auth).to_request(),
+                None => req.to_request(),
+            }
+        };
+
+        let service = app(&script).await;
+        let res = test::call_service(&service, get(None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = test::call_service(&service, get(Some("Bearer bad"))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        for _ in 0..3 {
+            let res = test::call_service(&service, get(Some("Bearer good"))).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        // 通过的凭据只调用一次认证程序，被拒绝的每次都调用
+        assert_eq!(std::fs::read_to_string(tools.path().join("calls")).unwrap().lines().count(), 3);
+
+        let service = app(&broken).await;
+        let res = test::call_service(&service, get(Some("Bearer good"))).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+
+// 检查共享目录是否存在的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+// 运行中共享目录被删除时返回 503，而不是让文件服务报出难以理解的IO错误
+#[derive(Clone)]
+pub struct DirGuard {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+}
+
+impl DirGuard {
+    pub fn new(mounts: Vec<Mount>) -> Self {
+        DirGuard {
+            inner: Rc::new(Inner { mounts }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for DirGuard {
+    type Service = DirGuardMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        DirGuardMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct DirGuardMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for DirGuardMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if let Some(mount) = mount::find(&self.inner.mounts, req.path())
+            && !mount.single_file
+            && !mount.dir.is_dir()
+        {
+            return Ok(req.into_response(HttpResponse::ServiceUnavailable().body(t(Msg::DirUnavailable))));
+        }
+        ctx.call(&self.service, req).await
+    }
+}
+
+// 后台定期检查共享目录：消失时记录警告，恢复时记录信息；开启 --recreate-dir 时与启动时一样重新创建
+pub struct Monitor {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl Monitor {
+    pub fn start(mounts: &[Mount], recreate: bool) -> Self {
+        let dirs: Vec<PathBuf> = mounts.iter().map(|mount| mount.dir.clone()).collect();
+        let (stop, rx) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let mut missing = vec![false; dirs.len()];
+            loop {
+                for (dir, missing) in dirs.iter().zip(missing.iter_mut()) {
+                    check(dir, missing, recreate);
+                }
+                match rx.recv_timeout(CHECK_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => return,
+                }
+            }
+        });
+        Monitor { stop, thread }
+    }
+
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+    }
+}
+
+fn check(dir: &Path, missing: &mut bool, recreate: bool) {
+    if dir.is_dir() {
+        if *missing {
+            log::info!("{}", tf(Msg::SharedDirRestored, &[&dir.display()]));
+            *missing = false;
+        }
+        return;
+    }
+    if !*missing {
+        log::warn!("{}", tf(Msg::SharedDirGone, &[&dir.display()]));
+        *missing = true;
+    }
+    if recreate {
+        match std::fs::create_dir_all(dir) {
+            Ok(()) => {
+                log::info!("{}", tf(Msg::SharedDirRecreated, &[&dir.display()]));
+                *missing = false;
+            }
+            Err(e) => log::error!("{}", tf(Msg::RecreateSharedDirFailed, &[&dir.display(), &e])),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::rc::Rc;
+
+use ntex::http::StatusCode;
+use ntex::http::header::{CACHE_CONTROL, ETAG, HeaderValue, LAST_MODIFIED};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{WebRequest, WebResponse};
+
+use crate::i18n::{Msg, tf};
+use crate::mount::{self, Mount};
+
+// 为文件响应统一加上 Cache-Control，包括范围请求和 304 响应；目录列表不受影响
+#[derive(Clone)]
+pub struct CacheControl {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    value: Option<HeaderValue>,
+    mounts: Vec<Mount>,
+}
+
+impl CacheControl {
+    // 未设置 --cache-control 时传入 None，中间件直接放行
+    pub fn new(value: Option<HeaderValue>, mounts: Vec<Mount>) -> Self {
+        CacheControl {
+            inner: Rc::new(Inner { value, mounts }),
+        }
+    }
+}
+
+// 校验 --cache-control 的值能否作为响应头
+pub fn parse(value: &str) -> Result<HeaderValue, String> {
+    HeaderValue::from_str(value.trim()).map_err(|_| tf(Msg::InvalidCacheControl, &[&value]))
+}
+
+impl<S> Middleware<S> for CacheControl {
+    type Service = CacheControlMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CacheControlMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct CacheControlMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for CacheControlMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let value = match self.inner.value {
+            Some(ref value) => value.clone(),
+            None => return ctx.call(&self.service, req).await,
+        };
+        let is_file = mount::find(&self.inner.mounts, req.path())
+            .and_then(|mount| mount.resolve(req.path()))
+            .is_some_and(|path| path.is_file());
+
+        let mut res = ctx.call(&self.service, req).await?;
+        let status = res.status();
+        if !is_file || !(status.is_success() || status == StatusCode::NOT_MODIFIED) {
+            return Ok(res);
+        }
+
+        let headers = res.headers_mut();
+        // no-store 完全禁用缓存，同时去掉 ETag 和 Last-Modified，客户端不会再发条件请求
+        if value.as_bytes().eq_ignore_ascii_case(b"no-store") {
+            headers.remove(ETAG);
+            headers.remove(LAST_MODIFIED);
+        }
+        headers.insert(CACHE_CONTROL, value);
+        Ok(res)
+    }
+}
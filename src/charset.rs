@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use ntex::http::header::{CONTENT_TYPE, HeaderValue};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{WebRequest, WebResponse};
+
+use crate::i18n::{Msg, tf};
+use crate::mount::{self, Mount};
+
+// 检查字符集名称，如 utf-8、gbk、shift_jis，统一转为小写
+pub fn parse_charset(value: &str) -> Result<String, String> {
+    let charset = value.trim().to_ascii_lowercase();
+    if charset.is_empty() || !charset.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')) {
+        return Err(tf(Msg::InvalidCharset, &[&value]));
+    }
+    Ok(charset)
+}
+
+// 解析 ext=charset 格式的字符集映射，扩展名不区分大小写，可带前导的 .
+pub fn parse_ext_charset(value: &str) -> Result<(String, String), String> {
+    let (ext, charset) = value
+        .split_once('=')
+        .ok_or_else(|| tf(Msg::InvalidExtCharset, &[&value]))?;
+    let ext = ext.trim().trim_start_matches('.').to_ascii_lowercase();
+    if ext.is_empty() {
+        return Err(tf(Msg::ExtCharsetMissingExt, &[&value]));
+    }
+    Ok((ext, parse_charset(charset)?))
+}
+
+// 为 text/* 类型的文件响应补上 charset 参数，按 --ext-charset 的映射选择，其余使用 --text-charset；
+// 已带 charset 的类型(如 --mime 中指定的)保持不变。需注册在 MimeOverride 之外
+#[derive(Clone)]
+pub struct Charset {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    default: String,
+    by_extension: HashMap<String, String>,
+    mounts: Vec<Mount>,
+}
+
+impl Charset {
+    pub fn new(default: &str, by_extension: &[(String, String)], mounts: Vec<Mount>) -> Self {
+        Charset {
+            inner: Rc::new(Inner {
+                default: default.to_string(),
+                by_extension: by_extension.iter().cloned().collect(),
+                mounts,
+            }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Charset {
+    type Service = CharsetMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CharsetMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct CharsetMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for CharsetMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let path = match mount::find(&self.inner.mounts, req.path()).and_then(|mount| mount.resolve(req.path())) {
+            Some(path) if path.is_file() => path,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        let charset = path
+            .extension()
+            .and_then(|ext| self.inner.by_extension.get(&ext.to_string_lossy().to_ascii_lowercase()))
+            .unwrap_or(&self.inner.default);
+
+        let mut res = ctx.call(&self.service, req).await?;
+        let content_type = match res.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()) {
+            Some(value) if res.status().is_success() && value.starts_with("text/") && !value.to_ascii_lowercase().contains("charset=") => value,
+            _ => return Ok(res),
+        };
+        if let Ok(value) = HeaderValue::from_str(&format!("{}; charset={}", content_type, charset)) {
+            res.headers_mut().insert(CONTENT_TYPE, value);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn appends_charset_to_text_files() {
+        let dir = TempDir::new();
+        dir.write("readme.md", "# 说明");
+        dir.write("gbk.txt", [0xc4, 0xe3, 0xba, 0xc3]);
+        dir.write("image.png", [0x89, b'P', b'N', b'G']);
+        let mount = dir.mount("/download/files");
+        let by_extension = [parse_ext_charset(".TXT=GBK").unwrap()];
+        let app = test::init_service(
+            web::App::new()
+                .wrap(Charset::new("utf-8", &by_extension, vec![mount.clone()]))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+        let content_type = |res: &WebResponse| res.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap().to_string();
+
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/readme.md").to_request()).await;
+        assert!(content_type(&res).ends_with("; charset=utf-8"));
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/gbk.txt").to_request()).await;
+        assert_eq!(content_type(&res), "text/plain; charset=gbk");
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/image.png").to_request()).await;
+        assert_eq!(content_type(&res), "image/png");
+        // 目录列表已带 charset=utf-8
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/").to_request()).await;
+        assert_eq!(content_type(&res), "text/html; charset=utf-8");
+
+        assert!(parse_charset("utf 8").is_err());
+        assert!(parse_ext_charset("txt").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use md5::Md5;
+use ntex::http::Method;
+use ntex::http::error::BlockingError;
+use ntex::http::header::ACCEPT;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 缓存的校验和条数上限，超过后清空重新累积
+const CACHE_CAPACITY: usize = 1024;
+
+// 每次读取文件的块大小
+const BUF_SIZE: usize = 64 * 1024;
+
+// 支持的校验算法
+const ALGORITHMS: [&str; 3] = ["sha256", "sha1", "md5"];
+
+// 已计算的校验和，按路径和算法索引；文件的修改时间或大小变化后重新计算
+#[derive(Clone, Default)]
+pub struct DigestCache {
+    entries: Arc<Mutex<HashMap<(PathBuf, &'static str), CachedDigest>>>,
+}
+
+struct CachedDigest {
+    mtime: Option<SystemTime>,
+    len: u64,
+    digest: String,
+}
+
+impl DigestCache {
+    pub fn new() -> Self {
+        DigestCache::default()
+    }
+
+    // 读取文件计算校验和，命中缓存时不再读取文件；会阻塞，需在线程池或独立线程中调用
+    pub fn digest(&self, path: &Path, algorithm: &'static str) -> io::Result<String> {
+        let metadata = std::fs::metadata(path)?;
+        let (mtime, len) = (metadata.modified().ok(), metadata.len());
+        let key = (path.to_path_buf(), algorithm);
+        {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(cached) = entries.get(&key)
+                && cached.mtime == mtime
+                && cached.len == len
+            {
+                return Ok(cached.digest.clone());
+            }
+        }
+
+        let file = File::open(path)?;
+        let digest = match algorithm {
+            "md5" => hash::<Md5>(file)?,
+            "sha1" => hash::<Sha1>(file)?,
+            _ => hash::<Sha256>(file)?,
+        };
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= CACHE_CAPACITY {
+            entries.clear();
+        }
+        entries.insert(key, CachedDigest { mtime, len, digest: digest.clone() });
+        Ok(digest)
+    }
+}
+
+// 逐块读取文件计算摘要，返回十六进制字符串
+fn hash<D: Digest>(mut file: File) -> io::Result<String> {
+    let mut hasher = D::new();
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// 文件请求带 ?checksum=sha256 时返回文件的校验和，也支持 md5 和 sha1；
+// 请求头 Accept 为 application/json 时返回 JSON
+#[derive(Clone)]
+pub struct Checksum {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    cache: DigestCache,
+}
+
+impl Checksum {
+    pub fn new(mounts: Vec<Mount>, cache: DigestCache) -> Self {
+        Checksum {
+            inner: Rc::new(Inner { mounts, cache }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Checksum {
+    type Service = ChecksumMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ChecksumMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct ChecksumMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ChecksumMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let requested = match query::get(req.query_string(), "checksum") {
+            Some(requested) if *req.method() == Method::GET => requested.to_ascii_lowercase(),
+            _ => return ctx.call(&self.service, req).await,
+        };
+        let path = match mount::find(&self.inner.mounts, req.path()).and_then(|mount| mount.resolve(req.path())) {
+            Some(path) => path,
+            None => return ctx.call(&self.service, req).await,
+        };
+        let algorithm = match ALGORITHMS.iter().find(|algorithm| **algorithm == requested) {
+            Some(algorithm) => *algorithm,
+            None => {
+                let body = tf(Msg::UnsupportedChecksum, &[&requested, &ALGORITHMS.join(", ")]);
+                return Ok(req.into_response(HttpResponse::BadRequest().body(body)));
+            }
+        };
+        // 文件不存在时交给文件服务返回 404
+        if path.is_dir() {
+            return Ok(req.into_response(HttpResponse::BadRequest().body(t(Msg::ChecksumFileOnly))));
+        } else if !path.exists() {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let cache = self.inner.cache.clone();
+        let file = path.clone();
+        let response = match web::block(move || cache.digest(&file, algorithm)).await {
+            Ok(digest) => {
+                let wants_json = req
+                    .headers()
+                    .get(ACCEPT)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|value| value.contains("application/json"));
+                if wants_json {
+                    HttpResponse::Ok().json(&serde_json::json!({ "algorithm": algorithm, "digest": digest }))
+                } else {
+                    HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(digest)
+                }
+            }
+            Err(BlockingError::Error(e)) if e.kind() == io::ErrorKind::NotFound => return ctx.call(&self.service, req).await,
+            Err(e) => {
+                log::error!("{}", tf(Msg::ChecksumFailed, &[&path.display(), &e]));
+                HttpResponse::InternalServerError().finish()
+            }
+        };
+        Ok(req.into_response(response))
+    }
+}
@@ -0,0 +1,236 @@
+use std::error::Error;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::future::LocalBoxFuture;
+use ntex::http::error::BlockingError;
+use ntex::http::header::{CONTENT_ENCODING, CONTENT_RANGE};
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::http::{Method, StatusCode};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{self, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::size::parse_size;
+
+// ntex-files 每次读取 64KB，--chunk-size 为该值时不替换响应体
+pub const DEFAULT_CHUNK_SIZE: u64 = 64 * 1024;
+const MIN_CHUNK_SIZE: u64 = 4 * 1024;
+const MAX_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+// 检查读取块大小是否在 4KB 到 16MB 之间
+pub fn check_chunk_size(size: u64) -> Result<u64, String> {
+    if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&size) {
+        return Err(tf(Msg::ChunkSizeOutOfRange, &[&size]));
+    }
+    Ok(size)
+}
+
+pub fn parse_chunk_size(value: &str) -> Result<u64, String> {
+    check_chunk_size(parse_size(value)?)
+}
+
+// 按 --chunk-size 读取文件：把文件服务返回的 200/206 响应体换成每次读取指定大小的响应体，
+// 高延迟、高带宽的链路上更大的块可以减少线程池往返次数。预压缩文件等带 Content-Encoding 的响应不处理。
+// 需注册在 HiddenPaths 之外，其余读取或包装响应体的中间件之内
+#[derive(Clone)]
+pub struct ChunkSize {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    size: u64,
+}
+
+impl ChunkSize {
+    pub fn new(mounts: Vec<Mount>, size: u64) -> Self {
+        ChunkSize {
+            inner: Rc::new(Inner { mounts, size }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for ChunkSize {
+    type Service = ChunkSizeMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ChunkSizeMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct ChunkSizeMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ChunkSizeMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if self.inner.size == DEFAULT_CHUNK_SIZE || *req.method() != Method::GET {
+            return ctx.call(&self.service, req).await;
+        }
+        let path = match mount::find(&self.inner.mounts, req.path()).and_then(|mount| mount.resolve(req.path())) {
+            Some(path) if path.is_file() => path,
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let res = ctx.call(&self.service, req).await?;
+        if res.headers().contains_key(CONTENT_ENCODING) {
+            return Ok(res);
+        }
+        // 206 的响应体没有长度(由 RangeLength 补上)，起止位置取自 Content-Range
+        let (offset, length) = match (res.status(), res.response().body().size()) {
+            (StatusCode::OK, BodySize::Sized(length)) => (0, length),
+            (StatusCode::PARTIAL_CONTENT, _) => match res.headers().get(CONTENT_RANGE).and_then(|value| value.to_str().ok()).and_then(range_bounds) {
+                Some(range) => range,
+                None => return Ok(res),
+            },
+            _ => return Ok(res),
+        };
+        let file = match File::open(&path).and_then(|mut file| {
+            let total = file.metadata()?.len();
+            file.seek(SeekFrom::Start(offset))?;
+            Ok((file, total))
+        }) {
+            Ok((file, total)) if offset.checked_add(length).is_some_and(|end| end <= total) => file,
+            _ => return Ok(res),
+        };
+
+        let body = FileBody {
+            file: Some(file),
+            remaining: length,
+            length,
+            chunk_size: self.inner.size,
+            fut: None,
+        };
+        Ok(res.map_body(|_, _| ResponseBody::Other(Body::from_message(body))))
+    }
+}
+
+// 从 "bytes 10-19/10000" 中取出起始位置 10 和长度 10
+fn range_bounds(value: &str) -> Option<(u64, u64)> {
+    let (range, _) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end): (u64, u64) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    Some((start, end.checked_sub(start)?.checked_add(1)?))
+}
+
+type ReadChunk = LocalBoxFuture<'static, Result<(File, Bytes), BlockingError<io::Error>>>;
+
+// 在线程池中按块读取文件，与 ntex-files 相同，只是块大小可配置
+struct FileBody {
+    file: Option<File>,
+    remaining: u64,
+    length: u64,
+    chunk_size: u64,
+    fut: Option<ReadChunk>,
+}
+
+impl MessageBody for FileBody {
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.length)
+    }
+
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        loop {
+            if let Some(ref mut fut) = self.fut {
+                return match Pin::new(fut).poll(cx) {
+                    Poll::Ready(Ok((file, bytes))) => {
+                        self.fut = None;
+                        self.file = Some(file);
+                        self.remaining -= bytes.len() as u64;
+                        Poll::Ready(Some(Ok(bytes)))
+                    }
+                    Poll::Ready(Err(BlockingError::Error(e))) => Poll::Ready(Some(Err(Rc::new(e)))),
+                    Poll::Ready(Err(BlockingError::Canceled)) => Poll::Ready(Some(Err(Rc::new(io::Error::other(t(Msg::ReadCanceled)))))),
+                    Poll::Pending => Poll::Pending,
+                };
+            }
+            if self.remaining == 0 {
+                return Poll::Ready(None);
+            }
+            let mut file = match self.file.take() {
+                Some(file) => file,
+                None => return Poll::Ready(None),
+            };
+            let len = self.remaining.min(self.chunk_size);
+            self.fut = Some(Box::pin(web::block(move || {
+                let mut buf = Vec::with_capacity(len as usize);
+                // 文件在发送过程中被截短时提前结束，不能一直返回空块
+                if file.by_ref().take(len).read_to_end(&mut buf)? == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok((file, Bytes::from(buf)))
+            })));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::header::RANGE;
+    use ntex::web::test;
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    // 逐块读取响应体，返回每块的长度和拼接后的内容
+    async fn chunks(mut res: WebResponse) -> (Vec<usize>, Vec<u8>) {
+        let mut body = res.take_body();
+        let (mut sizes, mut content) = (Vec::new(), Vec::new());
+        while let Some(chunk) = std::future::poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            let chunk = chunk.unwrap();
+            sizes.push(chunk.len());
+            content.extend_from_slice(&chunk);
+        }
+        (sizes, content)
+    }
+
+    #[ntex::test]
+    async fn reads_files_in_configured_chunks() {
+        let dir = TempDir::new();
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        dir.write("big.bin", &data);
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(
+            web::App::new()
+                .wrap(ChunkSize::new(vec![mount.clone()], parse_chunk_size("128KB").unwrap()))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/big.bin").to_request()).await;
+        let (sizes, content) = chunks(res).await;
+        assert_eq!(sizes, [131072, 131072, 300_000 - 2 * 131072]);
+        assert!(content == data);
+
+        let req = test::TestRequest::with_uri("/download/files/big.bin").header(RANGE, "bytes=1000-200999").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let (sizes, content) = chunks(res).await;
+        assert_eq!(sizes, [131072, 200_000 - 131072]);
+        assert!(content == data[1000..201_000]);
+
+        assert!(parse_chunk_size("1KB").is_err());
+        assert!(parse_chunk_size("32MB").is_err());
+    }
+}
@@ -0,0 +1,380 @@
+use std::error::Error;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::task::{Context, Poll, ready};
+
+use brotli::CompressorWriter;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use ntex::http::StatusCode;
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ContentEncoding, ETAG, HeaderValue, VARY};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{BodyEncoding, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, t, tf};
+
+// 本身已经压缩过的文件类型，再压缩只会浪费CPU
+const SKIP_EXTENSIONS: [&str; 22] = [
+    "zip", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "br", "png", "jpg", "jpeg", "gif", "webp", "avif", "ico",
+    "mp3", "mp4", "mkv", "webm", "pdf", "woff2",
+];
+
+// 小于该大小的响应不压缩
+const MIN_SIZE: u64 = 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    pub fn header_value(self) -> HeaderValue {
+        HeaderValue::from_static(self.as_str())
+    }
+
+    // 预压缩文件的扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gz",
+            Encoding::Brotli => "br",
+        }
+    }
+}
+
+// 客户端接受的压缩算法，按权重从高到低排列，权重相同时 brotli 在前，q=0 的不接受
+pub fn preferences(value: &str) -> Vec<Encoding> {
+    let mut accepted = Vec::new();
+    for item in value.split(',') {
+        let mut parts = item.split(';');
+        let encoding = match parts.next().unwrap_or("").trim().to_ascii_lowercase().as_str() {
+            "br" => Encoding::Brotli,
+            "gzip" => Encoding::Gzip,
+            _ => continue,
+        };
+        let q = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if q > 0.0 {
+            accepted.push((encoding, q));
+        }
+    }
+    accepted.sort_by(|a, b| b.1.total_cmp(&a.1).then((b.0 == Encoding::Brotli).cmp(&(a.0 == Encoding::Brotli))));
+    accepted.into_iter().map(|(encoding, _)| encoding).collect()
+}
+
+// 根据 Accept-Encoding 选择压缩算法
+fn negotiate(value: &str) -> Option<Encoding> {
+    preferences(value).into_iter().next()
+}
+
+fn skipped_extension(path: &str) -> bool {
+    path.rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|(_, ext)| SKIP_EXTENSIONS.iter().any(|skip| ext.eq_ignore_ascii_case(skip)))
+}
+
+// 图片、音视频以及压缩包类型的响应不压缩
+fn skipped_content_type(value: &str) -> bool {
+    value.starts_with("image/")
+        || value.starts_with("video/")
+        || value.starts_with("audio/")
+        || value.starts_with("application/zip")
+        || value.starts_with("application/gzip")
+        || value.starts_with("application/x-")
+}
+
+// 根据客户端的 Accept-Encoding 对响应进行 gzip/brotli 压缩；debug 日志级别下记录每个响应是否压缩、
+// 不压缩的原因以及压缩前后的大小，压缩后的大小在响应体发送完毕时统计，不会缓存响应体
+#[derive(Clone)]
+pub struct Compress {
+    enabled: bool,
+    level: u32,
+}
+
+impl Compress {
+    pub fn new(enabled: bool, level: u32) -> Self {
+        Compress { enabled, level }
+    }
+}
+
+impl<S> Middleware<S> for Compress {
+    type Service = CompressMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CompressMiddleware {
+            service,
+            enabled: self.enabled,
+            level: self.level,
+        }
+    }
+}
+
+pub struct CompressMiddleware<S> {
+    service: S,
+    enabled: bool,
+    level: u32,
+}
+
+impl<S, E> Service<WebRequest<E>> for CompressMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if !self.enabled {
+            return ctx.call(&self.service, req).await;
+        }
+        let encoding = req
+            .headers()
+            .get(ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .and_then(negotiate)
+            .ok_or(t(Msg::NoAcceptedEncoding))
+            .and_then(|encoding| if skipped_extension(req.path()) { Err(t(Msg::CompressedExtension)) } else { Ok(encoding) });
+        let label = log::log_enabled!(log::Level::Debug).then(|| format!("{} {}", req.method(), req.path()));
+
+        let mut res = ctx.call(&self.service, req).await?;
+        let encoding = match encoding.and_then(|encoding| skip_reason(&res).map_or(Ok(encoding), Err)) {
+            Ok(encoding) => encoding,
+            Err(reason) => {
+                if let Some(label) = label {
+                    let size = match res.response().body().size() {
+                        BodySize::Sized(size) => size.to_string(),
+                        BodySize::Stream => t(Msg::UnknownSize).to_string(),
+                        BodySize::None | BodySize::Empty => "0".to_string(),
+                    };
+                    log::debug!("{}", tf(Msg::NotCompressed, &[&label, &reason, &size]));
+                }
+                return Ok(res);
+            }
+        };
+
+        let headers = res.headers_mut();
+        headers.insert(CONTENT_ENCODING, encoding.header_value());
+        headers.remove(CONTENT_LENGTH);
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+        // 压缩后的内容与原文件字节不同，强 ETag 改为弱 ETag，If-None-Match 仍按弱比较命中
+        if let Some(etag) = headers.get(ETAG).and_then(|value| value.to_str().ok())
+            && etag.starts_with('"')
+            && let Ok(weak) = HeaderValue::from_str(&format!("W/{}", etag))
+        {
+            headers.insert(ETAG, weak);
+        }
+
+        let level = self.level;
+        Ok(res.map_body(move |_, body| {
+            ResponseBody::Other(Body::from_message(CompressedBody {
+                body,
+                encoder: Some(Encoder::new(encoding, level)),
+                stats: label.map(|label| Stats {
+                    label,
+                    encoding,
+                    original: 0,
+                    compressed: 0,
+                }),
+            }))
+        }))
+    }
+}
+
+// 只压缩完整的 200 响应，范围请求等已指定编码的响应保持原样；返回不压缩的原因
+fn skip_reason(res: &WebResponse) -> Option<&'static str> {
+    let big_enough = match res.response().body().size() {
+        BodySize::Sized(size) => size >= MIN_SIZE,
+        BodySize::Stream => true,
+        BodySize::None | BodySize::Empty => false,
+    };
+    let content_type = res.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).unwrap_or("");
+
+    if res.status() != StatusCode::OK {
+        Some(t(Msg::NotOkResponse))
+    } else if !big_enough {
+        Some(t(Msg::ResponseTooSmall))
+    } else if res.headers().contains_key(CONTENT_ENCODING) || res.response().get_encoding() == Some(ContentEncoding::Identity) {
+        Some(t(Msg::AlreadyEncoded))
+    } else if skipped_content_type(content_type) {
+        Some(t(Msg::CompressedContentType))
+    } else {
+        None
+    }
+}
+
+enum Encoder {
+    Gzip(Box<GzEncoder<Vec<u8>>>),
+    Brotli(Box<CompressorWriter<Vec<u8>>>),
+}
+
+impl Encoder {
+    fn new(encoding: Encoding, level: u32) -> Self {
+        match encoding {
+            Encoding::Gzip => Encoder::Gzip(Box::new(GzEncoder::new(Vec::new(), Compression::new(level)))),
+            Encoding::Brotli => Encoder::Brotli(Box::new(CompressorWriter::new(Vec::new(), 4096, level, 22))),
+        }
+    }
+
+    // 写入一块数据，返回目前已产生的压缩输出
+    fn write(&mut self, data: &[u8]) -> io::Result<Bytes> {
+        let output = match self {
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+            Encoder::Brotli(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(output)))
+    }
+
+    fn finish(self) -> io::Result<Bytes> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.finish().map(Bytes::from),
+            Encoder::Brotli(encoder) => Ok(Bytes::from(encoder.into_inner())),
+        }
+    }
+}
+
+// 边读取原响应体边压缩，不在内存中缓存整个文件
+struct CompressedBody {
+    body: ResponseBody<Body>,
+    encoder: Option<Encoder>,
+    // 只在 debug 日志级别下统计
+    stats: Option<Stats>,
+}
+
+// 压缩前后的字节数，响应体发送完毕时写入 debug 日志
+struct Stats {
+    label: String,
+    encoding: Encoding,
+    original: u64,
+    compressed: u64,
+}
+
+impl MessageBody for CompressedBody {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        loop {
+            let encoder = match self.encoder.as_mut() {
+                Some(encoder) => encoder,
+                None => return Poll::Ready(None),
+            };
+            let (result, done) = match ready!(self.body.poll_next_chunk(cx)) {
+                Some(Ok(chunk)) => {
+                    if let Some(stats) = self.stats.as_mut() {
+                        stats.original += chunk.len() as u64;
+                    }
+                    (encoder.write(&chunk), false)
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => (self.encoder.take().map_or(Ok(Bytes::new()), Encoder::finish), true),
+            };
+            if let (Ok(output), Some(stats)) = (&result, self.stats.as_mut()) {
+                stats.compressed += output.len() as u64;
+                if done {
+                    log::debug!("{}", tf(Msg::Compressed, &[&stats.label, &stats.encoding.as_str(), &stats.original, &stats.compressed]));
+                }
+            }
+            match result {
+                Ok(output) if output.is_empty() => continue,
+                Ok(output) => return Poll::Ready(Some(Ok(output))),
+                Err(e) => return Poll::Ready(Some(Err(Rc::new(e)))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use ntex::http::Request;
+    use ntex::http::header::{HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+    use ntex::service::Pipeline;
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    async fn get<S, E>(app: &Pipeline<S>, headers: &[(HeaderName, &str)]) -> WebResponse
+    where
+        S: Service<Request, Response = WebResponse, Error = E>,
+        E: std::fmt::Debug,
+    {
+        let mut req = test::TestRequest::with_uri("/download/files/a.txt");
+        for (name, value) in headers {
+            req = req.header(name.clone(), *value);
+        }
+        test::call_service(app, req.to_request()).await
+    }
+
+    fn etag(res: &WebResponse) -> String {
+        res.headers().get(ETAG).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[ntex::test]
+    async fn etag_revalidates_and_changes_with_mtime() {
+        let dir = TempDir::new();
+        let path = dir.write("a.txt", "hello");
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(web::App::new().service(testutil::files(&mount))).await;
+
+        let res = get(&app, &[]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let last_modified = res.headers().get(LAST_MODIFIED).unwrap().to_str().unwrap().to_string();
+        let first = etag(&res);
+        assert!(first.starts_with('"'));
+
+        let res = get(&app, &[(IF_NONE_MATCH, &first)]).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert!(test::read_body(res).await.is_empty());
+        let res = get(&app, &[(IF_MODIFIED_SINCE, &last_modified)]).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        // 修改时间变化后 ETag 随之变化，旧的 ETag 不再命中
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+        let res = get(&app, &[(IF_NONE_MATCH, &first)]).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_ne!(etag(&res), first);
+    }
+
+    #[ntex::test]
+    async fn compressed_response_has_weak_etag() {
+        let dir = TempDir::new();
+        dir.write("a.txt", "hello world\n".repeat(200));
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(web::App::new().wrap(Compress::new(true, 6)).service(testutil::files(&mount))).await;
+
+        let plain = etag(&get(&app, &[]).await);
+        let res = get(&app, &[(ACCEPT_ENCODING, "gzip")]).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let weak = etag(&res);
+        assert_eq!(weak, format!("W/{}", plain));
+
+        // 客户端带回弱 ETag 时按弱比较仍然命中
+        let res = get(&app, &[(ACCEPT_ENCODING, "gzip"), (IF_NONE_MATCH, &weak)]).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    }
+}
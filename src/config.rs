@@ -0,0 +1,530 @@
+use clap::builder::BoolishValueParser;
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Command};
+use ntex::http::header::{HeaderName, HeaderValue};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::duration::parse_duration;
+use crate::glob::{parse_extension, parse_glob};
+use crate::headers::parse_header;
+use crate::i18n::{LANGS, Msg, tf};
+use crate::ipfilter::parse_cidr;
+use crate::mount::{MountSpec, parse_base_path};
+use crate::archive::ZIP_COMPRESSIONS;
+use crate::listing::{SORT_KEYS, SORT_ORDERS, parse_csp, parse_index_file};
+use crate::logger::SYSLOG_FACILITIES;
+use crate::charset::{parse_charset, parse_ext_charset};
+use crate::chunk::check_chunk_size;
+use crate::methods::parse_method;
+use crate::mime::parse_mime;
+use crate::size::parse_size;
+use crate::tls::TLS_VERSIONS;
+use crate::uds::parse_mode;
+use crate::{Args, LOG_FORMATS, LOG_LEVELS, parse_mount};
+
+// 配置文件结构，字段与 Args 一一对应，未出现的键保持命令行或默认值
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    file_dir: Option<String>,
+    url_path: Option<String>,
+    log_level: Option<String>,
+    quiet: Option<bool>,
+    verbose: Option<bool>,
+    no_panic_hook: Option<bool>,
+    port: Option<u16>,
+    host: Option<String>,
+    worker: Option<usize>,
+    tls_cert: Option<String>,
+    tls_key: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    auth_command: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_mounts")]
+    mount: Option<Vec<MountSpec>>,
+    enable_upload: Option<bool>,
+    enable_zip: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_cidrs")]
+    allow: Option<Vec<ipnet::IpNet>>,
+    #[serde(default, deserialize_with = "deserialize_cidrs")]
+    deny: Option<Vec<ipnet::IpNet>>,
+    trust_proxy: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    shutdown_timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    idle_shutdown: Option<Duration>,
+    qr: Option<bool>,
+    public_ip: Option<String>,
+    not_found_page: Option<String>,
+    health_check_dir: Option<bool>,
+    enable_metrics: Option<bool>,
+    show_hidden: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    rate_limit: Option<u64>,
+    open: Option<bool>,
+    auto_port: Option<bool>,
+    auto_port_tries: Option<u16>,
+    log_format: Option<String>,
+    access_log: Option<String>,
+    syslog: Option<String>,
+    no_stdout_log: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    access_log_max_size: Option<u64>,
+    compress: Option<bool>,
+    precompressed: Option<bool>,
+    compress_level: Option<u32>,
+    blocking_threads: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    chunk_size: Option<u64>,
+    cache_control: Option<String>,
+    read_only: Option<bool>,
+    cors_origin: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_methods")]
+    allowed_methods: Option<Vec<String>>,
+    enable_delete: Option<bool>,
+    enable_mkdir: Option<bool>,
+    follow_symlinks: Option<bool>,
+    unix_socket: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_mode")]
+    socket_mode: Option<u32>,
+    ipv6_only: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_headers")]
+    header: Option<Vec<(HeaderName, HeaderValue)>>,
+    search_max_results: Option<usize>,
+    page_size: Option<usize>,
+    listing_max_entries: Option<usize>,
+    lang: Option<String>,
+    max_connections: Option<usize>,
+    max_per_ip: Option<usize>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    request_timeout: Option<Duration>,
+    daemon: Option<bool>,
+    pid_file: Option<String>,
+    thumbnails: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    preview_max_size: Option<u64>,
+    share_secret: Option<String>,
+    favicon: Option<String>,
+    allowed_referer: Option<Vec<String>>,
+    allow_empty_referer: Option<bool>,
+    hsts: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    hsts_max_age: Option<Duration>,
+    show_dir_size: Option<bool>,
+    dir_size_max_depth: Option<usize>,
+    download_stats_file: Option<String>,
+    force_download: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_mimes")]
+    mime: Option<Vec<(String, HeaderValue)>>,
+    mime_db: Option<String>,
+    text_charset: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_ext_charsets")]
+    ext_charset: Option<Vec<(String, String)>>,
+    client_ca: Option<String>,
+    enable_watch: Option<bool>,
+    http2: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    keep_alive: Option<Duration>,
+    backlog: Option<i32>,
+    enable_rename: Option<bool>,
+    default_sort: Option<String>,
+    default_order: Option<String>,
+    ui_dir: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    max_file_age: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    cleanup_interval: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_globs")]
+    keep_glob: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_cidrs")]
+    trusted_proxy: Option<Vec<ipnet::IpNet>>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    total_rate_limit: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_globs")]
+    include_glob: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_globs")]
+    exclude_glob: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_extensions")]
+    deny_ext: Option<Vec<String>>,
+    max_depth: Option<usize>,
+    serve_index: Option<bool>,
+    index_file: Option<String>,
+    log_sample: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    slow_request_threshold: Option<Duration>,
+    recreate_dir: Option<bool>,
+    allow_unsafe_dir: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    max_upload_size: Option<u64>,
+    no_listing: Option<bool>,
+    no_icons: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    min_free_space: Option<u64>,
+    title: Option<String>,
+    banner: Option<String>,
+    csp: Option<String>,
+    base_path: Option<String>,
+    zip_compression: Option<String>,
+    tls_min_version: Option<String>,
+    tls13_only: Option<bool>,
+}
+
+// 列表类配置项与命令行格式相同，逐项使用命令行的解析函数
+fn deserialize_list<'de, D, T>(deserializer: D, parse: fn(&str) -> Result<T, String>) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let values: Vec<String> = Vec::deserialize(deserializer)?;
+    values
+        .iter()
+        .map(|value| parse(value).map_err(serde::de::Error::custom))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+// 如 mount = ["/docs=/srv/docs", "/private=/srv/private:user:pass"]
+fn deserialize_mounts<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<MountSpec>>, D::Error> {
+    deserialize_list(deserializer, parse_mount)
+}
+
+// 如 allow = ["192.168.0.0/16"]
+fn deserialize_cidrs<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<ipnet::IpNet>>, D::Error> {
+    deserialize_list(deserializer, parse_cidr)
+}
+
+// 如 header = ["X-Frame-Options: DENY"]
+fn deserialize_headers<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<(HeaderName, HeaderValue)>>, D::Error> {
+    deserialize_list(deserializer, parse_header)
+}
+
+// 如 mime = ["log=text/plain"]
+fn deserialize_mimes<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<(String, HeaderValue)>>, D::Error> {
+    deserialize_list(deserializer, parse_mime)
+}
+
+// 如 ext_charset = ["txt=gbk"]
+fn deserialize_ext_charsets<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<(String, String)>>, D::Error> {
+    deserialize_list(deserializer, parse_ext_charset)
+}
+
+// 如 allowed_methods = ["GET", "HEAD", "POST"]
+fn deserialize_methods<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    deserialize_list(deserializer, parse_method)
+}
+
+// 如 keep_glob = ["*.keep"]
+fn deserialize_globs<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    deserialize_list(deserializer, parse_glob)
+}
+
+fn deserialize_extensions<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    deserialize_list(deserializer, parse_extension)
+}
+
+// 如 max_file_age = "7d"，整数按秒计算，如 shutdown_timeout = 30
+fn deserialize_duration<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Seconds(u64),
+        Text(String),
+    }
+    match Value::deserialize(deserializer)? {
+        Value::Seconds(seconds) => Ok(Some(Duration::from_secs(seconds))),
+        Value::Text(text) => parse_duration(&text).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+// 如 rate_limit = "5MB"，也可以直接写字节数
+fn deserialize_size<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+    match Size::deserialize(deserializer)? {
+        Size::Bytes(bytes) => Ok(Some(bytes)),
+        Size::Text(text) => parse_size(&text).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+// 如 socket_mode = "660"，与命令行一样按八进制解析
+fn deserialize_mode<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_mode(&value).map(Some).map_err(serde::de::Error::custom)
+}
+
+// 读取并解析 TOML 配置文件
+pub fn load(path: &str) -> Result<FileConfig, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| tf(Msg::ReadConfigFailed, &[&path, &e]))?;
+    let mut config: FileConfig = toml::from_str(&content).map_err(|e| tf(Msg::InvalidConfigFile, &[&path, &e]))?;
+
+    if let Some(ref level) = config.log_level
+        && !LOG_LEVELS.contains(&level.as_str())
+    {
+        return Err(tf(Msg::InvalidConfigChoice, &[&path, &"log_level", &level, &LOG_LEVELS.join(", ")]));
+    }
+    if let Some(ref format) = config.log_format
+        && !LOG_FORMATS.contains(&format.as_str())
+    {
+        return Err(tf(Msg::InvalidConfigChoice, &[&path, &"log_format", &format, &LOG_FORMATS.join(", ")]));
+    }
+    if let Some(ref sort) = config.default_sort
+        && !SORT_KEYS.contains(&sort.as_str())
+    {
+        return Err(tf(Msg::InvalidConfigChoice, &[&path, &"default_sort", &sort, &SORT_KEYS.join(", ")]));
+    }
+    if let Some(ref version) = config.tls_min_version
+        && !TLS_VERSIONS.contains(&version.as_str())
+    {
+        return Err(tf(Msg::InvalidConfigChoice, &[&path, &"tls_min_version", &version, &TLS_VERSIONS.join(", ")]));
+    }
+    if let Some(ref facility) = config.syslog
+        && !SYSLOG_FACILITIES.contains(&facility.as_str())
+    {
+        return Err(tf(Msg::InvalidConfigChoice, &[&path, &"syslog", &facility, &SYSLOG_FACILITIES.join(", ")]));
+    }
+    if let Some(ref compression) = config.zip_compression
+        && !ZIP_COMPRESSIONS.contains(&compression.as_str())
+    {
+        return Err(tf(Msg::InvalidConfigChoice, &[&path, &"zip_compression", &compression, &ZIP_COMPRESSIONS.join(", ")]));
+    }
+    if let Some(ref order) = config.default_order
+        && !SORT_ORDERS.contains(&order.as_str())
+    {
+        return Err(tf(Msg::InvalidConfigChoice, &[&path, &"default_order", &order, &SORT_ORDERS.join(", ")]));
+    }
+    if let Some(ref name) = config.index_file {
+        parse_index_file(name).map_err(|e| tf(Msg::InvalidConfigValue, &[&path, &"index_file", &e]))?;
+    }
+    if let Some(ref base_path) = config.base_path {
+        config.base_path = Some(parse_base_path(base_path).map_err(|e| tf(Msg::InvalidConfigValue, &[&path, &"base_path", &e]))?);
+    }
+    if let Some(ref csp) = config.csp {
+        parse_csp(csp).map_err(|e| tf(Msg::InvalidConfigValue, &[&path, &"csp", &e]))?;
+    }
+    if let Some(size) = config.chunk_size {
+        check_chunk_size(size).map_err(|e| tf(Msg::InvalidConfigValue, &[&path, &"chunk_size", &e]))?;
+    }
+
+    if let Some(ref charset) = config.text_charset {
+        config.text_charset = Some(parse_charset(charset).map_err(|e| tf(Msg::InvalidConfigValue, &[&path, &"text_charset", &e]))?);
+    }
+
+    if let Some(ref lang) = config.lang
+        && !LANGS.contains(&lang.as_str())
+    {
+        return Err(tf(Msg::InvalidConfigChoice, &[&path, &"lang", &lang, &LANGS.join(", ")]));
+    }
+
+    Ok(config)
+}
+
+// 环境变量名的前缀，其后为大写的参数名，- 换成 _，如 --file-dir 对应 FILESHARE_FILE_DIR
+pub const ENV_PREFIX: &str = "FILESHARE_";
+
+// 为每个参数加上对应的环境变量；开关型参数接受 1/0、true/false(以及 yes/no、on/off)
+pub fn with_env(command: Command) -> Command {
+    command.mut_args(|arg| {
+        let id = arg.get_id().as_str();
+        if matches!(id, "help" | "version") {
+            return arg;
+        }
+        let name = format!("{}{}", ENV_PREFIX, id.to_ascii_uppercase());
+        match arg.get_action() {
+            ArgAction::SetTrue => arg.env(name).value_parser(BoolishValueParser::new()),
+            _ => arg.env(name),
+        }
+    })
+}
+
+// 值是否由命令行或环境变量显式指定，这些值不会被配置文件覆盖
+pub fn is_explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable))
+}
+
+// 合并配置：优先级依次为命令行、环境变量、配置文件、内置默认值
+pub fn merge(args: &mut Args, matches: &ArgMatches, config: FileConfig) {
+    macro_rules! merge_fields {
+        ($($field:ident),* $(,)?) => {
+            $(
+                if let Some(value) = config.$field
+                    && !is_explicit(matches, stringify!($field))
+                {
+                    args.$field = value.into();
+                }
+            )*
+        };
+    }
+
+    merge_fields!(
+        file_dir,
+        url_path,
+        log_level,
+        quiet,
+        verbose,
+        no_panic_hook,
+        port,
+        host,
+        worker,
+        tls_cert,
+        tls_key,
+        username,
+        password,
+        auth_command,
+        mount,
+        enable_upload,
+        enable_zip,
+        allow,
+        deny,
+        trust_proxy,
+        shutdown_timeout,
+        idle_shutdown,
+        qr,
+        public_ip,
+        not_found_page,
+        health_check_dir,
+        enable_metrics,
+        show_hidden,
+        rate_limit,
+        open,
+        auto_port,
+        auto_port_tries,
+        log_format,
+        access_log,
+        syslog,
+        no_stdout_log,
+        access_log_max_size,
+        compress,
+        precompressed,
+        compress_level,
+        blocking_threads,
+        chunk_size,
+        cache_control,
+        read_only,
+        cors_origin,
+        allowed_methods,
+        enable_delete,
+        enable_mkdir,
+        follow_symlinks,
+        unix_socket,
+        socket_mode,
+        ipv6_only,
+        header,
+        search_max_results,
+        page_size,
+        listing_max_entries,
+        lang,
+        max_connections,
+        max_per_ip,
+        request_timeout,
+        daemon,
+        pid_file,
+        thumbnails,
+        preview_max_size,
+        share_secret,
+        favicon,
+        allowed_referer,
+        allow_empty_referer,
+        hsts,
+        hsts_max_age,
+        show_dir_size,
+        dir_size_max_depth,
+        download_stats_file,
+        force_download,
+        mime,
+        mime_db,
+        text_charset,
+        ext_charset,
+        client_ca,
+        enable_watch,
+        http2,
+        keep_alive,
+        backlog,
+        enable_rename,
+        default_sort,
+        default_order,
+        ui_dir,
+        max_file_age,
+        cleanup_interval,
+        keep_glob,
+        trusted_proxy,
+        total_rate_limit,
+        include_glob,
+        exclude_glob,
+        deny_ext,
+        max_depth,
+        serve_index,
+        index_file,
+        log_sample,
+        slow_request_threshold,
+        recreate_dir,
+        allow_unsafe_dir,
+        max_upload_size,
+        no_listing,
+        no_icons,
+        min_free_space,
+        title,
+        banner,
+        csp,
+        base_path,
+        zip_compression,
+        tls_min_version,
+        tls13_only,
+    );
+}
+
+// --print-config 输出的最终配置，列表项写成与配置文件相同的字符串格式，另附绝对路径等推导出的值
+pub fn to_json(args: &Args, file_dir: &Path, mounts: &[(String, PathBuf)]) -> Result<String, String> {
+    let mut value = serde_json::to_value(args).map_err(|e| tf(Msg::SerializeConfigFailed, &[&e]))?;
+    value["file_dir_absolute"] = serde_json::json!(file_dir);
+    value["mounts_resolved"] = mounts
+        .iter()
+        .map(|(url_path, dir)| serde_json::json!({ "url_path": url_path, "dir": dir }))
+        .collect();
+    serde_json::to_string_pretty(&value).map_err(|e| tf(Msg::SerializeConfigFailed, &[&e]))
+}
+
+// 密码等敏感值只显示是否设置
+pub fn serialize_secret<S: Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.as_ref().map(|_| "***").serialize(serializer)
+}
+
+pub fn serialize_display<S: Serializer, T: Display>(values: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|value| value.to_string()))
+}
+
+// 挂载点的密码与 --password 一样不输出
+pub fn serialize_mounts<S: Serializer>(values: &[MountSpec], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|spec| match spec.credentials {
+        Some((ref user, _)) => format!("{}={}:{}:***", spec.url, spec.dir, user),
+        None => format!("{}={}", spec.url, spec.dir),
+    }))
+}
+
+pub fn serialize_headers<S: Serializer>(values: &[(HeaderName, HeaderValue)], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|(name, value)| format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes()))))
+}
+
+pub fn serialize_mimes<S: Serializer>(values: &[(String, HeaderValue)], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|(ext, value)| format!("{}={}", ext, String::from_utf8_lossy(value.as_bytes()))))
+}
+
+pub fn serialize_ext_charsets<S: Serializer>(values: &[(String, String)], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|(ext, charset)| format!("{}={}", ext, charset)))
+}
+
+// 与配置文件一样写成八进制字符串
+pub fn serialize_mode<S: Serializer>(value: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.map(|mode| format!("{:o}", mode)).serialize(serializer)
+}
+
+// 与命令行相同的格式，如 7days
+pub fn serialize_duration<S: Serializer, T: Into<Option<Duration>> + Copy>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    (*value).into().map(|duration| humantime::format_duration(duration).to_string()).serialize(serializer)
+}
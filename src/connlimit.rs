@@ -0,0 +1,131 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+
+use ntex::io::IoRef;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, t, tf};
+
+// 两次达到上限警告之间的最短间隔(毫秒)
+const WARN_INTERVAL_MS: u64 = 10_000;
+
+// 限制所有工作线程合计的连接数，连接上的第一个请求到达时计数，连接断开时释放；
+// 超出上限的新连接返回 503 并关闭
+#[derive(Clone)]
+pub struct ConnLimit {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    max: usize,
+    count: AtomicUsize,
+    // 上次警告后被拒绝的连接数
+    rejected: AtomicUsize,
+    // 上次警告的时间，自 start 起的毫秒数，0 表示尚未警告
+    last_warn: AtomicU64,
+    start: Instant,
+}
+
+impl ConnLimit {
+    // max 为 0 时不限制
+    pub fn new(max: usize) -> Self {
+        ConnLimit {
+            inner: Arc::new(Inner {
+                max,
+                count: AtomicUsize::new(0),
+                rejected: AtomicUsize::new(0),
+                last_warn: AtomicU64::new(0),
+                start: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl Inner {
+    // 占用一个连接名额，已达上限时返回 false
+    fn acquire(&self) -> bool {
+        self.count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| (count < self.max).then_some(count + 1))
+            .is_ok()
+    }
+
+    // 记录一次拒绝，距上次警告超过间隔时输出警告
+    fn reject(&self) {
+        self.rejected.fetch_add(1, Ordering::SeqCst);
+        // 时间整体后移一个间隔，使第一次达到上限时也能立即警告
+        let now = self.start.elapsed().as_millis() as u64 + WARN_INTERVAL_MS;
+        let last = self.last_warn.load(Ordering::SeqCst);
+        if now - last >= WARN_INTERVAL_MS
+            && self.last_warn.compare_exchange(last, now, Ordering::SeqCst, Ordering::SeqCst).is_ok()
+        {
+            let rejected = self.rejected.swap(0, Ordering::SeqCst);
+            log::warn!("{}", tf(Msg::MaxConnectionsReached, &[&self.max, &rejected]));
+        }
+    }
+}
+
+impl<S> Middleware<S> for ConnLimit {
+    type Service = ConnLimitMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ConnLimitMiddleware {
+            service,
+            inner: self.inner.clone(),
+            tracked: Rc::new(RefCell::new(HashSet::new())),
+        }
+    }
+}
+
+pub struct ConnLimitMiddleware<S> {
+    service: S,
+    inner: Arc<Inner>,
+    // 本工作线程中已计数的连接
+    tracked: Rc<RefCell<HashSet<IoRef>>>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ConnLimitMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if self.inner.max == 0 {
+            return ctx.call(&self.service, req).await;
+        }
+        let io = match req.io() {
+            Some(io) if !self.tracked.borrow().contains(io) => io.clone(),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        if !self.inner.acquire() {
+            self.inner.reject();
+            let response = HttpResponse::ServiceUnavailable()
+                .force_close()
+                .header("Retry-After", "1")
+                .body(t(Msg::TooManyConnections));
+            return Ok(req.into_response(response));
+        }
+
+        // 连接断开后释放名额
+        self.tracked.borrow_mut().insert(io.clone());
+        let inner = self.inner.clone();
+        let tracked = self.tracked.clone();
+        ntex::rt::spawn(async move {
+            io.on_disconnect().await;
+            tracked.borrow_mut().remove(&io);
+            inner.count.fetch_sub(1, Ordering::SeqCst);
+        });
+        ctx.call(&self.service, req).await
+    }
+}
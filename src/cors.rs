@@ -0,0 +1,125 @@
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+    ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD, HeaderMap, HeaderValue, ORIGIN, VARY,
+};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+// 预检请求允许的请求头，覆盖认证、上传和断点续传
+const ALLOW_HEADERS: &str = "Authorization, Content-Type, Range, If-None-Match, If-Modified-Since";
+
+// 允许跨域脚本读取的响应头
+const EXPOSE_HEADERS: &str = "Content-Length, Content-Range, Content-Disposition, ETag, Last-Modified";
+
+// 预检结果的缓存时间(秒)
+const MAX_AGE: &str = "600";
+
+// 跨域中间件，未设置 --cors-origin 时不输出任何 CORS 头
+#[derive(Clone)]
+pub struct Cors {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    origins: Vec<String>,
+    any: bool,
+    methods: HeaderValue,
+    credentials: bool,
+}
+
+impl Cors {
+    // methods 为允许的方法列表，credentials 表示启用了认证，需要浏览器携带凭据
+    pub fn new(origins: &[String], methods: &[&str], credentials: bool) -> Self {
+        let origins: Vec<String> = origins.iter().map(|origin| origin.trim_end_matches('/').to_string()).collect();
+        Cors {
+            inner: Rc::new(Inner {
+                any: origins.iter().any(|origin| origin == "*"),
+                origins,
+                methods: HeaderValue::from_str(&methods.join(", ")).unwrap_or_else(|_| HeaderValue::from_static("GET, HEAD")),
+                credentials,
+            }),
+        }
+    }
+}
+
+impl Inner {
+    // 返回应写入 Access-Control-Allow-Origin 的值，来源不在允许列表中时返回 None
+    fn allow_origin(&self, origin: &str) -> Option<HeaderValue> {
+        if self.any {
+            Some(HeaderValue::from_static("*"))
+        } else if self.origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin)) {
+            HeaderValue::from_str(origin).ok()
+        } else {
+            None
+        }
+    }
+
+    fn apply(&self, headers: &mut HeaderMap, origin: HeaderValue) {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if !self.any {
+            headers.append(VARY, HeaderValue::from_static("Origin"));
+        }
+        if self.credentials {
+            headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+    }
+}
+
+impl<S> Middleware<S> for Cors {
+    type Service = CorsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CorsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct CorsMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for CorsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let allow_origin = match req.headers().get(ORIGIN).and_then(|value| value.to_str().ok()) {
+            Some(origin) if !self.inner.origins.is_empty() => self.inner.allow_origin(origin),
+            _ => None,
+        };
+        let allow_origin = match allow_origin {
+            Some(allow_origin) => allow_origin,
+            None => return ctx.call(&self.service, req).await,
+        };
+
+        // 预检请求不带凭据，必须在认证之前直接应答
+        if *req.method() == Method::OPTIONS && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD) {
+            let mut res = HttpResponse::NoContent()
+                .header(ACCESS_CONTROL_ALLOW_METHODS, self.inner.methods.clone())
+                .header(ACCESS_CONTROL_ALLOW_HEADERS, ALLOW_HEADERS)
+                .header(ACCESS_CONTROL_MAX_AGE, MAX_AGE)
+                .finish();
+            self.inner.apply(res.headers_mut(), allow_origin);
+            return Ok(req.into_response(res));
+        }
+
+        let mut res = ctx.call(&self.service, req).await?;
+        let headers = res.headers_mut();
+        self.inner.apply(headers, allow_origin);
+        headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(EXPOSE_HEADERS));
+        Ok(res)
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(unix)]
+use std::fs::{File, OpenOptions};
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, FromRawFd};
+
+use crate::i18n::{Msg, t};
+#[cfg(unix)]
+use crate::i18n::tf;
+
+// 后台运行的子进程，绑定成功后通过管道通知仍在等待的父进程
+#[cfg(unix)]
+pub struct Daemon {
+    ready: File,
+}
+
+#[cfg(not(unix))]
+pub struct Daemon;
+
+// 转入后台运行：fork 出子进程并脱离终端，标准输出和标准错误写入 log_path，未指定时丢弃；
+// 父进程等到子进程绑定成功后以 0 退出，子进程提前退出时以 1 退出。
+// 必须在启动运行时之前调用
+#[cfg(unix)]
+pub fn daemonize(log_path: Option<&str>) -> Result<Daemon, String> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(tf(Msg::DaemonFailed, &[&std::io::Error::last_os_error()]));
+    }
+    let (mut reader, writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+    match unsafe { libc::fork() } {
+        -1 => Err(tf(Msg::DaemonFailed, &[&std::io::Error::last_os_error()])),
+        0 => {
+            drop(reader);
+            unsafe { libc::setsid() };
+            redirect_output(log_path)?;
+            Ok(Daemon { ready: writer })
+        }
+        pid => {
+            drop(writer);
+            let mut buf = [0; 1];
+            if matches!(reader.read(&mut buf), Ok(1)) {
+                println!("{}", tf(Msg::DaemonStarted, &[&pid]));
+                std::process::exit(0);
+            }
+            eprintln!("{}", t(Msg::DaemonExited));
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn daemonize(_log_path: Option<&str>) -> Result<Daemon, String> {
+    Err(t(Msg::DaemonUnsupported).to_string())
+}
+
+// 标准输入改为 /dev/null，标准输出和标准错误追加到日志文件
+#[cfg(unix)]
+fn redirect_output(log_path: Option<&str>) -> Result<(), String> {
+    let path = log_path.unwrap_or("/dev/null");
+    let null = File::open("/dev/null").map_err(|e| tf(Msg::DaemonFailed, &[&e]))?;
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| tf(Msg::OpenAccessLogFailed, &[&path, &e]))?;
+    unsafe {
+        libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO);
+        libc::dup2(log.as_raw_fd(), libc::STDOUT_FILENO);
+        libc::dup2(log.as_raw_fd(), libc::STDERR_FILENO);
+    }
+    Ok(())
+}
+
+impl Daemon {
+    // 通知父进程已绑定成功，父进程随即退出
+    #[cfg(unix)]
+    pub fn notify_ready(mut self) {
+        let _ = self.ready.write_all(b"1");
+    }
+
+    #[cfg(not(unix))]
+    pub fn notify_ready(self) {}
+}
+
+// 写入当前进程的 PID
+pub fn write_pid_file(path: &str) -> std::io::Result<()> {
+    std::fs::write(path, format!("{}\n", std::process::id()))
+}
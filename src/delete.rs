@@ -0,0 +1,119 @@
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::error::BlockingError;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use percent_encoding::percent_decode_str;
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 处理 DELETE {URL路径}/<文件>，删除共享目录中的文件；目录需要带 ?recursive=true
+#[derive(Clone)]
+pub struct Delete {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    enabled: bool,
+}
+
+impl Delete {
+    pub fn new(mounts: Vec<Mount>, enabled: bool) -> Self {
+        Delete {
+            inner: Rc::new(Inner { mounts, enabled }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Delete {
+    type Service = DeleteMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        DeleteMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct DeleteMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for DeleteMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let mount = match mount::find(&self.inner.mounts, req.path()) {
+            Some(mount) if *req.method() == Method::DELETE => mount,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        if !self.inner.enabled {
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::DeleteDisabled))));
+        }
+
+        let path = match mount.resolve(req.path()) {
+            Some(path) => path,
+            None => return Ok(req.into_response(HttpResponse::BadRequest().body(t(Msg::InvalidPath)))),
+        };
+        // 不允许删除挂载点本身
+        if path == mount.dir {
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::DeleteRoot))));
+        }
+
+        let name = percent_decode_str(req.path()).decode_utf8_lossy().into_owned();
+        let recursive = query::flag(req.query_string(), "recursive");
+        let response = match remove(path.clone(), recursive).await {
+            Ok(kind) => {
+                log::info!("{}", tf(if kind == "directory" { Msg::DirDeleted } else { Msg::FileDeleted }, &[&path.display()]));
+                HttpResponse::Ok().json(&serde_json::json!({ "deleted": name, "type": kind }))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HttpResponse::NotFound().body(tf(Msg::NotExists, &[&name])),
+            Err(e) if e.kind() == io::ErrorKind::IsADirectory => {
+                HttpResponse::Conflict().body(tf(Msg::DeleteDirNotRecursive, &[&name]))
+            }
+            Err(e) => {
+                log::error!("{}", tf(Msg::DeleteError, &[&path.display(), &e]));
+                HttpResponse::InternalServerError().body(tf(Msg::DeleteFailed, &[&name]))
+            }
+        };
+        Ok(req.into_response(response))
+    }
+}
+
+// 在线程池中删除，返回被删除的类型；符号链接只删除链接本身
+async fn remove(path: PathBuf, recursive: bool) -> io::Result<&'static str> {
+    let result = web::block(move || {
+        let metadata = std::fs::symlink_metadata(&path)?;
+        if !metadata.is_dir() {
+            std::fs::remove_file(&path)?;
+            Ok("file")
+        } else if recursive {
+            std::fs::remove_dir_all(&path)?;
+            Ok("directory")
+        } else {
+            Err(io::Error::from(io::ErrorKind::IsADirectory))
+        }
+    })
+    .await;
+    match result {
+        Ok(kind) => Ok(kind),
+        Err(BlockingError::Error(e)) => Err(e),
+        Err(BlockingError::Canceled) => Err(io::Error::other(t(Msg::DeleteInterrupted))),
+    }
+}
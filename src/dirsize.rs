@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// 目录大小的缓存时间，过期后下次显示时重新统计
+const TTL: Duration = Duration::from_secs(30);
+
+// 缓存的目录条数上限，超过后清空重新累积
+const CACHE_CAPACITY: usize = 1024;
+
+// 已统计的目录总大小，所有工作线程共享
+#[derive(Clone, Default)]
+pub struct DirSizes {
+    entries: Arc<Mutex<HashMap<PathBuf, (Instant, u64)>>>,
+}
+
+impl DirSizes {
+    pub fn new() -> Self {
+        DirSizes::default()
+    }
+
+    // 目录下所有文件的总大小，最多统计到 max_depth 层子目录，0 表示不限
+    pub fn get(&self, dir: &Path, max_depth: usize) -> u64 {
+        {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((at, size)) = entries.get(dir)
+                && at.elapsed() < TTL
+            {
+                return *size;
+            }
+        }
+
+        let size = walk(dir, 1, max_depth);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= CACHE_CAPACITY {
+            entries.clear();
+        }
+        entries.insert(dir.to_path_buf(), (Instant::now(), size));
+        size
+    }
+}
+
+// 递归累加文件大小；不进入符号链接指向的目录以免循环，无法读取的条目跳过
+fn walk(dir: &Path, depth: usize, max_depth: usize) -> u64 {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    let mut total = 0;
+    for entry in entries.flatten() {
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            if max_depth == 0 || depth < max_depth {
+                total += walk(&entry.path(), depth + 1, max_depth);
+            }
+        } else if metadata.is_symlink() {
+            total += std::fs::metadata(entry.path()).ok().filter(|target| target.is_file()).map_or(0, |target| target.len());
+        } else {
+            total += metadata.len();
+        }
+    }
+    total
+}
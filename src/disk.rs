@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use ntex::http::error::BlockingError;
+use ntex::web::types::State;
+use ntex::web::{self, HttpResponse};
+use serde::Serialize;
+
+use crate::i18n::{Msg, tf};
+
+// 磁盘空间接口路径，与 /metrics 一样由 --enable-metrics 启用
+pub const PATH: &str = "/diskinfo";
+
+// 文件系统的空间，单位为字节；available 为普通用户可用的部分，可能小于 total - used
+#[derive(Serialize)]
+pub struct Usage {
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+}
+
+// 路径所在文件系统的空间；不支持的平台或查询失败时返回 None
+#[cfg(unix)]
+pub fn usage(path: &Path) -> Option<Usage> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    #[allow(clippy::unnecessary_cast)]
+    let (frsize, blocks, free, avail) = (stat.f_frsize as u64, stat.f_blocks as u64, stat.f_bfree as u64, stat.f_bavail as u64);
+    Some(Usage {
+        total: blocks * frsize,
+        used: blocks.saturating_sub(free) * frsize,
+        available: avail * frsize,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn usage(_path: &Path) -> Option<Usage> {
+    None
+}
+
+pub fn available(path: &Path) -> Option<u64> {
+    usage(path).map(|usage| usage.available)
+}
+
+// 每个挂载点对应的目录
+pub struct DiskInfo {
+    pub mounts: Vec<(String, PathBuf)>,
+}
+
+#[derive(Serialize)]
+struct MountUsage {
+    url_path: String,
+    #[serde(flatten)]
+    usage: Usage,
+}
+
+// GET /diskinfo 返回每个挂载点所在文件系统的总空间、已用空间和可用空间
+pub async fn diskinfo(state: State<DiskInfo>) -> HttpResponse {
+    let mounts = state.mounts.clone();
+    let result = web::block(move || {
+        let mut usages = Vec::new();
+        for (url_path, dir) in mounts {
+            let usage = usage(&dir).ok_or_else(|| tf(Msg::DiskUsageUnavailable, &[&dir.display()]))?;
+            usages.push(MountUsage { url_path, usage });
+        }
+        Ok::<_, String>(usages)
+    })
+    .await;
+    match result {
+        Ok(usages) => HttpResponse::Ok().json(&usages),
+        Err(BlockingError::Error(reason)) => {
+            log::warn!("{}", reason);
+            HttpResponse::ServiceUnavailable().body(reason)
+        }
+        Err(BlockingError::Canceled) => HttpResponse::InternalServerError().finish(),
+    }
+}
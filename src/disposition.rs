@@ -0,0 +1,127 @@
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::header::{CONTENT_DISPOSITION, HeaderValue};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{WebRequest, WebResponse};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+
+use crate::mount::{self, Mount};
+use crate::query;
+
+// RFC 5987 中 attr-char 以外的字符都需要编码
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+// 生成 Content-Disposition，filename 为 ASCII 回退名，filename* 为 RFC 5987 编码的原文件名
+pub fn header(kind: &str, name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' { c } else { '_' })
+        .collect();
+    format!("{}; filename=\"{}\"; filename*=UTF-8''{}", kind, fallback, utf8_percent_encode(name, ATTR_CHAR))
+}
+
+pub fn attachment(name: &str) -> String {
+    header("attachment", name)
+}
+
+// 文件默认在浏览器中直接打开，?download=1 改为下载；指定 --force-download 时默认下载，?download=0 改为直接打开
+#[derive(Clone)]
+pub struct Disposition {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    force_download: bool,
+}
+
+impl Disposition {
+    pub fn new(mounts: Vec<Mount>, force_download: bool) -> Self {
+        Disposition {
+            inner: Rc::new(Inner { mounts, force_download }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Disposition {
+    type Service = DispositionMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        DispositionMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct DispositionMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for DispositionMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let attachment = match query::get(req.query_string(), "download").as_deref() {
+            Some("1" | "true") => true,
+            Some("0" | "false") => false,
+            _ => self.inner.force_download,
+        };
+        // 默认直接打开时保持 ntex-files 的行为，不加响应头
+        if !attachment && !self.inner.force_download || !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return ctx.call(&self.service, req).await;
+        }
+        let name = mount::find(&self.inner.mounts, req.path())
+            .and_then(|mount| mount.resolve(req.path()))
+            .filter(|path| path.is_file())
+            .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()));
+        let name = match name {
+            Some(name) => name,
+            None => return ctx.call(&self.service, req).await,
+        };
+
+        let mut res = ctx.call(&self.service, req).await?;
+        if res.status().is_success()
+            && let Ok(value) = HeaderValue::from_str(&header(if attachment { "attachment" } else { "inline" }, &name))
+        {
+            res.headers_mut().insert(CONTENT_DISPOSITION, value);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_name_is_rfc5987_encoded() {
+        assert_eq!(
+            attachment("报告 v1.pdf"),
+            "attachment; filename=\"__ v1.pdf\"; filename*=UTF-8''%E6%8A%A5%E5%91%8A%20v1.pdf"
+        );
+        assert_eq!(header("inline", "a\"b.txt"), "inline; filename=\"a_b.txt\"; filename*=UTF-8''a%22b.txt");
+    }
+}
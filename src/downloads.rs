@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::http::header::CONTENT_RANGE;
+use ntex::http::{Method, StatusCode};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{WebRequest, WebResponse};
+use percent_encoding::percent_decode_str;
+
+use crate::i18n::{Msg, tf};
+use crate::mount::{self, Mount};
+
+// 每个文件的下载次数，以挂载点URL路径加文件相对路径为键，所有工作线程共享
+#[derive(Clone, Default)]
+pub struct Downloads {
+    counts: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl Downloads {
+    // 从 --download-stats-file 读取上次保存的计数，文件不存在时从零开始
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Downloads::default()),
+        };
+        let counts = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| tf(Msg::InvalidDownloadStatsFile, &[&path, &e]))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(tf(Msg::ReadDownloadStatsFailed, &[&path, &e])),
+        };
+        Ok(Downloads {
+            counts: Arc::new(Mutex::new(counts)),
+        })
+    }
+
+    // 关闭时写回文件，先写临时文件再改名，避免中途退出留下不完整的 JSON
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let json = {
+            let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+            serde_json::to_vec_pretty(&*counts).map_err(std::io::Error::other)?
+        };
+        let tmp = format!("{}.tmp", path);
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    pub fn get(&self, key: &str) -> u64 {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner()).get(key).copied().unwrap_or(0)
+    }
+
+    fn increment(&self, key: String) {
+        *self.counts.lock().unwrap_or_else(|e| e.into_inner()).entry(key).or_default() += 1;
+    }
+}
+
+// 计数键：挂载点URL路径加上解码后的相对路径，如 /download/files/release/app.zip；
+// 多余的斜杠不影响结果，同一个文件不同写法的请求计入同一个键
+pub fn key(mount: &Mount, path: &str) -> Option<String> {
+    let rest = mount.strip(path)?;
+    let mut key = mount.url_path.trim_end_matches('/').to_string();
+    for segment in rest.split('/').filter(|s| !s.is_empty()) {
+        key.push('/');
+        key.push_str(&percent_decode_str(segment).decode_utf8_lossy());
+    }
+    Some(key)
+}
+
+// 响应是否包含文件的最后一个字节：完整响应总是包含，范围响应看 Content-Range 的结束位置。
+// 断点续传和多线程分段下载的各个范围中只有一个会到达文件末尾，因此整个下载只计一次
+fn reaches_end(res: &WebResponse) -> bool {
+    match res.status() {
+        StatusCode::OK => true,
+        StatusCode::PARTIAL_CONTENT => res
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+                let (_, end) = range.split_once('-')?;
+                Some(end.parse::<u64>().ok()? + 1 == total.parse::<u64>().ok()?)
+            })
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+// 响应体发送完毕时计数，客户端中途断开的下载不计入
+struct CountedBody {
+    body: ResponseBody<Body>,
+    downloads: Downloads,
+    key: Option<String>,
+}
+
+impl MessageBody for CountedBody {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        let chunk = ready!(self.body.poll_next_chunk(cx));
+        if chunk.is_none()
+            && let Some(key) = self.key.take()
+        {
+            self.downloads.increment(key);
+        }
+        Poll::Ready(chunk)
+    }
+}
+
+// 统计文件下载次数，只统计 GET 请求，目录列表以及预览、缩略图等由外层中间件返回的响应不计入
+#[derive(Clone)]
+pub struct DownloadCounter {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    downloads: Downloads,
+}
+
+impl DownloadCounter {
+    pub fn new(mounts: Vec<Mount>, downloads: Downloads) -> Self {
+        DownloadCounter {
+            inner: Rc::new(Inner { mounts, downloads }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for DownloadCounter {
+    type Service = DownloadCounterMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        DownloadCounterMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct DownloadCounterMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for DownloadCounterMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let key = if *req.method() == Method::GET {
+            mount::find(&self.inner.mounts, req.path())
+                .filter(|mount| mount.resolve(req.path()).is_some_and(|path| path.is_file()))
+                .and_then(|mount| key(mount, req.path()))
+        } else {
+            None
+        };
+        let key = match key {
+            Some(key) => key,
+            None => return ctx.call(&self.service, req).await,
+        };
+
+        let res = ctx.call(&self.service, req).await?;
+        if !reaches_end(&res) {
+            return Ok(res);
+        }
+        let downloads = self.inner.downloads.clone();
+        Ok(res.map_body(move |_, body| {
+            ResponseBody::Other(Body::from_message(CountedBody {
+                body,
+                downloads,
+                key: Some(key),
+            }))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::header::RANGE;
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn ranged_download_counts_once() {
+        let dir = TempDir::new();
+        dir.write("release/app.bin", vec![7u8; 1000]);
+        let mount = dir.mount("/download/files");
+        let downloads = Downloads::default();
+        let app = test::init_service(
+            web::App::new()
+                .wrap(DownloadCounter::new(vec![mount.clone()], downloads.clone()))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+        let fetch = |range: Option<&str>| {
+            let req = test::TestRequest::with_uri("/download/files/release/app.bin");
+            match range {
+                Some(range) => req.header(RANGE, range).to_request(),
+                None => req.to_request(),
+            }
+        };
+
+        test::read_response(&app, fetch(None)).await;
+        assert_eq!(downloads.get("/download/files/release/app.bin"), 1);
+
+        for range in ["bytes=0-499", "bytes=500-799", "bytes=800-"] {
+            test::read_response(&app, fetch(Some(range))).await;
+        }
+        assert_eq!(downloads.get("/download/files/release/app.bin"), 2);
+
+        let listing = test::read_response(&app, test::TestRequest::with_uri("/download/files/release/").to_request()).await;
+        assert_eq!(downloads.get("/download/files/release"), 0);
+        assert!(String::from_utf8_lossy(&listing).contains("<td class=\"downloads\">-</td>"));
+    }
+}
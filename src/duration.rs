@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use crate::i18n::{Msg, tf};
+
+// 解析表示时长的参数(--shutdown-timeout、--request-timeout、--keep-alive、--max-file-age 等)，
+// 支持 30s、5m、2h、1d 等单位，也可以组合如 1h30m；不带单位的整数按秒计算，与旧版本兼容
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+    humantime::parse_duration(value).map_err(|e| tf(Msg::InvalidDuration, &[&value, &e]))
+}
+
+// 转换为 ntex 使用的秒数，超出 u16 范围时取最大值
+pub fn seconds(duration: Duration) -> ntex::time::Seconds {
+    ntex::time::Seconds(duration.as_secs().try_into().unwrap_or(u16::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_suffixes_and_plain_seconds() {
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert!(parse_duration("10x").unwrap_err().contains("10x"));
+        assert!(parse_duration("").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use globset::GlobSet;
+
+use crate::i18n::{Msg, tf};
+use crate::mount::Mount;
+
+// 后台定期删除修改时间早于 --max-file-age 的文件，目录和匹配 --keep-glob 的文件保留；
+// 隐藏的文件和目录(忽略文件、缩略图缓存、上传中的临时文件)不属于共享内容，也不删除
+pub struct Cleaner {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+struct Task {
+    dirs: Vec<PathBuf>,
+    max_age: Duration,
+    keep: GlobSet,
+}
+
+impl Cleaner {
+    // 启动后立即扫描一次，之后每隔 interval 扫描一次
+    pub fn start(mounts: &[Mount], max_age: Duration, interval: Duration, keep: GlobSet) -> Self {
+        let task = Task {
+            dirs: mounts.iter().map(|mount| mount.dir.clone()).collect(),
+            max_age,
+            keep,
+        };
+        let (stop, rx) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            loop {
+                task.run();
+                match rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => return,
+                }
+            }
+        });
+        Cleaner { stop, thread }
+    }
+
+    // 关闭时通知线程退出并等待正在进行的扫描结束
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+    }
+}
+
+impl Task {
+    fn run(&self) {
+        let deadline = match SystemTime::now().checked_sub(self.max_age) {
+            Some(deadline) => deadline,
+            None => return,
+        };
+        for dir in &self.dirs {
+            self.scan(dir, dir, deadline);
+        }
+    }
+
+    // 不跟随符号链接，避免删除共享目录之外的文件
+    fn scan(&self, root: &Path, dir: &Path, deadline: SystemTime) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("{}", tf(Msg::ExpiryScanFailed, &[&dir.display(), &e]));
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            let metadata = match std::fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                self.scan(root, &path, deadline);
+                continue;
+            }
+            if path.strip_prefix(root).is_ok_and(|relative| self.keep.is_match(relative)) {
+                continue;
+            }
+            if !metadata.modified().is_ok_and(|modified| modified < deadline) {
+                continue;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => log::info!("{}", tf(Msg::ExpiredFileDeleted, &[&path.display()])),
+                Err(e) => log::warn!("{}", tf(Msg::DeleteExpiredFileFailed, &[&path.display(), &e])),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glob::IGNORE_FILE;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn keeps_hidden_files_and_directories() {
+        let dir = TempDir::new();
+        let old = dir.write("old.txt", "a");
+        let nested = dir.write("sub/old.txt", "b");
+        let hidden = [IGNORE_FILE, ".thumbs/a.jpg", ".big.iso.part", ".upload-1.0.tmp"].map(|name| dir.write(name, "c"));
+        let task = Task {
+            dirs: vec![dir.path().to_path_buf()],
+            max_age: Duration::ZERO,
+            keep: GlobSet::empty(),
+        };
+        std::thread::sleep(Duration::from_millis(10));
+
+        task.run();
+        assert!(!old.exists() && !nested.exists());
+        assert!(hidden.iter().all(|path| path.exists()));
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use ntex::http::header::CACHE_CONTROL;
+use ntex::util::Bytes;
+use ntex::web::HttpResponse;
+use ntex::web::types::State;
+
+use crate::i18n::{Msg, tf};
+
+// 网站图标路径，不受挂载点和认证影响
+pub const PATH: &str = "/favicon.ico";
+
+// 内置的网站图标
+const DEFAULT_ICON: &[u8] = include_bytes!("assets/favicon.ico");
+
+// 启动时加载的网站图标
+#[derive(Clone)]
+pub struct Favicon {
+    content: Bytes,
+    content_type: &'static str,
+}
+
+// 读取自定义图标，未指定时使用内置图标；内容类型按扩展名判断
+pub fn load(path: Option<&str>) -> Result<Favicon, String> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            return Ok(Favicon {
+                content: Bytes::from_static(DEFAULT_ICON),
+                content_type: "image/x-icon",
+            });
+        }
+    };
+    let content = std::fs::read(path).map_err(|e| tf(Msg::ReadFaviconFailed, &[&path, &e]))?;
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase());
+    let content_type = match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("svg") => "image/svg+xml",
+        Some("gif") => "image/gif",
+        _ => "image/x-icon",
+    };
+    Ok(Favicon {
+        content: Bytes::from(content),
+        content_type,
+    })
+}
+
+pub async fn favicon(icon: State<Favicon>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(icon.content_type)
+        .header(CACHE_CONTROL, "public, max-age=86400")
+        .body(icon.content.clone())
+}
@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::i18n::{Msg, tf};
+
+// 检查通配符格式，命令行和配置文件中的每一项都先经过这里
+pub fn parse_glob(value: &str) -> Result<String, String> {
+    Glob::new(value).map_err(|e| tf(Msg::InvalidGlob, &[&value, &e]))?;
+    Ok(value.to_string())
+}
+
+// 检查 --deny-ext，去掉开头的 . 并转为小写，可以包含多段如 tar.gz
+pub fn parse_extension(value: &str) -> Result<String, String> {
+    let ext = value.trim().trim_start_matches('.').to_lowercase();
+    if ext.is_empty() || ext.contains(['/', '\\', '\0']) {
+        return Err(tf(Msg::InvalidExtension, &[&value]));
+    }
+    Ok(ext)
+}
+
+// 把多个通配符合并成一个集合，* 也匹配 /，因此 *.txt 匹配任意层级下的 .txt 文件
+pub fn build(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).expect("通配符已在解析参数时检查"));
+    }
+    builder.build().expect("通配符已在解析参数时检查")
+}
+
+// 共享目录根下的忽略文件，每行一个通配符，写法与 .gitignore 类似
+pub const IGNORE_FILE: &str = ".fileshareignore";
+
+// 把忽略文件的内容转换成通配符：# 开头的行是注释；以 / 开头或中间含 / 的规则相对于共享目录根，
+// 其余规则匹配任意层级；结尾的 / 忽略；不支持 ! 取反，与无效的通配符一样记录日志后跳过
+fn parse_ignore(content: &str, path: &Path) -> Vec<String> {
+    let mut patterns = vec![IGNORE_FILE.to_string()];
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('!') {
+            log::warn!("{}", tf(Msg::IgnoreNegation, &[&path.display(), &line]));
+            continue;
+        }
+        let line = line.trim_end_matches('/');
+        let pattern = match line.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if line.contains('/') => line.to_string(),
+            None => format!("**/{}", line),
+        };
+        match Glob::new(&pattern) {
+            Ok(_) if !pattern.is_empty() => patterns.push(pattern),
+            Ok(_) => {}
+            Err(e) => log::warn!("{}", tf(Msg::InvalidIgnoreRule, &[&path.display(), &line, &e])),
+        }
+    }
+    patterns
+}
+
+// 从忽略文件读取的规则，所有工作线程共享，收到 SIGHUP 时重新读取；文件不存在时不忽略任何路径
+#[derive(Clone, Debug)]
+pub struct IgnoreFile {
+    path: PathBuf,
+    set: Arc<RwLock<Option<GlobSet>>>,
+}
+
+impl IgnoreFile {
+    pub fn load(path: PathBuf) -> Self {
+        let file = IgnoreFile {
+            path,
+            set: Arc::new(RwLock::new(None)),
+        };
+        file.reload();
+        file
+    }
+
+    // 读取失败时保留原有规则
+    pub fn reload(&self) {
+        let set = match std::fs::read_to_string(&self.path) {
+            Ok(content) => {
+                let patterns = parse_ignore(&content, &self.path);
+                log::info!("{}", tf(Msg::IgnoreRulesLoaded, &[&self.path.display(), &(patterns.len() - 1)]));
+                Some(build(&patterns))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("{}", tf(Msg::ReadIgnoreFileFailed, &[&self.path.display(), &e]));
+                return;
+            }
+        };
+        *self.set.write().unwrap_or_else(|e| e.into_inner()) = set;
+    }
+
+    fn is_match(&self, relative: &Path) -> bool {
+        let set = self.set.read().unwrap_or_else(|e| e.into_inner());
+        set.as_ref()
+            .is_some_and(|set| relative.ancestors().filter(|p| !p.as_os_str().is_empty()).any(|p| set.is_match(p)))
+    }
+}
+
+// 列表和下载的过滤规则(--include-glob/--exclude-glob、--deny-ext、--max-depth 和忽略文件)，通配符匹配相对于挂载点根目录的路径。
+// 超过最大深度的路径和扩展名被禁止的文件总是不可见；排除规则其次：路径本身或任一上级目录匹配排除规则或忽略规则时不可见；
+// 设置了包含规则时只有匹配的文件可见，目录总是可见，以便进入其中查找匹配的文件
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    include: Option<Arc<GlobSet>>,
+    exclude: Option<Arc<GlobSet>>,
+    ignore: Option<IgnoreFile>,
+    denied_extensions: Arc<Vec<String>>,
+    // 挂载点根目录以下可访问的最大层数，0 表示不限
+    max_depth: usize,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Self {
+        let set = |patterns: &[String]| (!patterns.is_empty()).then(|| Arc::new(build(patterns)));
+        PathFilter {
+            include: set(include),
+            exclude: set(exclude),
+            ignore: None,
+            denied_extensions: Arc::default(),
+            max_depth: 0,
+        }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // 相对路径 relative 之下还可以进入的层数，不限深度时返回 None
+    pub fn remaining_depth(&self, relative: &Path) -> Option<usize> {
+        (self.max_depth > 0).then(|| self.max_depth.saturating_sub(relative.components().count()))
+    }
+
+    // 禁止的扩展名，已由 parse_extension 转为小写
+    pub fn with_denied_extensions(mut self, extensions: &[String]) -> Self {
+        self.denied_extensions = Arc::new(extensions.to_vec());
+        self
+    }
+
+    // 按文件名结尾比较，不区分大小写，因此 tar.gz 这样的多段扩展名也能匹配
+    fn denies_extension(&self, relative: &Path) -> bool {
+        if self.denied_extensions.is_empty() {
+            return false;
+        }
+        let name = match relative.file_name() {
+            Some(name) => name.to_string_lossy().to_lowercase(),
+            None => return false,
+        };
+        self.denied_extensions
+            .iter()
+            .any(|ext| name.strip_suffix(ext.as_str()).is_some_and(|stem| stem.ends_with('.')))
+    }
+
+    // 同时应用共享目录下忽略文件中的规则
+    pub fn with_ignore_file(mut self, path: PathBuf) -> Self {
+        self.ignore = Some(IgnoreFile::load(path));
+        self
+    }
+
+    pub fn ignore_file(&self) -> Option<&IgnoreFile> {
+        self.ignore.as_ref()
+    }
+
+    pub fn allows(&self, relative: &Path, is_dir: bool) -> bool {
+        if self.max_depth > 0 && relative.components().count() > self.max_depth {
+            return false;
+        }
+        if !is_dir && self.denies_extension(relative) {
+            return false;
+        }
+        if let Some(ref exclude) = self.exclude
+            && relative.ancestors().filter(|p| !p.as_os_str().is_empty()).any(|p| exclude.is_match(p))
+        {
+            return false;
+        }
+        if self.ignore.as_ref().is_some_and(|ignore| ignore.is_match(relative)) {
+            return false;
+        }
+        match self.include {
+            Some(ref include) if !is_dir => include.is_match(relative),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let owned = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        PathFilter::new(&owned(include), &owned(exclude))
+    }
+
+    #[test]
+    fn include_exclude_precedence() {
+        let all = PathFilter::default();
+        assert!(all.allows(Path::new("a/b.bin"), false));
+
+        let txt = filter(&["*.txt"], &[]);
+        assert!(txt.allows(Path::new("a.txt"), false));
+        assert!(txt.allows(Path::new("docs/a.txt"), false));
+        assert!(!txt.allows(Path::new("a.bin"), false));
+        assert!(txt.allows(Path::new("docs"), true));
+
+        let secret = filter(&[], &["**/secret/*"]);
+        assert!(!secret.allows(Path::new("secret/a.txt"), false));
+        assert!(!secret.allows(Path::new("x/y/secret/a.txt"), false));
+        assert!(!secret.allows(Path::new("x/secret/deep/a.txt"), false));
+        assert!(secret.allows(Path::new("x/secret"), true));
+        assert!(secret.allows(Path::new("x/public/a.txt"), false));
+
+        let both = filter(&["*.txt"], &["**/secret/*", "drafts"]);
+        assert!(both.allows(Path::new("notes/a.txt"), false));
+        assert!(!both.allows(Path::new("notes/secret/a.txt"), false));
+        assert!(!both.allows(Path::new("drafts"), true));
+        assert!(!both.allows(Path::new("drafts/a.txt"), false));
+    }
+
+    #[test]
+    fn ignore_file_rules_and_reload() {
+        let dir = crate::testutil::TempDir::new();
+        dir.write(IGNORE_FILE, "# 注释\nnode_modules/\n/build\ndocs/*.tmp\n!keep.txt\n");
+        let filter = PathFilter::default().with_ignore_file(dir.path().join(IGNORE_FILE));
+        assert!(!filter.allows(Path::new(IGNORE_FILE), false));
+        assert!(!filter.allows(Path::new("node_modules"), true));
+        assert!(!filter.allows(Path::new("web/node_modules/a.js"), false));
+        assert!(!filter.allows(Path::new("build/out.bin"), false));
+        assert!(filter.allows(Path::new("web/build/out.bin"), false));
+        assert!(!filter.allows(Path::new("docs/a.tmp"), false));
+        assert!(filter.allows(Path::new("keep.txt"), false));
+
+        dir.write(IGNORE_FILE, "*.txt\n");
+        filter.ignore_file().unwrap().reload();
+        assert!(filter.allows(Path::new("node_modules"), true));
+        assert!(!filter.allows(Path::new("a/keep.txt"), false));
+
+        std::fs::remove_file(dir.path().join(IGNORE_FILE)).unwrap();
+        filter.ignore_file().unwrap().reload();
+        assert!(filter.allows(Path::new("a/keep.txt"), false));
+    }
+}
@@ -0,0 +1,126 @@
+use std::error::Error;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use ntex::http::Method;
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{WebRequest, WebResponse};
+
+// HEAD 请求的响应头与 GET 相同，但不读取响应体。ntex 对 HEAD 仍会读取响应体的第一块再丢弃，
+// 文件会被读取 64KB，开启压缩时还会压缩，访问日志和指标也记下了没有发送的字节数。
+// 需注册在 Compress 和 Throttle 之外，访问日志和指标之内
+#[derive(Clone, Default)]
+pub struct HeadBody;
+
+// 保留原响应体的长度，ntex 据此写出与 GET 相同的 Content-Length 或 Transfer-Encoding
+struct EmptyBody {
+    size: BodySize,
+}
+
+impl MessageBody for EmptyBody {
+    fn size(&self) -> BodySize {
+        self.size
+    }
+
+    fn poll_next_chunk(&mut self, _: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        Poll::Ready(None)
+    }
+}
+
+impl<S> Middleware<S> for HeadBody {
+    type Service = HeadBodyMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        HeadBodyMiddleware { service }
+    }
+}
+
+pub struct HeadBodyMiddleware<S> {
+    service: S,
+}
+
+impl<S, E> Service<WebRequest<E>> for HeadBodyMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if *req.method() != Method::HEAD {
+            return ctx.call(&self.service, req).await;
+        }
+        let res = ctx.call(&self.service, req).await?;
+        Ok(res.map_body(|_, body| {
+            let size = body.size();
+            ResponseBody::Other(Body::from_message(EmptyBody { size }))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::http::header::{
+        ACCEPT_ENCODING, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_RANGE, CONTENT_TYPE, ETAG, LAST_MODIFIED, RANGE,
+    };
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::cache::{self, CacheControl};
+    use crate::compress::Compress;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn head_matches_get_without_body() {
+        let dir = TempDir::new();
+        dir.write("notes.txt", "0123456789".repeat(1000));
+        dir.write("docs/a.txt", "a");
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(
+            web::App::new()
+                .wrap(CacheControl::new(Some(cache::parse("max-age=60").unwrap()), vec![mount.clone()]))
+                .wrap(Compress::new(true, 6))
+                .wrap(HeadBody)
+                .service(testutil::files(&mount)),
+        )
+        .await;
+        let request = |method: Method, uri: &str, header: Option<(_, &str)>| {
+            let mut req = test::TestRequest::with_uri(uri).method(method);
+            if let Some((name, value)) = header {
+                req = req.header(name, value);
+            }
+            req.to_request()
+        };
+
+        let cases = [
+            ("/download/files/notes.txt", None, StatusCode::OK),
+            ("/download/files/notes.txt", Some((RANGE, "bytes=10-19")), StatusCode::PARTIAL_CONTENT),
+            ("/download/files/notes.txt", Some((ACCEPT_ENCODING, "gzip")), StatusCode::OK),
+            ("/download/files/docs/", None, StatusCode::OK),
+        ];
+        for (uri, header, status) in cases {
+            let get = test::call_service(&app, request(Method::GET, uri, header.clone())).await;
+            let head = test::call_service(&app, request(Method::HEAD, uri, header)).await;
+            assert_eq!(head.status(), status, "{}", uri);
+            assert_eq!(get.status(), status, "{}", uri);
+            for name in [CONTENT_TYPE, ACCEPT_RANGES, CACHE_CONTROL, LAST_MODIFIED, ETAG, CONTENT_RANGE, CONTENT_ENCODING] {
+                assert_eq!(head.headers().get(&name), get.headers().get(&name), "{} {}", uri, name);
+            }
+            assert_eq!(head.response().body().size(), get.response().body().size(), "{}", uri);
+            assert!(test::read_body(head).await.is_empty(), "{}", uri);
+        }
+
+        let head = test::call_service(&app, request(Method::HEAD, "/download/files/notes.txt", None)).await;
+        assert_eq!(head.response().body().size(), BodySize::Sized(10000));
+        assert_eq!(head.headers().get(ACCEPT_RANGES).unwrap(), "bytes");
+        assert!(head.headers().get(LAST_MODIFIED).is_some());
+        assert!(head.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap().starts_with("text/plain"));
+    }
+}
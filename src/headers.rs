@@ -0,0 +1,98 @@
+use std::rc::Rc;
+
+use ntex::http::header::{HeaderName, HeaderValue};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{WebRequest, WebResponse};
+
+use crate::i18n::{Msg, tf};
+
+// 逐跳头和影响响应体长度的头由服务器自己管理，不允许自定义
+const FORBIDDEN: [&str; 10] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+];
+
+// 解析 "Name: Value" 格式的响应头
+pub fn parse_header(value: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, header_value) = value
+        .split_once(':')
+        .ok_or_else(|| tf(Msg::InvalidHeader, &[&value]))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| tf(Msg::InvalidHeaderName, &[&value]))?;
+    if FORBIDDEN.contains(&name.as_str()) {
+        return Err(tf(Msg::HeaderNotAllowed, &[&name]));
+    }
+    let header_value = HeaderValue::from_str(header_value.trim()).map_err(|_| tf(Msg::InvalidHeaderValue, &[&value]))?;
+    Ok((name, header_value))
+}
+
+// 为所有响应加上 --header 指定的响应头；压缩、缓存等功能已经设置的同名头保持不变
+#[derive(Clone)]
+pub struct CustomHeaders {
+    headers: Rc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl CustomHeaders {
+    pub fn new(headers: Vec<(HeaderName, HeaderValue)>) -> Self {
+        CustomHeaders {
+            headers: Rc::new(headers),
+        }
+    }
+}
+
+impl<S> Middleware<S> for CustomHeaders {
+    type Service = CustomHeadersMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CustomHeadersMiddleware {
+            service,
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+pub struct CustomHeadersMiddleware<S> {
+    service: S,
+    headers: Rc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl<S, E> Service<WebRequest<E>> for CustomHeadersMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let mut res = ctx.call(&self.service, req).await?;
+        if self.headers.is_empty() {
+            return Ok(res);
+        }
+
+        // 先记下响应原有的头，同一名称重复指定时全部追加
+        let existing: Vec<HeaderName> = self
+            .headers
+            .iter()
+            .filter(|(name, _)| res.headers().contains_key(name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let headers = res.headers_mut();
+        for (name, value) in self.headers.iter() {
+            if !existing.contains(name) {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+        Ok(res)
+    }
+}
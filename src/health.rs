@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use ntex::http::error::BlockingError;
+use ntex::web::types::State;
+use ntex::web::{self, HttpResponse};
+
+use crate::i18n::{Msg, t, tf};
+
+// 健康检查接口路径，不受挂载点和认证影响
+pub const PATH: &str = "/healthz";
+
+pub struct HealthCheck {
+    pub dirs: Vec<PathBuf>,
+    pub check_dir: bool,
+}
+
+// 默认只返回 ok；启用目录检查时确认所有共享目录仍可读取
+pub async fn healthz(state: State<HealthCheck>) -> HttpResponse {
+    if !state.check_dir {
+        return HttpResponse::Ok().body("ok");
+    }
+
+    let dirs = state.dirs.clone();
+    let result = web::block(move || {
+        for dir in &dirs {
+            std::fs::read_dir(dir).map_err(|e| tf(Msg::DirUnreadable, &[&dir.display(), &e]))?;
+        }
+        Ok::<_, String>(())
+    })
+    .await;
+
+    let reason = match result {
+        Ok(()) => return HttpResponse::Ok().body("ok"),
+        Err(BlockingError::Error(reason)) => reason,
+        Err(BlockingError::Canceled) => t(Msg::HealthCheckInterrupted).to_string(),
+    };
+    log::warn!("{}", tf(Msg::HealthCheckFailed, &[&reason]));
+    HttpResponse::ServiceUnavailable().body(reason)
+}
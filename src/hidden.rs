@@ -0,0 +1,103 @@
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{WebRequest, WebResponse};
+use ntex_files::{Directory, NamedFile};
+use percent_encoding::percent_decode_str;
+
+use crate::dirsize::DirSizes;
+use crate::downloads::Downloads;
+use crate::listing;
+use crate::mount::{self, Mount};
+use crate::notfound::{self, NotFoundPage};
+
+// 按解码后的路径检查请求：访问隐藏文件或 .. 越界时返回 404。
+// ntex-files 在解码之前检查路径段，%2e 编码的 . 和 .. 会绕过它的检查，所以必须在这里先拦截
+#[derive(Clone)]
+pub struct HiddenPaths {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    not_found_page: NotFoundPage,
+    dir_sizes: DirSizes,
+    downloads: Downloads,
+}
+
+impl HiddenPaths {
+    pub fn new(mounts: Vec<Mount>, not_found_page: NotFoundPage, dir_sizes: DirSizes, downloads: Downloads) -> Self {
+        HiddenPaths {
+            inner: Rc::new(Inner {
+                mounts,
+                not_found_page,
+                dir_sizes,
+                downloads,
+            }),
+        }
+    }
+}
+
+// 路径中是否有 . 开头的段
+fn has_hidden_segment(path: &str) -> bool {
+    path.split('/')
+        .any(|segment| percent_decode_str(segment).decode_utf8_lossy().starts_with('.'))
+}
+
+impl<S> Middleware<S> for HiddenPaths {
+    type Service = HiddenPathsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        HiddenPathsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct HiddenPathsMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for HiddenPathsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let mount = match mount::find(&self.inner.mounts, req.path()) {
+            Some(mount) => mount,
+            None => return ctx.call(&self.service, req).await,
+        };
+        let path = match mount.resolve(req.path()) {
+            Some(path) => path,
+            None => return Ok(req.into_response(notfound::response(&self.inner.not_found_page))),
+        };
+        let serve_hidden = mount.show_hidden
+            && matches!(*req.method(), Method::GET | Method::HEAD)
+            && mount.strip(req.path()).is_some_and(has_hidden_segment);
+        if !serve_hidden {
+            return ctx.call(&self.service, req).await;
+        }
+
+        // ntex-files 拒绝 . 开头的路径段，开启 --show-hidden 时由这里直接提供隐藏文件
+        let (req, _) = req.into_parts();
+        if path.is_dir() {
+            if let Ok(res) = listing::render_html(&Directory::new(mount.dir.clone(), path), &req, mount, &self.inner.dir_sizes, &self.inner.downloads) {
+                return Ok(res);
+            }
+        } else if let Ok(file) = NamedFile::open(&path) {
+            let res = file.disable_content_disposition().into_response(&req);
+            return Ok(WebResponse::new(res, req));
+        }
+        Ok(WebResponse::new(notfound::response(&self.inner.not_found_page), req))
+    }
+}
@@ -0,0 +1,509 @@
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+// 支持的语言，auto 表示根据环境变量选择
+pub const LANGS: [&str; 3] = ["zh", "en", "auto"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lang {
+    Zh,
+    En,
+}
+
+impl Lang {
+    // auto 时依次查看 LC_ALL、LC_MESSAGES、LANG，都未设置时使用中文
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "en" => Lang::En,
+            "auto" => ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|name| std::env::var(name).ok())
+                .find(|value| !value.is_empty())
+                .map_or(Lang::Zh, |value| if value.starts_with("zh") { Lang::Zh } else { Lang::En }),
+            _ => Lang::Zh,
+        }
+    }
+}
+
+static CURRENT: OnceLock<Lang> = OnceLock::new();
+
+// 启动时设置一次，之后所有线程共用
+pub fn init(lang: Lang) {
+    let _ = CURRENT.set(lang);
+}
+
+pub fn current() -> Lang {
+    CURRENT.get().copied().unwrap_or(Lang::Zh)
+}
+
+// 文案表，每条依次为中文、英文；新增语言时在 Lang 中加一项并为每条文案补上译文
+macro_rules! messages {
+    ($($(#[$meta:meta])* $name:ident => $zh:literal, $en:literal;)*) => {
+        #[derive(Clone, Copy)]
+        pub enum Msg {
+            $($(#[$meta])* $name,)*
+        }
+
+        impl Msg {
+            fn text(self, lang: Lang) -> &'static str {
+                match (self, lang) {
+                    $(
+                        (Msg::$name, Lang::Zh) => $zh,
+                        (Msg::$name, Lang::En) => $en,
+                    )*
+                }
+            }
+        }
+    };
+}
+
+messages! {
+    // 运行参数
+    ArgsTitle => "运行参数:", "Options:";
+    FileDir => "文件目录", "File directory";
+    UrlPath => "URL路径", "URL path";
+    MountPoint => "挂载点", "Mount";
+    MountWithAuth => "{} -> {} (用户 {})", "{} -> {} (user {})";
+    LogLevel => "日志级别", "Log level";
+    Verbose => "详细模式", "Verbose";
+    PanicHook => "崩溃信息写入日志", "Log panics";
+    Panicked => "线程 {} 崩溃: {} ({})", "Thread {} panicked: {} ({})";
+    Port => "端口", "Port";
+    UnixSocket => "Unix套接字", "Unix socket";
+    SocketMode => "套接字权限", "Socket mode";
+    Host => "监听地址", "Listen address";
+    Ipv6Only => "仅IPv6", "IPv6 only";
+    Worker => "工作线程数", "Workers";
+    ChunkSize => "读取块大小", "Read chunk size";
+    BlockingThreads => "阻塞线程池", "Blocking threads";
+    BlockingThreadsValue => "每个工作线程最多 {} 个，合计最多 {} 个", "up to {} per worker, {} in total";
+    QuietWithVerbose => "--quiet 和 --verbose 不能同时使用", "--quiet and --verbose cannot be used together";
+    ZeroBlockingThreads => "--blocking-threads 至少为1", "--blocking-threads must be at least 1";
+    KeepAlive => "连接保持", "Keep-alive";
+    Backlog => "连接队列长度", "Listen backlog";
+    TlsCert => "TLS证书", "TLS certificate";
+    TlsKey => "TLS私钥", "TLS private key";
+    TlsMinVersion => "最低TLS版本", "Minimum TLS version";
+    ClientCa => "客户端CA证书", "Client CA";
+    Http2 => "HTTP/2", "HTTP/2";
+    Username => "用户名", "Username";
+    Password => "密码", "Password";
+    AuthCommand => "认证程序", "Auth command";
+    ConfigFile => "配置文件", "Config file";
+    CheckOnly => "仅检查配置", "Check only";
+    Upload => "文件上传", "Upload";
+    MaxUploadSize => "上传大小上限", "Max upload size";
+    MinFreeSpace => "最小剩余空间", "Min free space";
+    Delete => "文件删除", "Delete";
+    Mkdir => "新建目录", "Create directory";
+    Rename => "重命名", "Rename";
+    Zip => "目录打包下载", "Directory ZIP download";
+    ZipCompression => "打包压缩方式", "ZIP compression";
+    Watch => "目录变化推送", "Directory change notifications";
+    Allow => "允许地址段", "Allowed networks";
+    Deny => "禁止地址段", "Denied networks";
+    TrustedProxy => "受信任的代理", "Trusted proxies";
+    TrustProxy => "信任代理", "Trust proxy";
+    ShutdownTimeout => "关闭等待时间", "Shutdown timeout";
+    IdleShutdown => "空闲自动关闭", "Idle shutdown";
+    Qr => "二维码", "QR code";
+    PublicIp => "局域网访问地址的主机", "Public address";
+    NotFoundPage => "404页面", "404 page";
+    HealthCheckDir => "健康检查目录", "Health check directories";
+    Metrics => "指标接口", "Metrics endpoint";
+    ShowHidden => "显示隐藏文件", "Show hidden files";
+    FollowSymlinks => "跟随符号链接", "Follow symlinks";
+    RateLimit => "限速", "Rate limit";
+    TotalRateLimit => "总限速", "Total rate limit";
+    Open => "打开浏览器", "Open browser";
+    LogFormat => "访问日志格式", "Access log format";
+    AccessLog => "访问日志文件", "Access log file";
+    Syslog => "syslog", "Syslog";
+    StdoutLog => "终端日志", "Log to stdout";
+    SyslogFailed => "连接 syslog 失败，日志只输出到终端: {}", "Failed to connect to syslog, logging to stdout only: {}";
+    LogSample => "4xx日志采样", "4xx log sampling";
+    LogSampleValue => "每 {} 条记录一条", "1 in {}";
+    SlowRequestThreshold => "慢请求警告阈值", "Slow request threshold";
+    LogAll => "全部记录", "log all";
+    AccessLogMaxSize => "访问日志轮转大小", "Access log rotation size";
+    AutoPort => "自动选择端口", "Auto port";
+    CacheControl => "缓存控制", "Cache-Control";
+    ReadOnly => "只读模式", "Read-only mode";
+    CorsOrigin => "跨域来源", "CORS origins";
+    AllowedMethods => "允许的HTTP方法", "Allowed HTTP methods";
+    CustomHeader => "自定义响应头", "Custom header";
+    MaxFileAge => "过期文件清理", "Expire files";
+    MaxFileAgeValue => "超过 {} 删除，每 {} 检查一次", "older than {}, checked every {}";
+    KeepGlob => "不清理的文件", "Keep files";
+    IncludeGlob => "只显示的文件", "Include files";
+    ExcludeGlob => "排除的文件", "Exclude files";
+    DenyExt => "禁止的扩展名", "Denied extensions";
+    MaxDepth => "最大访问深度", "Max depth";
+    UiDir => "界面目录", "UI directory";
+    DefaultSort => "默认排序", "Default sort";
+    RecreateDir => "重新创建被删除的目录", "Recreate deleted directories";
+    AllowUnsafeDir => "允许共享系统目录", "Allow system directories";
+    Listing => "目录列表", "Directory listing";
+    Icons => "文件类型图标", "File type icons";
+    ServeIndex => "目录首页", "Index file";
+    ListingTitleArg => "列表标题", "Listing title";
+    Banner => "列表提示", "Listing banner";
+    BasePath => "外部路径前缀", "Base path";
+    Csp => "内容安全策略", "Content-Security-Policy";
+    PageSize => "每页条目数", "Entries per page";
+    ListingMaxEntries => "目录列表条目上限", "Max listing entries";
+    SearchMaxResults => "搜索结果上限", "Search result limit";
+    Compress => "响应压缩", "Compression";
+    Precompressed => "预压缩文件", "Precompressed files";
+    Language => "语言", "Language";
+    MaxConnections => "最大连接数", "Max connections";
+    MaxPerIp => "单IP并发请求数", "Max requests per IP";
+    RequestTimeout => "请求超时", "Request timeout";
+    Daemon => "后台运行", "Daemon";
+    PidFile => "PID文件", "PID file";
+    Thumbnails => "图片缩略图", "Thumbnails";
+    PreviewMaxSize => "预览大小上限", "Preview size limit";
+    ShareSecret => "分享链接密钥", "Share link secret";
+    Favicon => "网站图标", "Favicon";
+    AllowedReferer => "允许的Referer", "Allowed referers";
+    AllowEmptyReferer => "允许空Referer", "Allow empty referer";
+    Hsts => "HSTS", "HSTS";
+    ShowDirSize => "显示目录大小", "Show directory sizes";
+    DirSizeMaxDepth => "目录大小统计层数", "Directory size max depth";
+    DownloadStatsFile => "下载统计文件", "Download stats file";
+    ForceDownload => "强制下载", "Force download";
+    MimeType => "MIME类型映射", "MIME type mapping";
+    MimeDb => "MIME类型文件", "MIME types file";
+    TextCharset => "文本字符集", "Text charset";
+    ExtCharset => "字符集映射", "Charset mapping";
+
+    // 功能概览
+    Features => "功能概览: {}", "Features: {}";
+    FeatureTls => "HTTPS", "HTTPS";
+    FeatureAuth => "用户认证", "Authentication";
+    FeatureIpFilter => "IP过滤", "IP filter";
+    FeatureShareLinks => "分享链接", "Share links";
+
+    // 参数值
+    Enabled => "启用", "enabled";
+    Disabled => "未启用", "disabled";
+    Yes => "是", "yes";
+    No => "否", "no";
+    Unset => "未设置", "not set";
+    BuiltIn => "内置", "built-in";
+    Unlimited => "不限", "unlimited";
+    BytesPerSecond => "{} 字节/秒", "{} bytes/s";
+    Bytes => "{} 字节", "{} bytes";
+    NoRotate => "不轮转", "no rotation";
+    AutoPortTries => "是(最多尝试{}个)", "yes (up to {} ports)";
+    NonAscii => "(非ASCII)", "(non-ASCII)";
+    NoPaging => "不分页", "no paging";
+    CompressLevel => "启用(级别{})", "enabled (level {})";
+
+    // 启动和关闭日志
+    RuntimeFailed => "创建运行时失败: {}", "Failed to create the runtime: {}";
+    PortInUse => "端口 {} 已被占用，尝试端口 {}", "Port {} is in use, trying port {}";
+    BindUnixFailed => "绑定Unix套接字 {} 失败: {}", "Failed to bind Unix socket {}: {}";
+    #[cfg_attr(unix, allow(dead_code))]
+    UnixUnsupported => "当前系统不支持 --unix-socket", "--unix-socket is not supported on this platform";
+    NoCurrentDir => "无法获取当前目录，使用相对路径", "Cannot get the current directory, using the relative path";
+    DuplicateMount => "挂载点URL路径 {} 重复", "Mount URL path {} is used more than once";
+    DirMissing => "目录 {} 不存在，正在创建...", "Directory {} does not exist, creating it...";
+    CreateDirFailed => "创建目录 {} 失败: {} (当前目录: {})", "Failed to create directory {}: {} (current directory: {})";
+    SingleFile => "{} 是一个文件，只提供该文件的下载，目录列表、上传等目录功能不可用", "{} is a regular file; only that file is served and directory features such as listing and upload are unavailable";
+    NotADirectory => "{} 不是一个目录", "{} is not a directory";
+    DirUnreadable => "无法读取目录 {}: {}", "Cannot read directory {}: {}";
+    UnsafeDir => "拒绝共享 {}: 这是根目录、用户主目录或系统目录，确需共享请指定 --allow-unsafe-dir", "Refusing to share {}: it is the root, a home or a system directory; pass --allow-unsafe-dir if this is intended";
+    UnsafeDirAllowed => "!!! 正在共享系统目录 {}，其中的所有文件都可被访问 !!!", "!!! Sharing system directory {}; every file in it is accessible !!!";
+    WorldWritableDir => "目录 {} 所有用户可写，其他用户可以放入或替换共享的文件", "Directory {} is world-writable; other users can add or replace shared files";
+    DirNotFound => "目录 {} 不存在", "Directory {} does not exist";
+    LowDiskSpace => "目录 {} 所在磁盘仅剩 {} 字节，低于 --min-free-space {} 字节，上传将被拒绝", "The disk of {} has only {} bytes left, below --min-free-space {} bytes; uploads will be refused";
+    DirCreated => "创建目录 {} 成功", "Created directory {}";
+    TlsPair => "--tls-cert 和 --tls-key 必须同时指定", "--tls-cert and --tls-key must be given together";
+    ClientCaWithoutTls => "--client-ca 需要同时指定 --tls-cert 和 --tls-key 启用HTTPS", "--client-ca requires HTTPS, set --tls-cert and --tls-key as well";
+    Http2WithoutTls => "--http2 需要同时指定 --tls-cert 和 --tls-key 启用HTTPS", "--http2 requires HTTPS, set --tls-cert and --tls-key as well";
+    AuthPair => "--username 和 --password 必须同时指定", "--username and --password must be given together";
+    CorsWildcardAuth => "--cors-origin * 不能与 --username/--password、--auth-command 或带账号的 --mount 同时使用，请指定具体的来源", "--cors-origin * cannot be combined with --username/--password, --auth-command or a --mount with credentials, list the allowed origins instead";
+    AuthCommandWithBasic => "--auth-command 不能与 --username/--password 或带账号的 --mount 同时使用", "--auth-command cannot be combined with --username/--password or a --mount with credentials";
+    AuthCommandNotFound => "认证程序 {} 不存在", "Auth command {} does not exist";
+    InvalidHost => "--host 的值 {} 不是有效的IP地址", "--host value {} is not a valid IP address";
+    TrustedProxyWithoutTrust => "--trusted-proxy 需要同时指定 --trust-proxy", "--trusted-proxy requires --trust-proxy";
+    ZeroCleanupInterval => "--cleanup-interval 不能为0", "--cleanup-interval must not be zero";
+    MaxFileAgeReadOnly => "--max-file-age 会删除文件，不能与 --read-only 同时使用", "--max-file-age deletes files and cannot be combined with --read-only";
+    InvalidBacklog => "--backlog 的值 {} 无效，应在1到{}之间", "--backlog value {} is invalid, it must be between 1 and {}";
+    Ipv6OnlyWithV4 => "--ipv6-only 不能与IPv4地址 {} 同时使用", "--ipv6-only cannot be used with the IPv4 address {}";
+    CheckPassed => "配置检查通过", "Configuration check passed";
+    ReadOnlyEnabled => "只读模式已启用", "Read-only mode enabled";
+    HstsWithoutTls => "未启用TLS，忽略 --hsts", "TLS is not enabled, ignoring --hsts";
+    UnixWithTls => "--unix-socket 不能与 --tls-cert/--tls-key 同时使用", "--unix-socket cannot be combined with --tls-cert/--tls-key";
+    PortIgnored => "已指定 --unix-socket，忽略 --port {}", "--unix-socket is set, ignoring --port {}";
+    BindFailed => "绑定端口 {} 失败: {}", "Failed to bind port {}: {}";
+    ListenFailed => "启动监听失败: {}", "Failed to start listening: {}";
+    TlsListenFailed => "启动HTTPS服务失败: {}", "Failed to start the HTTPS service: {}";
+    SharedDir => "共享文件夹绝对路径：{}", "Shared directory: {}";
+    LocalUrl => "本机访问地址：{}", "Local URL: {}";
+    LanUrl => "局域网访问地址：{}", "LAN URL: {}";
+    LanUrlV6 => "局域网访问地址(IPv6)：{}", "LAN URL (IPv6): {}";
+    DetectedAddresses => "检测到的网络接口地址: {}，自动选择的地址不对时可用 --public-ip 指定", "Detected interface addresses: {}; use --public-ip if the detected LAN address is wrong";
+    Url => "访问地址：{}", "URL: {}";
+    ListenUnix => "监听Unix套接字：{}", "Listening on Unix socket: {}";
+    #[cfg_attr(unix, allow(dead_code))]
+    DaemonUnsupported => "当前系统不支持 --daemon", "--daemon is not supported on this platform";
+    DaemonFailed => "转入后台运行失败: {}", "Failed to daemonize: {}";
+    DaemonStarted => "已在后台运行，进程号 {}", "Running in the background with PID {}";
+    DaemonExited => "后台进程启动失败，详见日志", "The background process failed to start, see the log for details";
+    WritePidFileFailed => "写入PID文件 {} 失败: {}", "Failed to write PID file {}: {}";
+    SaveDownloadStatsFailed => "保存下载统计文件 {} 失败: {}", "Failed to save download stats file {}: {}";
+    RemovePidFileFailed => "删除PID文件 {} 失败: {}", "Failed to remove PID file {}: {}";
+    OpenAccessLogFailed => "打开访问日志文件 {} 失败: {}", "Failed to open access log {}: {}";
+    NoDisplay => "未检测到图形界面，跳过打开浏览器", "No graphical display detected, not opening a browser";
+    BrowserOpened => "已在浏览器中打开 {}", "Opened {} in the browser";
+    BrowserFailed => "打开浏览器失败: {}", "Failed to open the browser: {}";
+    ShuttingDown => "正在优雅关闭...", "Shutting down gracefully...";
+    IdleShutdownReached => "空闲超时，自动关闭", "Idle timeout reached, shutting down";
+    RemoveSocketFailed => "删除Unix套接字 {} 失败: {}", "Failed to remove Unix socket {}: {}";
+    MaxConnectionsReached => "连接数已达上限 {}，拒绝新连接(近期共拒绝 {} 个)", "Connection limit {} reached, refusing new connections ({} refused recently)";
+    DrainTimeout => "关闭超时，仍有 {} 个请求未完成", "Shutdown timed out with {} requests still in flight";
+    Stopped => "已关闭", "Stopped";
+
+    // 重新加载配置
+    ListenSighupFailed => "监听SIGHUP失败: {}", "Failed to listen for SIGHUP: {}";
+    ReloadWithoutConfig => "收到SIGHUP，但未指定 --config，没有可重新加载的配置", "Received SIGHUP but no --config was given, nothing to reload";
+    ReloadFailed => "重新加载配置失败，继续使用当前配置: {}", "Failed to reload the configuration, keeping the current one: {}";
+    RestartRequired => "{} 已修改，需要重启生效", "{} changed, restart required to take effect";
+    ReloadUnchanged => "已重新加载配置，可在运行时修改的配置项没有变化", "Configuration reloaded, no runtime-changeable settings changed";
+    Reloaded => "已重新加载配置文件 {}", "Reloaded configuration file {}";
+
+    // 目录列表页面
+    ListingTitle => "文件列表: {}", "Index of {}";
+    Root => "根目录", "Root";
+    ColumnName => "名称", "Name";
+    ColumnSize => "大小", "Size";
+    ColumnModified => "修改时间", "Modified";
+    ColumnDownloads => "下载次数", "Downloads";
+    SelectAll => "全选", "Select all";
+    DownloadSelected => "打包下载所选", "Download selected as ZIP";
+    Total => "共 {} 项", "{} items";
+    PageOf => "共 {} 项，第 {}/{} 页", "{} items, page {} of {}";
+    PrevPage => "上一页", "Previous";
+    NextPage => "下一页", "Next";
+
+    // 文件预览页面
+    BackToListing => "返回目录", "Back to directory";
+    EmptyDir => "此目录为空", "This folder is empty";
+    ListingTruncated => "仅显示前 {} 项，其余条目可在目录地址后加 ?search=关键字 搜索", "Only the first {} entries are shown, add ?search=keyword to the folder URL to find the rest";
+    ParentDir => "返回上级目录", "Up to parent folder";
+    Download => "下载", "Download";
+
+    // 404页面
+    NotFoundTitle => "文件不存在", "Not Found";
+    NotFoundMessage => "您访问的文件或目录不存在。", "The file or directory you requested does not exist.";
+
+    // 上传页面
+    UploadTitle => "上传文件", "Upload files";
+    UploadDrop => "拖放文件到这里，或点击选择文件", "Drop files here or click to choose";
+    UploadOverwrite => "覆盖同名文件", "Overwrite existing files";
+    UploadReadOnly => "服务器处于只读模式，无法上传文件", "The server is in read-only mode, uploads are disabled";
+    UploadDone => "完成", "Done";
+    UploadFailed => "失败", "Failed";
+
+    // 请求的错误响应
+    InvalidPath => "路径无效", "Invalid path";
+    InvalidPathNamed => "路径 {} 无效", "Invalid path {}";
+    InvalidTarget => "目标路径无效", "Invalid target path";
+    NotExists => "{} 不存在", "{} does not exist";
+    OutsideShare => "禁止访问共享目录之外的文件", "Access to files outside the shared directory is forbidden";
+    DirUnavailable => "目录不可用", "Directory unavailable";
+    ReadOnlyRejected => "服务器处于只读模式，不允许写入操作", "The server is in read-only mode, writes are not allowed";
+    TooManyConnections => "连接数已达上限，请稍后重试", "Too many connections, please try again later";
+    TooManyRequests => "同时进行的请求过多，请稍后重试", "Too many concurrent requests, please try again later";
+    RequestTimedOut => "请求处理超时", "Request timed out";
+    HotlinkForbidden => "禁止从其他网站引用本站文件", "Linking to files on this site from other sites is forbidden";
+    NoFilesSelected => "未选择文件，使用?file=相对路径指定要打包的文件", "No files selected, use ?file=<relative path> to choose the files to archive";
+    UnsupportedChecksum => "不支持的校验算法 {}，可选值: {}", "Unsupported checksum algorithm {}, possible values: {}";
+    ChecksumFileOnly => "只能计算文件的校验和", "Checksums are only available for files";
+    ShareLinkInvalid => "分享链接无效或已过期", "The share link is invalid or has expired";
+    ShareLinkReshare => "分享链接不能用于生成新的分享链接", "A share link cannot be used to create another share link";
+    ShareSecretMissing => "未设置 --share-secret，无法生成分享链接", "--share-secret is not set, share links cannot be created";
+    InvalidShareDuration => "share 应为大于0的有效秒数", "share must be a positive number of seconds";
+    ShareFileOnly => "只能分享文件", "Only files can be shared";
+    InvalidThumbSize => "缩略图尺寸应为{}到{}之间的整数", "The thumbnail size must be an integer between {} and {}";
+    ThumbUnsupported => "只能为jpg、png、gif、webp图片生成缩略图", "Thumbnails are only available for jpg, png, gif and webp images";
+    ImageUndecodable => "无法解码该图片", "The image cannot be decoded";
+    UploadDisabled => "上传功能未启用", "Upload is not enabled";
+    ParseUploadFailed => "解析上传内容失败: {}", "Failed to parse the upload: {}";
+    InvalidFileName => "文件名 {} 无效", "Invalid file name {}";
+    UploadNotAllowed => "不允许上传文件 {}", "Uploading {} is not allowed";
+    FileExists => "文件 {} 已存在，使用?overwrite=true覆盖", "File {} already exists, use ?overwrite=true to replace it";
+    UploadInProgress => "文件 {} 正在上传", "File {} is already being uploaded";
+    ChunkInProgress => "文件 {} 正在上传其他分块", "Another chunk of {} is being uploaded";
+    FinalizeSizeRequired => "完成上传时需要指定 size", "size is required to finish the upload";
+    InvalidOffset => "offset 无效", "Invalid offset";
+    OffsetMismatch => "offset 与已上传的大小不一致", "offset does not match the size uploaded so far";
+    UploadTooLarge => "上传内容超过大小上限 {} 字节", "The upload exceeds the size limit of {} bytes";
+    DiskFull => "磁盘空间不足", "Not enough disk space";
+    SaveFailed => "保存文件 {} 失败", "Failed to save file {}";
+    DeleteDisabled => "删除功能未启用", "Delete is not enabled";
+    DeleteRoot => "不能删除共享目录本身", "The shared directory itself cannot be deleted";
+    DeleteDirNotRecursive => "{} 是目录，使用?recursive=true删除", "{} is a directory, use ?recursive=true to delete it";
+    DeleteFailed => "删除 {} 失败", "Failed to delete {}";
+    MkdirDisabled => "新建目录功能未启用", "Creating directories is not enabled";
+    MkdirConflict => "{} 与已有文件冲突", "{} conflicts with an existing file";
+    MkdirFailed => "创建目录 {} 失败", "Failed to create directory {}";
+    RenameDisabled => "重命名功能未启用", "Rename is not enabled";
+    RenameNotAllowed => "不允许重命名为 {}", "Renaming to {} is not allowed";
+    AlreadyExists => "{} 已存在，使用?overwrite=true覆盖", "{} already exists, use ?overwrite=true to replace it";
+    ParentMissing => "{} 的上级目录不存在", "The parent directory of {} does not exist";
+    OverwriteDir => "{} 是目录，不能覆盖", "{} is a directory and cannot be replaced";
+    RenameFailed => "重命名 {} 失败", "Failed to rename {}";
+
+    // 运行日志
+    RotateAccessLogFailed => "轮转访问日志 {} 失败: {}", "Failed to rotate access log {}: {}";
+    WriteAccessLogFailed => "写入访问日志 {} 失败: {}", "Failed to write access log {}: {}";
+    SlowRequest => "慢请求: {} {} {} {} 字节 耗时 {}s", "Slow request: {} {} {} {} bytes in {}s";
+    ZipDirAborted => "打包目录 {} 中止: {}", "Archiving directory {} aborted: {}";
+    ZipTooDeep => "目录 {} 超过最大打包深度 {}，已跳过", "Directory {} exceeds the maximum archive depth {}, skipped";
+    ZipSymlinkRejected => "拒绝打包 {}：符号链接指向共享目录之外", "Refusing to archive {}: the symlink points outside the shared directory";
+    ZipSelectionAborted => "打包所选文件中止: {}", "Archiving the selected files aborted: {}";
+    ThreadPoolClosed => "线程池已关闭", "The thread pool has shut down";
+    AuthCommandExited => "认证程序 {} 异常退出: {}", "Auth command {} exited abnormally: {}";
+    AuthCommandFailed => "执行认证程序 {} 失败: {}", "Failed to run auth command {}: {}";
+    AuthCommandTimedOut => "认证程序超时", "Auth command timed out";
+    SharedDirRestored => "共享目录 {} 已恢复", "Shared directory {} is available again";
+    SharedDirGone => "共享目录 {} 已不存在", "Shared directory {} no longer exists";
+    SharedDirRecreated => "已重新创建共享目录 {}", "Recreated shared directory {}";
+    RecreateSharedDirFailed => "重新创建共享目录 {} 失败: {}", "Failed to recreate shared directory {}: {}";
+    InvalidCacheControl => "--cache-control 的值 {} 无效", "Invalid --cache-control value {}";
+    ChecksumFailed => "计算 {} 的校验和失败: {}", "Failed to compute the checksum of {}: {}";
+    ReadCanceled => "读取文件被取消", "Reading the file was canceled";
+    NoAcceptedEncoding => "客户端不接受gzip/br", "client accepts neither gzip nor br";
+    CompressedExtension => "已压缩的文件类型", "already compressed file type";
+    NotOkResponse => "不是200响应", "not a 200 response";
+    ResponseTooSmall => "响应过小", "response too small";
+    AlreadyEncoded => "已指定编码", "encoding already set";
+    CompressedContentType => "已压缩的内容类型", "already compressed content type";
+    UnknownSize => "未知", "unknown";
+    NotCompressed => "{} 不压缩(identity, {}): {} 字节", "{} not compressed (identity, {}): {} bytes";
+    Compressed => "{} 压缩({}): {} 字节 -> {} 字节", "{} compressed ({}): {} bytes -> {} bytes";
+    SerializeConfigFailed => "序列化配置失败: {}", "Failed to serialize the configuration: {}";
+    FileDeleted => "已删除文件 {}", "Deleted file {}";
+    DirDeleted => "已删除目录 {}", "Deleted directory {}";
+    DeleteError => "删除 {} 失败: {}", "Failed to delete {}: {}";
+    DeleteInterrupted => "删除操作被中断", "The delete was interrupted";
+    DiskUsageUnavailable => "无法获取目录 {} 所在磁盘的空间", "Cannot get the disk space of directory {}";
+    InvalidDownloadStatsFile => "下载统计文件 {} 格式错误: {}", "Download stats file {} is malformed: {}";
+    ReadDownloadStatsFailed => "读取下载统计文件 {} 失败: {}", "Failed to read download stats file {}: {}";
+    ExpiryScanFailed => "扫描过期文件时读取目录 {} 失败: {}", "Failed to read directory {} while looking for expired files: {}";
+    ExpiredFileDeleted => "已删除过期文件 {}", "Deleted expired file {}";
+    DeleteExpiredFileFailed => "删除过期文件 {} 失败: {}", "Failed to delete expired file {}: {}";
+    ReadFaviconFailed => "读取网站图标 {} 失败: {}", "Failed to read favicon {}: {}";
+    Filtered => "{} 被通配符过滤", "{} is excluded by the glob filter";
+    IgnoreNegation => "{} 中的规则 {} 无效: 不支持 ! 取反", "Invalid rule in {}: {}: ! negation is not supported";
+    InvalidIgnoreRule => "{} 中的规则 {} 无效: {}", "Invalid rule in {}: {}: {}";
+    IgnoreRulesLoaded => "已从 {} 加载 {} 条忽略规则", "Loaded ignore file {} with {} rules";
+    ReadIgnoreFileFailed => "读取忽略文件 {} 失败: {}", "Failed to read ignore file {}: {}";
+    HealthCheckInterrupted => "检查目录被中断", "The directory check was interrupted";
+    HealthCheckFailed => "健康检查失败: {}", "Health check failed: {}";
+    IpDenied => "拒绝来自 {} 的访问", "Denied access from {}";
+    #[cfg_attr(unix, allow(dead_code))]
+    SyslogUnsupported => "当前平台不支持 syslog", "syslog is not supported on this platform";
+    ManifestAborted => "生成 {} 的文件清单中止: {}", "Generating the manifest of {} aborted: {}";
+    ManifestTooDeep => "目录 {} 超过文件清单的最大深度 {}，已跳过", "Directory {} exceeds the maximum manifest depth {}, skipped";
+    MethodNotAllowed => "拒绝 {} {}: 方法不在允许列表中", "Rejected {} {}: method not in the allowed list";
+    ReadMimeDbFailed => "读取MIME类型文件 {} 失败: {}", "Failed to read MIME types file {}: {}";
+    InvalidMimeDbLine => "MIME类型文件 {} 第{}行格式错误，已跳过", "MIME types file {} line {} is malformed, skipped";
+    MkdirError => "创建目录 {} 失败: {}", "Failed to create directory {}: {}";
+    MkdirInterrupted => "创建目录操作被中断", "Creating the directory was interrupted";
+    ReadNotFoundPageFailed => "读取404页面 {} 失败: {}", "Failed to read 404 page {}: {}";
+    PerIpLimitReached => "{} 同时进行的请求数超过 {}，拒绝请求", "{} has more than {} concurrent requests, rejecting";
+    OpenPrecompressedFailed => "打开预压缩文件 {} 失败: {}", "Failed to open precompressed file {}: {}";
+    ReadFileFailed => "读取 {} 失败: {}", "Failed to read {}: {}";
+    QrFailed => "生成二维码失败: {}", "Failed to generate the QR code: {}";
+    HotlinkRejected => "拒绝盗链请求 {}，Referer: {}", "Rejected hotlink request {}, Referer: {}";
+    RenameSymlinkRejected => "拒绝重命名到 {}：符号链接指向共享目录之外", "Refusing to rename to {}: the symlink points outside the shared directory";
+    RenameFilteredRejected => "拒绝重命名到 {}：目标路径被过滤", "Refusing to rename to {}: the target path is filtered";
+    Renamed => "已将 {} 重命名为 {}", "Renamed {} to {}";
+    RenameError => "重命名 {} 为 {} 失败: {}", "Failed to rename {} to {}: {}";
+    RenameInterrupted => "重命名操作被中断", "The rename was interrupted";
+    SearchFailed => "搜索 {} 失败: {}", "Search in {} failed: {}";
+    ListenSignalFailed => "监听退出信号失败: {}", "Failed to listen for shutdown signals: {}";
+    ListenSigtermFailed => "监听SIGTERM失败: {}", "Failed to listen for SIGTERM: {}";
+    OpenSharedFileFailed => "打开共享文件 {} 失败: {}", "Failed to open shared file {}: {}";
+    ReadMetadataFailed => "读取 {} 的元数据失败: {}", "Failed to read the metadata of {}: {}";
+    SymlinkRejected => "拒绝访问 {}：符号链接指向共享目录之外", "Refusing access to {}: the symlink points outside the shared directory";
+    DecodeImageFailed => "无法解码图片 {}: {}", "Cannot decode image {}: {}";
+    ThumbnailFailed => "生成 {} 的缩略图失败: {}", "Failed to create a thumbnail of {}: {}";
+    ReadThumbnailFailed => "读取缩略图 {} 失败: {}", "Failed to read thumbnail {}: {}";
+    RequestAborted => "请求 {} 处理超过 {} 秒，已中止", "Request {} took longer than {} seconds and was aborted";
+    ReadTlsCertFailed => "读取TLS证书 {} 失败: {}", "Failed to read TLS certificate {}: {}";
+    NoTlsCert => "TLS证书 {} 中没有找到任何证书", "No certificates found in TLS certificate file {}";
+    ReadTlsKeyFailed => "读取TLS私钥 {} 失败: {}", "Failed to read TLS private key {}: {}";
+    TlsKeyMismatch => "TLS证书与私钥无效或不匹配: {}", "The TLS certificate and private key are invalid or do not match: {}";
+    ReadClientCaFailed => "读取客户端CA证书 {} 失败: {}", "Failed to read client CA {}: {}";
+    NoClientCa => "客户端CA证书 {} 中没有找到有效的证书", "No valid certificates found in client CA file {}";
+    InvalidClientCa => "客户端CA证书 {} 无效: {}", "Invalid client CA {}: {}";
+    TlsHandshakeTimeout => "TLS握手超时", "TLS handshake timed out";
+    TlsHandshakeVersion => "TLS握手使用 {}", "TLS handshake using {}";
+    ReadUiFileFailed => "读取界面文件 {} 失败: {}", "Failed to read UI file {}: {}";
+    InvalidUiDir => "界面目录 {} 不存在或不是目录", "UI directory {} does not exist or is not a directory";
+    UploadNameRejected => "拒绝上传 {}：文件名被隐藏或过滤", "Rejected upload {}: the file name is hidden or filtered";
+    SaveUploadError => "保存上传文件 {} 失败: {}", "Failed to save upload {}: {}";
+    WriteInterrupted => "写入操作被中断", "The write was interrupted";
+    UploadSaved => "已保存上传文件 {} ({} 字节)", "Saved upload {} ({} bytes)";
+    OpenTempFileFailed => "打开临时文件 {} 失败: {}", "Failed to open temporary file {}: {}";
+    ChunkInterrupted => "接收文件 {} 的分块中断: {}", "Receiving a chunk of {} was interrupted: {}";
+    RemoveTempFileFailed => "删除上传临时文件 {} 失败: {}", "Failed to remove temporary upload file {}: {}";
+    CreateWatcherFailed => "创建目录监视失败: {}", "Failed to create the directory watcher: {}";
+    WatchDirFailed => "监视目录 {} 失败: {}", "Failed to watch directory {}: {}";
+    WatchError => "目录监视出错: {}", "Directory watcher error: {}";
+    WatchLagged => "目录监视客户端接收过慢，丢弃 {} 个事件", "A directory watch client is too slow, dropped {} events";
+
+    // 参数和配置文件的解析错误
+    ReadConfigFailed => "读取配置文件 {} 失败: {}", "Failed to read config file {}: {}";
+    InvalidConfigFile => "配置文件 {} 格式错误: {}", "Config file {} is malformed: {}";
+    InvalidConfigChoice => "配置文件 {} 中 {} 的值 \"{}\" 无效，可选值: {}", "Config file {}: invalid {} value \"{}\", possible values: {}";
+    InvalidConfigValue => "配置文件 {} 中 {} 的值无效: {}", "Config file {}: invalid {} value: {}";
+    InvalidCharset => "字符集 {} 无效", "Invalid charset {}";
+    InvalidExtCharset => "字符集映射 {} 格式错误，应为ext=charset", "Malformed charset mapping {}, expected ext=charset";
+    ExtCharsetMissingExt => "字符集映射 {} 缺少扩展名", "Charset mapping {} has no extension";
+    ChunkSizeOutOfRange => "读取块大小 {} 字节超出范围，应在4KB到16MB之间", "Read chunk size {} bytes is out of range, it must be between 4KB and 16MB";
+    InvalidDuration => "时长 {} 无效: {}，应为秒数或如30s、5m、2h、7d的格式", "Invalid duration {}: {}, expected seconds or a value such as 30s, 5m, 2h or 7d";
+    InvalidGlob => "通配符 {} 无效: {}", "Invalid glob {}: {}";
+    InvalidExtension => "扩展名 {} 无效", "Invalid extension {}";
+    InvalidHeader => "响应头 {} 格式错误，应为\"名称: 值\"", "Malformed header {}, expected \"Name: value\"";
+    InvalidHeaderName => "响应头 {} 的名称无效", "Header {} has an invalid name";
+    HeaderNotAllowed => "不允许自定义响应头 {}", "Header {} cannot be customized";
+    InvalidHeaderValue => "响应头 {} 的值无效", "Header {} has an invalid value";
+    InvalidCidr => "无效的CIDR地址段: {}", "Invalid CIDR network: {}";
+    InvalidCsp => "--csp 的值 {} 无效", "Invalid --csp value {}";
+    InvalidIndexFile => "首页文件名 {} 无效，只能是文件名", "Invalid index file {}, it must be a plain file name";
+    InvalidMethod => "HTTP方法 {} 无效", "Invalid HTTP method {}";
+    InvalidMimeMapping => "MIME类型映射 {} 格式错误，应为ext=type", "Malformed MIME type mapping {}, expected ext=type";
+    MimeMappingMissingExt => "MIME类型映射 {} 缺少扩展名", "MIME type mapping {} has no extension";
+    InvalidMimeType => "MIME类型映射 {} 的类型无效，应为如text/plain的格式", "MIME type mapping {} has an invalid type, expected a type such as text/plain";
+    BasePathNoSlash => "--base-path {} 必须以/开头", "--base-path {} must start with /";
+    BasePathInvalidChars => "--base-path {} 含有不允许的字符", "--base-path {} contains characters that are not allowed";
+    UnknownSizeUnit => "无法识别的大小单位 \"{}\"，可用单位: B, K/KB/KiB, M/MB/MiB, G/GB/GiB, T/TB/TiB", "Unknown size unit \"{}\", available units: B, K/KB/KiB, M/MB/MiB, G/GB/GiB, T/TB/TiB";
+    InvalidSize => "大小 \"{}\" 格式错误，如 5MB", "Malformed size \"{}\", expected a value such as 5MB";
+    SizeOutOfRange => "大小 \"{}\" 超出范围", "Size \"{}\" is out of range";
+    InvalidSocketMode => "套接字权限 {} 无效，应为八进制数，如660", "Invalid socket mode {}, expected an octal number such as 660";
+}
+
+// 当前语言的文案
+pub fn t(msg: Msg) -> &'static str {
+    msg.text(current())
+}
+
+// 当前语言的文案，依次将其中的 {} 替换为参数
+pub fn tf(msg: Msg, args: &[&dyn Display]) -> String {
+    let mut parts = t(msg).split("{}");
+    let mut out = parts.next().unwrap_or("").to_string();
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
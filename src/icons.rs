@@ -0,0 +1,63 @@
+use std::path::Path;
+
+// 目录列表中每个条目前的图标，按扩展名分类，SVG 直接内嵌在页面中
+const FOLDER: &str = include_str!("assets/icons/folder.svg");
+const IMAGE: &str = include_str!("assets/icons/image.svg");
+const VIDEO: &str = include_str!("assets/icons/video.svg");
+const AUDIO: &str = include_str!("assets/icons/audio.svg");
+const ARCHIVE: &str = include_str!("assets/icons/archive.svg");
+const TEXT: &str = include_str!("assets/icons/text.svg");
+const CODE: &str = include_str!("assets/icons/code.svg");
+const OTHER: &str = include_str!("assets/icons/other.svg");
+
+const IMAGE_EXTENSIONS: [&str; 11] = ["jpg", "jpeg", "png", "gif", "webp", "bmp", "svg", "ico", "tif", "tiff", "heic"];
+const VIDEO_EXTENSIONS: [&str; 9] = ["mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v", "ts"];
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "wav", "flac", "aac", "ogg", "m4a", "wma", "opus"];
+const ARCHIVE_EXTENSIONS: [&str; 11] = ["zip", "rar", "7z", "tar", "gz", "tgz", "bz2", "xz", "zst", "iso", "dmg"];
+const TEXT_EXTENSIONS: [&str; 9] = ["txt", "md", "log", "csv", "pdf", "doc", "docx", "rtf", "odt"];
+const CODE_EXTENSIONS: [&str; 24] = [
+    "rs", "c", "h", "cpp", "hpp", "go", "py", "js", "ts", "java", "kt", "rb", "php", "sh", "html", "css", "json", "toml", "yaml",
+    "yml", "xml", "sql", "swift", "lua",
+];
+
+// 目录总是使用文件夹图标，文件按扩展名(不区分大小写)选择，无法识别的使用通用图标
+pub fn for_entry(name: &str, is_dir: bool) -> &'static str {
+    if is_dir {
+        return FOLDER;
+    }
+    let ext = match Path::new(name).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.to_ascii_lowercase(),
+        None => return OTHER,
+    };
+    let ext = ext.as_str();
+    [
+        (&IMAGE_EXTENSIONS[..], IMAGE),
+        (&VIDEO_EXTENSIONS[..], VIDEO),
+        (&AUDIO_EXTENSIONS[..], AUDIO),
+        (&ARCHIVE_EXTENSIONS[..], ARCHIVE),
+        (&TEXT_EXTENSIONS[..], TEXT),
+        (&CODE_EXTENSIONS[..], CODE),
+    ]
+    .into_iter()
+    .find_map(|(extensions, icon)| extensions.contains(&ext).then_some(icon))
+    .unwrap_or(OTHER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_icon_by_extension() {
+        assert_eq!(for_entry("photos", true), FOLDER);
+        assert_eq!(for_entry("photos.zip", true), FOLDER);
+        assert_eq!(for_entry("IMG_0001.JPG", false), IMAGE);
+        assert_eq!(for_entry("movie.mkv", false), VIDEO);
+        assert_eq!(for_entry("song.flac", false), AUDIO);
+        assert_eq!(for_entry("backup.tar.gz", false), ARCHIVE);
+        assert_eq!(for_entry("notes.md", false), TEXT);
+        assert_eq!(for_entry("main.rs", false), CODE);
+        assert_eq!(for_entry("Makefile", false), OTHER);
+        assert_eq!(for_entry("data.bin", false), OTHER);
+    }
+}
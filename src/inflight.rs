@@ -0,0 +1,213 @@
+use std::error::Error;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{WebRequest, WebResponse};
+
+// 统计所有工作线程中尚未完成的请求数，响应体发送完毕或连接断开时才计为完成
+#[derive(Clone)]
+pub struct InFlight {
+    state: Arc<State>,
+}
+
+struct State {
+    count: AtomicUsize,
+    // 最近一个请求完成的时间，启动时为创建时间，用于 --idle-shutdown
+    last_done: Mutex<Instant>,
+}
+
+impl Default for InFlight {
+    fn default() -> Self {
+        InFlight {
+            state: Arc::new(State {
+                count: AtomicUsize::new(0),
+                last_done: Mutex::new(Instant::now()),
+            }),
+        }
+    }
+}
+
+impl InFlight {
+    pub fn new() -> Self {
+        InFlight::default()
+    }
+
+    pub fn count(&self) -> usize {
+        self.state.count.load(Ordering::SeqCst)
+    }
+
+    // 没有未完成的请求并且最近一个请求完成后已经过了 idle 时返回
+    pub async fn wait_idle(&self, idle: Duration) {
+        loop {
+            let elapsed = self.state.last_done.lock().unwrap().elapsed();
+            match idle.checked_sub(elapsed) {
+                _ if self.count() > 0 => ntex::time::sleep(idle).await,
+                Some(remaining) if !remaining.is_zero() => ntex::time::sleep(remaining).await,
+                _ => return,
+            }
+        }
+    }
+}
+
+// 请求计数守卫，释放时计数减一并记下完成时间
+struct Guard(Arc<State>);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        *self.0.last_done.lock().unwrap() = Instant::now();
+        self.0.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// 包装响应体，使守卫随响应体一起释放
+struct TrackedBody<G> {
+    body: ResponseBody<Body>,
+    _guard: G,
+}
+
+impl<G: 'static> MessageBody for TrackedBody<G> {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        self.body.poll_next_chunk(cx)
+    }
+}
+
+impl<S> Middleware<S> for InFlight {
+    type Service = InFlightMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        InFlightMiddleware {
+            service,
+            state: self.state.clone(),
+        }
+    }
+}
+
+pub struct InFlightMiddleware<S> {
+    service: S,
+    state: Arc<State>,
+}
+
+impl<S, E> Service<WebRequest<E>> for InFlightMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        self.state.count.fetch_add(1, Ordering::SeqCst);
+        let guard = Guard(self.state.clone());
+
+        let res = ctx.call(&self.service, req).await?;
+        Ok(attach(res, guard))
+    }
+}
+
+// 将守卫挂到响应体上，响应体发送完毕或连接中途断开时守卫随之释放
+pub fn attach<G: 'static>(res: WebResponse, guard: G) -> WebResponse {
+    res.map_body(|_, body| ResponseBody::Other(Body::from_message(TrackedBody { body, _guard: guard })))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::reload::{Live, Settings};
+    use crate::testutil::{self, TempDir};
+    use crate::throttle::{Throttle, TotalBucket};
+
+    // 当前进程中打开了该文件的描述符数
+    fn open_handles(path: &Path) -> usize {
+        std::fs::read_dir("/proc/self/fd")
+            .map(|fds| fds.flatten().filter(|fd| std::fs::read_link(fd.path()).is_ok_and(|target| target == path)).count())
+            .unwrap_or(0)
+    }
+
+    #[ntex::test]
+    async fn disconnect_mid_download_releases_request() {
+        let dir = TempDir::new();
+        let file = dir.write("big.bin", vec![0u8; 8 * 1024 * 1024]);
+        let mount = dir.mount("/download/files");
+        let in_flight = InFlight::new();
+        let live = Live::new(Settings {
+            log_level: "info".to_string(),
+            rate_limit: 1024 * 1024,
+            total_rate_limit: 0,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        });
+        let counter = in_flight.clone();
+        let srv = test::server(move || {
+            web::App::new()
+                .wrap(Throttle::new(live.clone(), TotalBucket::default()))
+                .wrap(counter.clone())
+                .service(testutil::files(&mount))
+        });
+
+        let mut stream = TcpStream::connect(srv.addr()).unwrap();
+        stream.write_all(b"GET /download/files/big.bin HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut buf = [0u8; 64 * 1024];
+        let mut received = 0;
+        while received < 256 * 1024 {
+            received += stream.read(&mut buf).unwrap();
+        }
+        assert_eq!(in_flight.count(), 1);
+        if cfg!(target_os = "linux") {
+            assert_eq!(open_handles(&file), 1);
+        }
+        drop(stream);
+
+        // 限速下剩余的数据需要数秒才能发完，计数应在连接断开后很快归零
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while (in_flight.count() > 0 || open_handles(&file) > 0) && Instant::now() < deadline {
+            ntex::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(in_flight.count(), 0);
+        if cfg!(target_os = "linux") {
+            assert_eq!(open_handles(&file), 0);
+        }
+    }
+
+    #[ntex::test]
+    async fn idle_timer_waits_for_requests_to_finish() {
+        let dir = TempDir::new();
+        dir.write("a.txt", "a");
+        let mount = dir.mount("/download/files");
+        let in_flight = InFlight::new();
+        let app = test::init_service(web::App::new().wrap(in_flight.clone()).service(testutil::files(&mount))).await;
+
+        // 未读取的响应体保持请求进行中，空闲计时从它完成时开始
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/a.txt").to_request()).await;
+        let idle = Duration::from_millis(200);
+        let waiter = in_flight.clone();
+        let started = Instant::now();
+        let wait = ntex::rt::spawn(async move { waiter.wait_idle(idle).await });
+        ntex::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(in_flight.count(), 1);
+        drop(res);
+        let finished = Instant::now();
+        wait.await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(finished.elapsed() >= idle);
+    }
+}
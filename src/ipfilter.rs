@@ -0,0 +1,90 @@
+use std::net::IpAddr;
+use std::rc::Rc;
+
+use ipnet::IpNet;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, tf};
+use crate::proxy::{self, TrustProxy};
+use crate::reload::{Live, Settings};
+
+// 解析 CIDR，单个 IP 视为 /32 或 /128
+pub fn parse_cidr(value: &str) -> Result<IpNet, String> {
+    value
+        .parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| tf(Msg::InvalidCidr, &[&value]))
+}
+
+// IP 访问控制：命中 deny 直接拒绝；配置了 allow 时只放行命中的地址；都未配置时全部放行。
+// 地址段可通过 SIGHUP 重新加载
+#[derive(Clone)]
+pub struct IpFilter {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    live: Live,
+    trust_proxy: TrustProxy,
+}
+
+impl IpFilter {
+    pub fn new(live: Live, trust_proxy: TrustProxy) -> Self {
+        IpFilter {
+            inner: Rc::new(Inner { live, trust_proxy }),
+        }
+    }
+}
+
+fn permits(settings: &Settings, ip: Option<IpAddr>) -> bool {
+    if settings.allow.is_empty() && settings.deny.is_empty() {
+        return true;
+    }
+    let ip = match ip {
+        Some(ip) => ip.to_canonical(),
+        None => return false,
+    };
+    if settings.deny.iter().any(|net| net.contains(&ip)) {
+        return false;
+    }
+    settings.allow.is_empty() || settings.allow.iter().any(|net| net.contains(&ip))
+}
+
+impl<S> Middleware<S> for IpFilter {
+    type Service = IpFilterMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        IpFilterMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct IpFilterMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for IpFilterMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let ip = proxy::client_ip(req.headers(), req.peer_addr(), &self.inner.trust_proxy);
+        if permits(&self.inner.live.load(), ip) {
+            ctx.call(&self.service, req).await
+        } else {
+            log::debug!("{}", tf(Msg::IpDenied, &[&format!("{:?}", ip)]));
+            Ok(req.into_response(HttpResponse::Forbidden().finish()))
+        }
+    }
+}
@@ -0,0 +1,637 @@
+use std::path::Path;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use ntex::http::Method;
+use ntex::http::header::{ACCEPT, CONTENT_SECURITY_POLICY, HeaderName, HeaderValue, LOCATION};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpRequest, HttpResponse, WebRequest, WebResponse};
+use ntex_files::{Directory, NamedFile};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use serde::Serialize;
+
+use crate::dirsize::DirSizes;
+use crate::downloads::{self, Downloads};
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::{icons, query, thumb, ui, watch};
+
+// JSON 列表被 --listing-max-entries 截断时返回的响应头，值为返回的条目数
+const TRUNCATED_HEADER: HeaderName = HeaderName::from_static("x-listing-truncated");
+
+// 文件名放入链接时需要编码的字符
+pub const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+#[derive(Serialize)]
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<String>,
+    #[serde(skip)]
+    mtime: Option<SystemTime>,
+}
+
+// 读取目录内容，符号链接返回目标的元数据，未开启 --show-hidden 时不输出隐藏文件，也不输出被通配符过滤掉的文件。
+// 设置了 --listing-max-entries 时读到上限即停止，不再读取其余条目，第二个返回值表示是否被截断
+fn read_entries(dir: &Path, mount: &Mount) -> std::io::Result<(Vec<Entry>, bool)> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !mount.is_visible(&name) {
+            continue;
+        }
+        let metadata = match std::fs::metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if !mount.allows(&entry.path(), metadata.is_dir()) {
+            continue;
+        }
+        if mount.max_entries > 0 && entries.len() == mount.max_entries {
+            return Ok((entries, true));
+        }
+        entries.push(Entry {
+            name,
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(|t| humantime::format_rfc3339_seconds(t).to_string()),
+            mtime: metadata.modified().ok(),
+        });
+    }
+    Ok((entries, false))
+}
+
+// --default-sort 和 --default-order 的可选值
+pub const SORT_KEYS: [&str; 3] = ["name", "size", "modified"];
+pub const SORT_ORDERS: [&str; 2] = ["asc", "desc"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    Name,
+    Size,
+    Modified,
+}
+
+impl SortKey {
+    const ALL: [SortKey; 3] = [SortKey::Name, SortKey::Size, SortKey::Modified];
+
+    fn parse(value: Option<&str>) -> Self {
+        match value {
+            Some("size") => SortKey::Size,
+            Some("modified") => SortKey::Modified,
+            _ => SortKey::Name,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Modified => "modified",
+        }
+    }
+}
+
+// 目录始终排在文件前面，再按指定的列排序
+fn sort_entries(entries: &mut [Entry], key: SortKey, desc: bool) {
+    entries.sort_by(|a, b| {
+        let order = match key {
+            SortKey::Name => a.name.cmp(&b.name),
+            SortKey::Size => a.size.cmp(&b.size).then_with(|| a.name.cmp(&b.name)),
+            SortKey::Modified => a.mtime.cmp(&b.mtime).then_with(|| a.name.cmp(&b.name)),
+        };
+        let order = if desc { order.reverse() } else { order };
+        b.is_dir.cmp(&a.is_dir).then(order)
+    });
+}
+
+// 以 KB/MB/GB 等单位显示文件大小
+fn human_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", size) } else { format!("{:.1} {}", value, UNITS[unit]) }
+}
+
+// 修改时间按本地时区显示
+fn local_time(time: Option<SystemTime>) -> String {
+    time.and_then(|time| jiff::Timestamp::try_from(time).ok())
+        .map(|ts| ts.to_zoned(jiff::tz::TimeZone::system()).strftime("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// 生成目录列表链接的查询字符串，用于排序和翻页
+fn list_link(sort: SortKey, desc: bool, page: Option<usize>, per_page: Option<usize>) -> String {
+    let mut link = format!("?sort={}&amp;order={}", sort.as_str(), if desc { "desc" } else { "asc" });
+    if let Some(page) = page {
+        link.push_str(&format!("&amp;page={}", page));
+    }
+    if let Some(per_page) = per_page {
+        link.push_str(&format!("&amp;per_page={}", per_page));
+    }
+    link
+}
+
+// 分页导航：总数、当前页和上一页/下一页链接，只有一页时只显示总数
+fn pagination(total: usize, page: usize, per_page: usize, per_page_param: Option<usize>, sort: SortKey, desc: bool) -> String {
+    if per_page == 0 || (total <= per_page && page == 1) {
+        return tf(Msg::Total, &[&total]);
+    }
+    let pages = total.div_ceil(per_page).max(1);
+    let mut html = tf(Msg::PageOf, &[&total, &page, &pages]);
+    if page > 1 {
+        let prev = (page - 1).min(pages);
+        html.push_str(&format!(" <a href=\"{}\">{}</a>", list_link(sort, desc, Some(prev), per_page_param), t(Msg::PrevPage)));
+    }
+    if page < pages {
+        html.push_str(&format!(" <a href=\"{}\">{}</a>", list_link(sort, desc, Some(page + 1), per_page_param), t(Msg::NextPage)));
+    }
+    html
+}
+
+// 面包屑导航：从挂载点根目录开始，每一级链接到对应目录，不会链接到挂载点之外
+fn breadcrumb(req: &HttpRequest, mount: &Mount) -> String {
+    let mut href = format!("{}/", mount.external(&mount.url_path).trim_end_matches('/'));
+    let mut html = format!("<a href=\"{}\">{}</a> /", escape_html(&href), t(Msg::Root));
+    let rest = mount.strip(req.path()).unwrap_or("");
+    for segment in rest.split('/').filter(|s| !s.is_empty()) {
+        let name = percent_decode_str(segment).decode_utf8_lossy();
+        href.push_str(&format!("{}/", utf8_percent_encode(&name, SEGMENT)));
+        html.push_str(&format!(" <a href=\"{}\">{}</a> /", escape_html(&href), escape_html(&name)));
+    }
+    html
+}
+
+// 启用 --show-dir-size 时统计子目录的总大小，写入 size 字段
+fn fill_dir_sizes(entries: &mut [Entry], dir: &Path, mount: &Mount, dir_sizes: &DirSizes) {
+    for entry in entries.iter_mut().filter(|entry| entry.is_dir) {
+        let path = dir.join(&entry.name);
+        // 设置了 --max-depth 时只统计能访问到的层数
+        let remaining = path.strip_prefix(&mount.dir).ok().and_then(|relative| mount.filter.remaining_depth(relative));
+        entry.size = match remaining {
+            Some(0) => 0,
+            Some(depth) if mount.dir_size_max_depth == 0 || depth < mount.dir_size_max_depth => dir_sizes.get(&path, depth),
+            _ => dir_sizes.get(&path, mount.dir_size_max_depth),
+        };
+    }
+}
+
+// 开启 --serve-index 且目录中有首页文件时返回该文件而不是列表；
+// 请求路径不以 / 结尾时先跳转，使页面中的相对链接指向目录内部
+fn serve_index(dir: &Directory, req: &HttpRequest, mount: &Mount) -> Option<WebResponse> {
+    let index = dir.path.join(mount.index_file.as_deref()?);
+    if !index.is_file() || !mount.allows(&index, false) {
+        return None;
+    }
+    if !req.path().ends_with('/') {
+        let location = match req.query_string() {
+            "" => format!("{}/", mount.external(req.path())),
+            query => format!("{}/?{}", mount.external(req.path()), query),
+        };
+        return Some(WebResponse::new(HttpResponse::Found().header(LOCATION, location).finish(), req.clone()));
+    }
+    let file = NamedFile::open(&index).ok()?;
+    Some(WebResponse::new(file.disable_content_disposition().into_response(req), req.clone()))
+}
+
+// --csp 的值，空字符串表示不发送 Content-Security-Policy
+pub fn parse_csp(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    HeaderValue::from_str(value).map_err(|_| tf(Msg::InvalidCsp, &[&value]))?;
+    Ok(value.to_string())
+}
+
+// 检查 --index-file 的文件名，只能是目录中的文件名而不能含有路径
+pub fn parse_index_file(value: &str) -> Result<String, String> {
+    if value.is_empty() || value == "." || value == ".." || value.contains(['/', '\\', '\0']) {
+        return Err(tf(Msg::InvalidIndexFile, &[&value]));
+    }
+    Ok(value.to_string())
+}
+
+// 替换 ntex-files 默认的目录列表，显示文件大小和修改时间，支持 ?sort=size&order=desc 排序；
+// 请求中没有 sort 参数时使用挂载点的默认排序列和方向，只指定 sort 时按升序
+pub fn render_html(dir: &Directory, req: &HttpRequest, mount: &Mount, dir_sizes: &DirSizes, downloads: &Downloads) -> std::io::Result<WebResponse> {
+    if let Some(res) = serve_index(dir, req, mount) {
+        return Ok(res);
+    }
+    // --no-listing 时目录与不存在的路径一样返回 404，只能按已知的地址下载文件
+    if !mount.listing {
+        return Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+    }
+    let sort_param = query::get(req.query_string(), "sort");
+    let sort = SortKey::parse(Some(sort_param.as_deref().unwrap_or(&mount.default_sort)));
+    let desc = match query::get(req.query_string(), "order") {
+        Some(order) => order == "desc",
+        None => sort_param.is_none() && mount.default_desc,
+    };
+    let (mut entries, truncated) = read_entries(&dir.path, mount)?;
+    // 按大小排序时需要先统计所有子目录，否则只统计当前页中的子目录
+    if mount.show_dir_size && sort == SortKey::Size {
+        fill_dir_sizes(&mut entries, &dir.path, mount, dir_sizes);
+    }
+    sort_entries(&mut entries, sort, desc);
+
+    // 排序后再分页，超出范围的页码返回空页；per_page 为 0 时不分页
+    let total = entries.len();
+    let per_page_param = query::get(req.query_string(), "per_page").and_then(|value| value.parse::<usize>().ok()).filter(|n| *n > 0);
+    let per_page = per_page_param.unwrap_or(mount.page_size);
+    let page = query::get(req.query_string(), "page").and_then(|value| value.parse::<usize>().ok()).filter(|n| *n > 0).unwrap_or(1);
+    let page_entries = if per_page == 0 {
+        &mut entries[..]
+    } else {
+        let start = (page - 1).saturating_mul(per_page).min(total);
+        &mut entries[start..(start.saturating_add(per_page)).min(total)]
+    };
+    if mount.show_dir_size && sort != SortKey::Size {
+        fill_dir_sizes(page_entries, &dir.path, mount, dir_sizes);
+    }
+    let page_entries = &*page_entries;
+
+    let external = mount.external(req.path());
+    let base = external.trim_end_matches('/');
+    // 启用打包下载时每行前加复选框，值为相对于挂载点根目录的路径，通过 form 属性归入列表上方的表单
+    let select = mount.zip;
+    let relative_dir = mount.strip(req.path()).map(|rest| percent_decode_str(rest).decode_utf8_lossy().trim_matches('/').to_string()).unwrap_or_default();
+    let select_cell = |name: &str| {
+        if !select {
+            return String::new();
+        }
+        let value = if relative_dir.is_empty() { name.to_string() } else { format!("{}/{}", relative_dir, name) };
+        format!("<td class=\"select\"><input type=\"checkbox\" name=\"file\" value=\"{}\" form=\"selection\"></td>", escape_html(&value))
+    };
+    let mut rows = String::new();
+    let parent = base.rsplit_once('/').map_or("", |(parent, _)| parent);
+    if dir.path != dir.base {
+        rows.push_str(&format!(
+            "<tr>{}<td><a href=\"{}/\">{}../</a></td><td class=\"size\">-</td><td class=\"modified\">-</td><td class=\"downloads\">-</td></tr>\n",
+            if select { "<td class=\"select\"></td>" } else { "" },
+            escape_html(parent),
+            if mount.icons { icons::for_entry("..", true) } else { "" },
+        ));
+    }
+    let dir_key = downloads::key(mount, req.path()).unwrap_or_default();
+    for entry in page_entries {
+        let slash = if entry.is_dir { "/" } else { "" };
+        let size = if entry.is_dir && !mount.show_dir_size { "-".to_string() } else { human_size(entry.size) };
+        let count = if entry.is_dir { "-".to_string() } else { downloads.get(&format!("{}/{}", dir_key, entry.name)).to_string() };
+        rows.push_str(&format!(
+            "<tr>{}<td><a href=\"{}/{}{}\">{}{}{}</a></td><td class=\"size\">{}</td><td class=\"modified\">{}</td><td class=\"downloads\">{}</td></tr>\n",
+            select_cell(&entry.name),
+            escape_html(base),
+            utf8_percent_encode(&entry.name, SEGMENT),
+            slash,
+            if mount.icons { icons::for_entry(&entry.name, entry.is_dir) } else { "" },
+            escape_html(&entry.name),
+            slash,
+            size,
+            local_time(entry.mtime),
+            count,
+        ));
+    }
+
+    // 启用缩略图时，当前页中的图片另以网格显示在列表上方
+    let mut gallery = String::new();
+    if mount.thumbnails {
+        for entry in page_entries.iter().filter(|entry| !entry.is_dir && thumb::is_image(&entry.name)) {
+            let href = format!("{}/{}", escape_html(base), utf8_percent_encode(&entry.name, SEGMENT));
+            gallery.push_str(&format!(
+                "<a href=\"{}\"><img src=\"{}?thumb=200\" alt=\"\" loading=\"lazy\"><span>{}</span></a>\n",
+                href,
+                href,
+                escape_html(&entry.name),
+            ));
+        }
+        if !gallery.is_empty() {
+            gallery = format!("<div class=\"gallery\">\n{}</div>", gallery);
+        }
+    }
+
+    // 样式和脚本通过 /_fileshare/ 下的地址引用，浏览器可以长期缓存
+    let ui = ui::current();
+    let watch_script = if mount.watch {
+        let ws_path = format!("{}{}", mount.external(&mount.url_path).trim_end_matches('/'), watch::SUFFIX);
+        format!("<script src=\"{}\" data-ws=\"{}\"></script>", escape_html(&mount.external(&ui.watch_js_url)), escape_html(&ws_path))
+    } else {
+        String::new()
+    };
+
+    // 所选条目提交到 {挂载点}/zip；使用 GET，只读模式和默认的 --allowed-methods 下同样可用
+    let (select_header, selection) = if select && total > 0 {
+        let action = format!("{}/zip", mount.external(&mount.url_path).trim_end_matches('/'));
+        (
+            format!("<th class=\"select\"><input type=\"checkbox\" id=\"select-all\" title=\"{}\"></th>", t(Msg::SelectAll)),
+            format!(
+                "<form id=\"selection\" class=\"selection\" method=\"get\" action=\"{}\"><button type=\"submit\">{}</button></form>\n<script src=\"{}\" defer></script>",
+                escape_html(&action),
+                t(Msg::DownloadSelected),
+                escape_html(&mount.external(&ui.select_js_url)),
+            ),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+
+    // 空目录不显示表格，改为提示和返回上级目录的链接；可以上传时根目录显示醒目的上传入口，上传的文件只保存到挂载点根目录
+    let empty = if total == 0 {
+        let up = if dir.path != dir.base {
+            format!(" <a href=\"{}/\">{}</a>", escape_html(parent), t(Msg::ParentDir))
+        } else {
+            String::new()
+        };
+        let upload = if mount.upload && dir.path == dir.base {
+            let href = format!("{}/upload", mount.external(&mount.url_path).trim_end_matches('/'));
+            format!("<a class=\"dropzone\" href=\"{}\">{}</a>", escape_html(&href), t(Msg::UploadTitle))
+        } else {
+            String::new()
+        };
+        format!("<div class=\"empty\"><p>{}{}</p>{}</div>", t(Msg::EmptyDir), up, upload)
+    } else {
+        String::new()
+    };
+
+    // 排序和分页只针对已读取的条目，其余条目只能通过搜索找到
+    let truncated = if truncated {
+        format!("<p class=\"truncated\">{}</p>", tf(Msg::ListingTruncated, &[&mount.max_entries]))
+    } else {
+        String::new()
+    };
+
+    let title = match mount.title {
+        Some(ref title) => title.clone(),
+        None => tf(Msg::ListingTitle, &[&percent_decode_str(req.path()).decode_utf8_lossy()]),
+    };
+    let banner = match mount.banner {
+        Some(ref banner) => format!("<div class=\"banner\">{}</div>", escape_html(banner)),
+        None => String::new(),
+    };
+    let mut html = ui
+        .listing_html
+        .replace("{{stylesheet}}", &format!("<link rel=\"stylesheet\" href=\"{}\">", escape_html(&mount.external(&ui.css_url))))
+        .replace("{{title}}", &escape_html(&title))
+        .replace("{{banner}}", &banner)
+        .replace("{{breadcrumb}}", &breadcrumb(req, mount))
+        .replace("{{truncated}}", &truncated)
+        .replace("{{name_label}}", t(Msg::ColumnName))
+        .replace("{{size_label}}", t(Msg::ColumnSize))
+        .replace("{{modified_label}}", t(Msg::ColumnModified))
+        .replace("{{downloads_label}}", t(Msg::ColumnDownloads))
+        .replace("{{gallery}}", &gallery)
+        .replace("{{select_header}}", &select_header)
+        .replace("{{selection}}", &selection)
+        .replace("{{empty}}", &empty)
+        .replace("{{table_hidden}}", if total == 0 { " hidden" } else { "" })
+        .replace("{{rows}}", &rows)
+        .replace("{{pagination}}", &pagination(total, page, per_page, per_page_param, sort, desc))
+        .replace("{{watch_script}}", &watch_script);
+    // 点击当前排序列切换升降序，点击其他列按升序排列，请求中指定的每页数量保持不变
+    for key in SortKey::ALL {
+        let active = key == sort;
+        let order = if active && !desc { "desc" } else { "asc" };
+        let arrow = match (active, desc) {
+            (false, _) => "",
+            (true, false) => " ▲",
+            (true, true) => " ▼",
+        };
+        html = html
+            .replace(&format!("{{{{{}_link}}}}", key.as_str()), &list_link(key, order == "desc", None, per_page_param))
+            .replace(&format!("{{{{{}_arrow}}}}", key.as_str()), arrow);
+    }
+
+    let mut res = HttpResponse::Ok();
+    if let Some(ref csp) = mount.csp {
+        res.header(CONTENT_SECURITY_POLICY, csp.clone());
+    }
+    Ok(WebResponse::new(res.content_type("text/html; charset=utf-8").body(html), req.clone()))
+}
+
+// 请求头 Accept 为 application/json 时，目录请求返回 JSON 列表而不是 HTML
+#[derive(Clone)]
+pub struct JsonListing {
+    mounts: Rc<Vec<Mount>>,
+}
+
+impl JsonListing {
+    pub fn new(mounts: Vec<Mount>) -> Self {
+        JsonListing { mounts: Rc::new(mounts) }
+    }
+}
+
+impl<S> Middleware<S> for JsonListing {
+    type Service = JsonListingMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        JsonListingMiddleware {
+            service,
+            mounts: self.mounts.clone(),
+        }
+    }
+}
+
+pub struct JsonListingMiddleware<S> {
+    service: S,
+    mounts: Rc<Vec<Mount>>,
+}
+
+impl<S, E> Service<WebRequest<E>> for JsonListingMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let wants_json = matches!(*req.method(), Method::GET | Method::HEAD)
+            && req
+                .headers()
+                .get(ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.contains("application/json"));
+
+        let resolved = mount::find(&self.mounts, req.path()).and_then(|mount| Some((mount.resolve(req.path())?, mount.clone())));
+        let (dir, mount) = match resolved {
+            Some((dir, mount)) if wants_json && mount.listing && dir.is_dir() => (dir, mount),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let response = match web::block(move || read_entries(&dir, &mount)).await {
+            // 被截断时用响应头告知客户端，JSON 仍是条目数组
+            Ok((entries, truncated)) => {
+                let mut res = HttpResponse::Ok();
+                if truncated {
+                    res.header(TRUNCATED_HEADER, entries.len().to_string());
+                }
+                res.json(&entries)
+            }
+            Err(e) => {
+                log::error!("{}", tf(Msg::DirUnreadable, &[&req.path(), &e]));
+                HttpResponse::InternalServerError().finish()
+            }
+        };
+        Ok(req.into_response(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::test;
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn serves_index_file_and_falls_back_to_listing() {
+        let dir = TempDir::new();
+        dir.write("site/index.html", "<h1>site</h1>");
+        dir.write("plain/a.txt", "a");
+        let mut mount = dir.mount("/download/files");
+        mount.index_file = Some("index.html".to_string());
+        let app = test::init_service(web::App::new().service(testutil::files(&mount))).await;
+        let get = |uri: &str| test::TestRequest::with_uri(uri).to_request();
+
+        let body = test::read_response(&app, get("/download/files/site/")).await;
+        assert_eq!(body, "<h1>site</h1>");
+
+        let res = test::call_service(&app, get("/download/files/site")).await;
+        assert_eq!(res.status(), StatusCode::FOUND);
+        assert_eq!(res.headers().get(LOCATION).unwrap(), "/download/files/site/");
+
+        let body = test::read_response(&app, get("/download/files/plain/")).await;
+        assert!(String::from_utf8_lossy(&body).contains("a.txt"));
+    }
+
+    #[ntex::test]
+    async fn no_listing_hides_directories_but_serves_files() {
+        let dir = TempDir::new();
+        dir.write("site/index.html", "<h1>site</h1>");
+        dir.write("plain/a.txt", "a");
+        let mut mount = dir.mount("/download/files");
+        mount.index_file = Some("index.html".to_string());
+        mount.listing = false;
+        let app = test::init_service(web::App::new().service(testutil::files(&mount))).await;
+        let get = |uri: &str| test::TestRequest::with_uri(uri).to_request();
+
+        let res = test::call_service(&app, get("/download/files/plain/")).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let body = test::read_response(&app, get("/download/files/plain/a.txt")).await;
+        assert_eq!(body, "a");
+        let body = test::read_response(&app, get("/download/files/site/")).await;
+        assert_eq!(body, "<h1>site</h1>");
+    }
+
+    #[ntex::test]
+    async fn escapes_custom_title_and_banner() {
+        let dir = TempDir::new();
+        dir.write("a.txt", "a");
+        let mut mount = dir.mount("/download/files");
+        mount.title = Some("<b>共享</b>".to_string());
+        mount.banner = Some("内部文件 <script>".to_string());
+        let app = test::init_service(web::App::new().service(testutil::files(&mount))).await;
+
+        let body = test::read_response(&app, test::TestRequest::with_uri("/download/files/").to_request()).await;
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<title>&lt;b&gt;共享&lt;/b&gt;</title>"));
+        assert!(body.contains("<div class=\"banner\">内部文件 &lt;script&gt;</div>"));
+    }
+
+    #[ntex::test]
+    async fn crafted_file_name_is_rendered_inert() {
+        let dir = TempDir::new();
+        dir.write("<img src=x onerror=alert(1)>.txt", "a");
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(web::App::new().service(testutil::files(&mount))).await;
+
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/").to_request()).await;
+        assert_eq!(res.headers().get(CONTENT_SECURITY_POLICY).unwrap(), mount::DEFAULT_CSP);
+        let body = String::from_utf8_lossy(&test::read_body(res).await).into_owned();
+        assert!(!body.contains("<img src=x"));
+        assert!(body.contains("&lt;img src=x onerror=alert(1)&gt;.txt"));
+        assert!(body.contains("%3Cimg%20src%3Dx%20onerror%3Dalert%281%29%3E.txt"));
+    }
+
+    #[ntex::test]
+    async fn links_are_prefixed_with_base_path() {
+        let dir = TempDir::new();
+        dir.write("sub/a.txt", "a");
+        let mut mount = dir.mount("/download/files");
+        mount.base_path = "/share".to_string();
+        let app = test::init_service(web::App::new().service(testutil::files(&mount))).await;
+
+        let body = test::read_response(&app, test::TestRequest::with_uri("/download/files/sub/").to_request()).await;
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("href=\"/share/download/files/sub/a.txt\""));
+        assert!(body.contains("href=\"/share/download/files/\""));
+        assert!(body.contains("href=\"/share/_fileshare/listing.css"));
+    }
+
+    #[ntex::test]
+    async fn empty_directory_shows_empty_state() {
+        let dir = TempDir::new();
+        dir.write("docs/.hidden", "h");
+        let mut mount = dir.mount("/download/files");
+        mount.upload = true;
+        let app = test::init_service(web::App::new().service(testutil::files(&mount))).await;
+
+        // 只有隐藏文件的目录同样算空目录，显示返回上级目录的链接，上传入口只在根目录显示
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/docs/").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = String::from_utf8_lossy(&test::read_body(res).await).into_owned();
+        assert!(body.contains(&format!("<div class=\"empty\"><p>{} <a href=\"/download/files/\">{}</a></p></div>", t(Msg::EmptyDir), t(Msg::ParentDir))));
+        assert!(body.contains("<table hidden>"));
+        assert!(!body.contains("class=\"dropzone\""));
+
+        std::fs::remove_dir_all(dir.path().join("docs")).unwrap();
+        let body = test::read_response(&app, test::TestRequest::with_uri("/download/files/").to_request()).await;
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains(t(Msg::EmptyDir)));
+        assert!(body.contains("<a class=\"dropzone\" href=\"/download/files/upload\">"));
+    }
+
+    #[ntex::test]
+    async fn listing_stops_at_max_entries() {
+        let dir = TempDir::new();
+        for i in 0..5 {
+            dir.write(&format!("{}.txt", i), "a");
+        }
+        let mut mount = dir.mount("/download/files");
+        mount.max_entries = 3;
+        let app = test::init_service(web::App::new().wrap(JsonListing::new(vec![mount.clone()])).service(testutil::files(&mount))).await;
+
+        let body = test::read_response(&app, test::TestRequest::with_uri("/download/files/").to_request()).await;
+        let body = String::from_utf8_lossy(&body);
+        assert_eq!(body.matches(".txt</a>").count(), 3);
+        assert!(body.contains(&tf(Msg::ListingTruncated, &[&3])));
+
+        let req = test::TestRequest::with_uri("/download/files/").header(ACCEPT, "application/json").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get(TRUNCATED_HEADER).unwrap(), "3");
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&test::read_body(res).await).unwrap();
+        assert_eq!(entries.len(), 3);
+    }
+}
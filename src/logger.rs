@@ -0,0 +1,146 @@
+use env_logger::Env;
+use log::{Level, Log, Metadata, Record};
+
+#[cfg(not(unix))]
+use crate::i18n::{Msg, t};
+
+// --syslog 可选的 facility，顺序与 syslog 协议中的编号一致，local0 起为 16
+pub const SYSLOG_FACILITIES: [&str; 20] = [
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron", "authpriv", "ftp", "local0", "local1", "local2",
+    "local3", "local4", "local5", "local6", "local7",
+];
+
+fn facility_code(name: &str) -> u8 {
+    match SYSLOG_FACILITIES.iter().position(|facility| *facility == name) {
+        Some(i) if i >= 12 => (i + 4) as u8,
+        Some(i) => i as u8,
+        None => 1,
+    }
+}
+
+// 同时输出到终端和 syslog 的日志；各输出共用 RUST_LOG 和 --log-level 决定的级别，
+// 访问日志文件(--access-log)由访问日志中间件在同一级别下写入
+struct Logger {
+    // 负责级别过滤和终端输出的格式
+    env: env_logger::Logger,
+    stdout: bool,
+    syslog: Option<Syslog>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.env.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.env.matches(record) {
+            return;
+        }
+        if self.stdout {
+            self.env.log(record);
+        }
+        if let Some(ref syslog) = self.syslog {
+            syslog.send(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.env.flush();
+    }
+}
+
+// 初始化全局日志；连接 syslog 失败时只输出到终端，返回连接错误，由调用方在设置好日志级别后给出警告
+pub fn init(stdout: bool, syslog_facility: Option<&str>) -> Option<std::io::Error> {
+    let env = env_logger::Builder::from_env(Env::default().default_filter_or("trace")).build();
+    let (syslog, syslog_error) = match syslog_facility.map(Syslog::connect) {
+        Some(Ok(syslog)) => (Some(syslog), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+    let stdout = stdout || (syslog_facility.is_some() && syslog.is_none());
+    log::set_max_level(env.filter());
+    let _ = log::set_boxed_logger(Box::new(Logger { env, stdout, syslog }));
+    syslog_error
+}
+
+// 通过本机的 syslog 套接字发送日志，格式为 RFC 3164: <优先级>FileShare[进程号]: 消息
+#[cfg(unix)]
+struct Syslog {
+    socket: std::os::unix::net::UnixDatagram,
+    facility: u8,
+}
+
+#[cfg(unix)]
+impl Syslog {
+    // Linux 为 /dev/log，macOS 为 /var/run/syslog，FreeBSD 为 /var/run/log
+    const PATHS: [&str; 3] = ["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+    fn connect(facility: &str) -> std::io::Result<Self> {
+        Self::connect_to(facility, &Self::PATHS)
+    }
+
+    fn connect_to<P: AsRef<std::path::Path>>(facility: &str, paths: &[P]) -> std::io::Result<Self> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        let mut last_error = std::io::Error::from(std::io::ErrorKind::NotFound);
+        for path in paths {
+            match socket.connect(path) {
+                Ok(()) => {
+                    return Ok(Syslog {
+                        socket,
+                        facility: facility_code(facility),
+                    });
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    // syslog 守护进程暂时不可用时丢弃该条日志，不能在日志输出中再记录日志
+    fn send(&self, record: &Record<'_>) {
+        let severity = match record.level() {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug | Level::Trace => 7,
+        };
+        let message = format!("<{}>FileShare[{}]: {}", self.facility * 8 + severity, std::process::id(), record.args());
+        let _ = self.socket.send(message.as_bytes());
+    }
+}
+
+#[cfg(not(unix))]
+struct Syslog;
+
+#[cfg(not(unix))]
+impl Syslog {
+    fn connect(_facility: &str) -> std::io::Result<Self> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, t(Msg::SyslogUnsupported)))
+    }
+
+    fn send(&self, _record: &Record<'_>) {}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::net::UnixDatagram;
+
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn sends_rfc3164_messages_with_facility_and_severity() {
+        let dir = TempDir::new();
+        let path = dir.path().join("log.sock");
+        let server = UnixDatagram::bind(&path).unwrap();
+        let syslog = Syslog::connect_to("local3", &[dir.path().join("missing.sock"), path]).unwrap();
+
+        syslog.send(&Record::builder().level(Level::Warn).args(format_args!("磁盘空间不足")).build());
+        let mut buf = [0; 256];
+        let len = server.recv(&mut buf).unwrap();
+        // local3 = 19，19 * 8 + 4(warning) = 156
+        assert_eq!(String::from_utf8_lossy(&buf[..len]), format!("<156>FileShare[{}]: 磁盘空间不足", std::process::id()));
+
+        assert!(Syslog::connect_to("user", &[dir.path().join("missing.sock")]).is_err());
+    }
+}
@@ -1,28 +1,590 @@
+#![recursion_limit = "2048"]
+
 use ntex::{web};
-use ntex_files::Files;
-use env_logger::Env;
-use clap::Parser;
+use ntex_files::{Directory, Files};
+use clap::{CommandFactory, FromArgMatches, Parser};
 use std::num::NonZeroUsize;
+use i18n::{Msg, t, tf};
+
+mod accesslog;
+mod archive;
+mod availability;
+mod auth;
+mod cache;
+mod charset;
+mod chunk;
+mod checksum;
+mod compress;
+mod config;
+mod connlimit;
+mod daemon;
+mod cors;
+mod favicon;
+mod filter;
+mod glob;
+mod delete;
+mod dirsize;
+mod duration;
+mod disk;
+mod disposition;
+mod downloads;
+mod expiry;
+mod head;
+mod headers;
+mod health;
+mod i18n;
+mod icons;
+mod hidden;
+mod inflight;
+mod ipfilter;
+mod listing;
+mod logger;
+mod manifest;
+mod methods;
+mod metrics;
+mod mime;
+mod mkdir;
+mod mount;
+mod netif;
+mod notfound;
+mod perip;
+mod precompressed;
+mod preview;
+mod proxy;
+mod qr;
+mod query;
+mod range;
+mod readonly;
+mod redirect;
+mod referer;
+mod reload;
+mod rename;
+mod requestid;
+mod search;
+mod share;
+mod shutdown;
+mod single;
+mod size;
+mod stat;
+mod symlink;
+#[cfg(test)]
+mod testutil;
+mod throttle;
+mod thumb;
+mod timeout;
+mod tls;
+mod uds;
+mod ui;
+mod upload;
+mod version;
+mod watch;
+
+// 支持的日志级别
+const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+// 支持的访问日志格式
+const LOG_FORMATS: [&str; 2] = ["text", "json"];
+
+// 未指定 --max-connections 时每个工作线程的连接数上限，与 ntex 的默认值相同
+const DEFAULT_MAXCONN: usize = 25_600;
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = "这是一个高性能的静态文件服务器，支持文件列表查看和下载。\n使用示例：FileShare --port 8080")]
+// --backlog 的上限，更大的值会被系统截断，多半是写错了
+const MAX_BACKLOG: i32 = 65_535;
+
+#[derive(Parser, Debug, Clone, serde::Serialize)]
+#[command(version, about, long_about = "这是一个高性能的静态文件服务器，支持文件列表查看和下载。\n使用示例：FileShare --port 8080\n每个参数也可以用FILESHARE_加大写参数名的环境变量设置，如FILESHARE_PORT=8080、FILESHARE_FILE_DIR=/data，开关型参数取1/0或true/false；\n同一参数的优先级依次为：命令行 > 环境变量 > 配置文件(--config) > 默认值。\n启动失败时的退出码：参数或配置错误为2，共享目录错误为3，监听失败为4，TLS证书错误为5，其他错误为1。")]
 struct Args {
-    #[arg(short, long, default_value = "files", help = "指定文件目录，默认为files。")]
+    #[arg(short, long, default_value = "files", help = "指定文件目录，默认为files；指向普通文件时只在URL路径上提供这一个文件的下载。")]
     file_dir: String,
 
     #[arg(short, long, default_value = "/download/files", help="指定URL路径，默认为/download/files。")]
     url_path: String,
 
-    #[arg(short, long, default_value = "info", value_parser = ["trace", "debug", "info", "warn", "error"], help="指定日志级别，默认为info。")]
+    #[arg(short, long, default_value = "info", value_parser = LOG_LEVELS, help="指定日志级别，默认为info。")]
     log_level: String,
 
+    #[arg(short, long, conflicts_with = "verbose", help = "安静模式，日志级别固定为error，不输出运行参数和启动信息。")]
+    quiet: bool,
+
+    #[arg(short, long, help = "详细模式，日志级别固定为debug。")]
+    verbose: bool,
+
+    #[arg(long, help = "不接管panic输出：默认程序崩溃时通过日志(含syslog)记录崩溃的线程、位置和原因，指定后恢复Rust默认的标准错误输出。")]
+    no_panic_hook: bool,
+
     #[arg(short, long, default_value_t = 8080, help="指定端口，默认为8080。")]
     port: u16,
 
+    #[arg(long, default_value = "0.0.0.0", help = "指定监听地址，支持IPv4和IPv6，默认为0.0.0.0。")]
+    host: String,
+
     #[arg(short, long, default_value_t = default_worker_count(), help = format!("指定工作线程数，默认为系统核心数({})。", default_worker_count()))]
     worker: usize,
+
+    #[arg(long, default_value_t = DEFAULT_BLOCKING_THREADS, value_parser = clap::value_parser!(u32).range(1..), help = "每个工作线程用于文件读写等阻塞操作的线程池上限，至少为1，默认为512；各工作线程的线程池相互独立，合计最多为该值乘以工作线程数。")]
+    blocking_threads: u32,
+
+    #[arg(long, default_value = "64KB", value_parser = chunk::parse_chunk_size, help = "发送文件时每次读取的块大小，支持KB、MB等单位，范围为4KB到16MB；高延迟、高带宽的网络上调大可提高大文件的传输速度，默认为64KB。")]
+    chunk_size: u64,
+
+    #[arg(long, help = "指定TLS证书文件(PEM格式)，需与--tls-key同时使用以启用HTTPS。")]
+    tls_cert: Option<String>,
+
+    #[arg(long, help = "指定TLS私钥文件(PEM格式)，需与--tls-cert同时使用以启用HTTPS。")]
+    tls_key: Option<String>,
+
+    #[arg(long, default_value = "1.2", value_parser = tls::TLS_VERSIONS, help = "HTTPS接受的最低TLS版本(1.2或1.3)，更低版本的客户端在握手时被拒绝，默认为1.2。")]
+    tls_min_version: String,
+
+    #[arg(long = "tls-1.3-only", help = "只接受TLS 1.3，等同于--tls-min-version 1.3。")]
+    tls13_only: bool,
+
+    #[arg(long, help = "指定访问用户名，需与--password同时使用以启用Basic认证。")]
+    username: Option<String>,
+
+    #[arg(long, help = "指定访问密码，需与--username同时使用以启用Basic认证。")]
+    #[serde(serialize_with = "config::serialize_secret")]
+    password: Option<String>,
+
+    #[arg(long, help = "指定外部认证程序，挂载点下的请求把Authorization头通过环境变量HTTP_AUTHORIZATION和标准输入交给该程序，退出码为0时放行，非0时返回401，程序无法运行或超时(10秒)时返回503；认证通过的凭据缓存30秒。不能与Basic认证同时使用。")]
+    auth_command: Option<String>,
+
+    #[arg(long, help = "指定TOML配置文件，命令行参数优先于配置文件。")]
+    config: Option<String>,
+
+    #[arg(long, value_parser = parse_mount, help = "挂载目录到URL路径，格式为/url=/dir，可重复指定。指定后忽略--file-dir和--url-path。写成/url=/dir:用户名:密码时该挂载点单独要求Basic认证(密码不能含:)，未指定账号的挂载点使用--username/--password，都未指定时不需认证。")]
+    #[serde(serialize_with = "config::serialize_mounts")]
+    mount: Vec<mount::MountSpec>,
+
+    #[arg(long, help = "启用文件上传，POST multipart/form-data 到 {URL路径}/upload，在浏览器中打开该地址可拖放上传；大文件可加?name=文件名&offset=N分块上传，最后加?finalize=1&size=总大小完成。")]
+    enable_upload: bool,
+
+    #[arg(long, help = "启用目录打包下载，在目录URL后加?zip=1即可下载ZIP压缩包；列表中可勾选多个文件，提交到{URL路径}/zip(GET或POST表单，参数file=相对路径，可重复)打包下载。")]
+    enable_zip: bool,
+
+    #[arg(long, default_value = "deflate", value_parser = archive::ZIP_COMPRESSIONS, help = "打包下载时文件的压缩方式：stored不压缩，deflate压缩，auto对图片、视频、压缩包等已压缩的格式直接存储、其他文件压缩；默认为deflate。")]
+    zip_compression: String,
+
+    #[arg(long, value_parser = ipfilter::parse_cidr, help = "允许访问的地址段(CIDR)，可重复指定。")]
+    #[serde(serialize_with = "config::serialize_display")]
+    allow: Vec<ipnet::IpNet>,
+
+    #[arg(long, value_parser = ipfilter::parse_cidr, help = "禁止访问的地址段(CIDR)，可重复指定，优先于--allow。")]
+    #[serde(serialize_with = "config::serialize_display")]
+    deny: Vec<ipnet::IpNet>,
+
+    #[arg(long, help = "信任反向代理，从X-Forwarded-For或X-Real-IP获取客户端地址，用于日志、限流和访问控制。")]
+    trust_proxy: bool,
+
+    #[arg(long, default_value = "30s", value_parser = duration::parse_duration, help = "收到退出信号后等待请求完成的时长，如30s、2m，不带单位时按秒计算，默认为30s。")]
+    #[serde(serialize_with = "config::serialize_duration")]
+    shutdown_timeout: std::time::Duration,
+
+    #[arg(long, default_value = "0", value_parser = duration::parse_duration, help = "连续该时长没有请求时自动优雅关闭，如10m、1h，不带单位时按秒计算；进行中的请求和下载会推迟关闭，默认为0即不自动关闭。")]
+    #[serde(serialize_with = "config::serialize_duration")]
+    idle_shutdown: std::time::Duration,
+
+    #[arg(long, help = "启动时在终端输出局域网访问地址的二维码。")]
+    qr: bool,
+
+    #[arg(long, help = "局域网访问地址和二维码中使用的地址，原样使用，如在容器中运行时指定宿主机的IP或域名；默认自动检测。")]
+    public_ip: Option<String>,
+
+    #[arg(long, help = "指定自定义404页面(HTML文件)，默认使用内置页面。")]
+    not_found_page: Option<String>,
+
+    #[arg(long, help = "启用响应压缩，根据Accept-Encoding使用gzip或brotli。")]
+    compress: bool,
+
+    #[arg(long, help = "客户端接受gzip/brotli且文件旁边有同名的.gz/.br文件时，直接发送预压缩文件。")]
+    precompressed: bool,
+
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(1..=9), help = "指定压缩级别(1-9)，越大压缩率越高但越慢，默认为6。")]
+    compress_level: u32,
+
+    #[arg(long, help = "健康检查(/healthz)时同时检查共享目录是否存在且可读。")]
+    health_check_dir: bool,
+
+    #[arg(long, help = "启用Prometheus指标接口(/metrics)和磁盘空间接口(/diskinfo)。")]
+    enable_metrics: bool,
+
+    #[arg(long, help = "显示并允许访问 . 开头的隐藏文件和目录，默认隐藏。")]
+    show_hidden: bool,
+
+    #[arg(long, default_value = "0", value_parser = size::parse_size, help = "限制每个连接的下载速率(字节/秒)，支持KB、MB等单位，如5MB，默认为0即不限速。")]
+    rate_limit: u64,
+
+    #[arg(long, help = "启动后在默认浏览器中打开访问地址。")]
+    open: bool,
+
+    #[arg(long, help = "端口被占用时自动尝试后面的端口。")]
+    auto_port: bool,
+
+    #[arg(long, default_value_t = 10, help = "启用--auto-port时最多尝试的端口数，默认为10。")]
+    auto_port_tries: u16,
+
+    #[arg(long, default_value = "text", value_parser = LOG_FORMATS, help = "指定访问日志格式(text或json)，默认为text。")]
+    log_format: String,
+
+    #[arg(long, help = "同时将访问日志写入指定文件。")]
+    access_log: Option<String>,
+
+    #[arg(long, value_parser = logger::SYSLOG_FACILITIES, help = "同时将日志发送到本机syslog，参数为facility，如daemon、local0；连接失败时只输出到终端。")]
+    syslog: Option<String>,
+
+    #[arg(long, help = "不在终端输出日志，可与--syslog、--access-log配合使用。")]
+    no_stdout_log: bool,
+
+    #[arg(long, default_value = "0", value_parser = size::parse_size, help = "访问日志文件超过该大小时轮转，支持KB、MB等单位，默认为0即不轮转。")]
+    access_log_max_size: u64,
+
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..), help = "4xx响应的访问日志每N条只记录一条，避免被扫描时日志暴增；其他响应总是记录，默认为1即全部记录。")]
+    log_sample: u64,
+
+    #[arg(long, default_value = "0", value_parser = duration::parse_duration, help = "请求从开始到响应发送完毕的耗时超过该时长时输出一条警告，包含路径、状态码、字节数和耗时，如2s、500ms，便于发现磁盘或客户端卡顿；不受访问日志采样的影响，默认为0即不检查。")]
+    #[serde(serialize_with = "config::serialize_duration")]
+    slow_request_threshold: std::time::Duration,
+
+    #[arg(long, help = "为文件响应设置Cache-Control头，如max-age=3600；设为no-store时完全禁用缓存。")]
+    cache_control: Option<String>,
+
+    #[arg(long, help = "只读模式，禁用上传等所有写操作，优先于各功能自身的开关。")]
+    read_only: bool,
+
+    #[arg(long, help = "允许跨域访问的来源，如https://app.example.com，可重复指定，*表示任意来源。")]
+    cors_origin: Vec<String>,
+
+    #[arg(long, value_delimiter = ',', value_parser = methods::parse_method, help = "允许的HTTP方法，以逗号分隔，其他方法返回405；默认为GET,HEAD，开启上传、删除等功能时自动加上对应方法，设置--cors-origin时总是允许OPTIONS。")]
+    allowed_methods: Option<Vec<String>>,
+
+    #[arg(long, help = "启用文件删除，DELETE {URL路径}/<文件>，删除目录需加?recursive=true。")]
+    enable_delete: bool,
+
+    #[arg(long, help = "启用新建目录，POST {URL路径}/<目录>?action=mkdir 或 MKCOL {URL路径}/<目录>。")]
+    enable_mkdir: bool,
+
+    #[arg(long, help = "允许通过符号链接访问共享目录之外的文件，默认禁止。")]
+    follow_symlinks: bool,
+
+    #[arg(long, help = "监听指定的Unix套接字，指定后不再监听TCP端口。")]
+    unix_socket: Option<String>,
+
+    #[arg(long, value_parser = uds::parse_mode, help = "指定Unix套接字文件的权限(八进制)，如660。")]
+    #[serde(serialize_with = "config::serialize_mode")]
+    socket_mode: Option<u32>,
+
+    #[arg(long, help = "只监听IPv6，--host为通配地址时不再接受IPv4连接。")]
+    ipv6_only: bool,
+
+    #[arg(long, value_parser = headers::parse_header, help = "为所有响应添加自定义响应头，格式为\"名称: 值\"，可重复指定。")]
+    #[serde(serialize_with = "config::serialize_headers")]
+    header: Vec<(ntex::http::header::HeaderName, ntex::http::header::HeaderValue)>,
+
+    #[arg(long, default_value_t = 100, help = "文件名搜索(目录URL后加?search=关键字)最多返回的结果数，默认为100。")]
+    search_max_results: usize,
+
+    #[arg(long, default_value_t = 1000, help = "目录列表每页显示的条目数，可用?page=N&per_page=M翻页，默认为1000，0表示不分页。")]
+    page_size: usize,
+
+    #[arg(long, default_value_t = 0, help = "目录列表最多读取的条目数，读到上限即停止并提示其余条目可通过搜索查找，防止条目极多的目录耗尽内存；与分页无关，默认为0即不限制。")]
+    listing_max_entries: usize,
+
+    #[arg(long, default_value = "zh", value_parser = i18n::LANGS, help = "指定启动日志、错误响应、运行日志和目录列表的语言(zh、en或auto)，auto根据LANG环境变量选择，默认为zh。")]
+    lang: String,
+
+    #[arg(long, default_value_t = 0, help = "限制所有工作线程合计的同时连接数，超出时新连接返回503，默认为0即不限制。")]
+    max_connections: usize,
+
+    #[arg(long, default_value_t = 0, help = "限制同一客户端地址同时进行的请求数，超出时返回429，默认为0即不限制。")]
+    max_per_ip: usize,
+
+    #[arg(long, default_value = "0", value_parser = duration::parse_duration, help = "请求头未在该时长内收完或处理超时未返回响应时中止请求并返回408，如30s，不带单位时按秒计算；已开始传输的下载不受影响，默认为0即使用ntex的默认设置。")]
+    #[serde(serialize_with = "config::serialize_duration")]
+    request_timeout: std::time::Duration,
+
+    #[arg(long, help = "在后台运行(仅Unix)，脱离终端，标准输出和错误写入--access-log指定的文件。")]
+    daemon: bool,
+
+    #[arg(long, help = "绑定成功后将进程号写入指定文件，退出时删除。")]
+    pid_file: Option<String>,
+
+    #[arg(long, help = "启用图片缩略图，图片URL后加?thumb=200获取缩略图，目录列表以网格显示图片；缩略图缓存在共享目录的.thumbs下。")]
+    thumbnails: bool,
+
+    #[arg(long, default_value = "1MB", value_parser = size::parse_size, help = "文件URL后加?preview=1可在浏览器中预览文本和Markdown文件，超过该大小的文件照常下载，默认为1MB。")]
+    preview_max_size: u64,
+
+    #[arg(long, help = "分享链接的签名密钥，设置后登录用户可在文件URL后加?share=秒数生成限时分享链接，持有链接者无需账号密码即可下载。")]
+    #[serde(serialize_with = "config::serialize_secret")]
+    share_secret: Option<String>,
+
+    #[arg(long, help = "指定网站图标文件(/favicon.ico)，默认使用内置图标。")]
+    favicon: Option<String>,
+
+    #[arg(long, help = "防盗链：只允许来自这些主机的页面引用文件，如example.com或*.example.com，可重复指定。")]
+    allowed_referer: Vec<String>,
+
+    #[arg(long, help = "设置--allowed-referer时，允许没有Referer的文件请求(如直接打开链接或命令行下载)。")]
+    allow_empty_referer: bool,
+
+    #[arg(long, help = "启用TLS时为所有响应添加Strict-Transport-Security头，要求浏览器只用HTTPS访问。")]
+    hsts: bool,
+
+    #[arg(long, default_value = "365d", value_parser = duration::parse_duration, help = "HSTS的max-age，如180d，不带单位时按秒计算，默认为365d(一年)。")]
+    #[serde(serialize_with = "config::serialize_duration")]
+    hsts_max_age: std::time::Duration,
+
+    #[arg(long, help = "在目录列表中显示每个子目录的总大小，目录较深时统计较慢，结果缓存30秒。")]
+    show_dir_size: bool,
+
+    #[arg(long, default_value_t = 10, help = "统计目录大小时最多进入的子目录层数，0表示不限，默认为10。")]
+    dir_size_max_depth: usize,
+
+    #[arg(long, help = "启动时从该JSON文件读取各文件的下载次数，退出时写回，不指定时计数只保存在内存中。")]
+    download_stats_file: Option<String>,
+
+    #[arg(long, help = "所有文件默认作为附件下载而不是在浏览器中打开，文件URL后加?download=0可改为直接打开。")]
+    force_download: bool,
+
+    #[arg(long, value_parser = mime::parse_mime, help = "按扩展名指定文件响应的Content-Type，格式为ext=type，如log=text/plain，可重复指定。")]
+    #[serde(serialize_with = "config::serialize_mimes")]
+    mime: Vec<(String, ntex::http::header::HeaderValue)>,
+
+    #[arg(long, help = "启动时读取Apache格式的mime.types文件(每行一个类型，后跟以空白分隔的扩展名)，按其中的映射设置文件响应的Content-Type，--mime指定的映射优先。")]
+    mime_db: Option<String>,
+
+    #[arg(long, default_value = "utf-8", value_parser = charset::parse_charset, help = "text/*类型的文件响应中附加的字符集，如gbk，默认为utf-8。")]
+    text_charset: String,
+
+    #[arg(long, value_parser = charset::parse_ext_charset, help = "按扩展名指定文本文件的字符集，格式为ext=charset，如txt=gbk，优先于--text-charset，可重复指定。")]
+    #[serde(serialize_with = "config::serialize_ext_charsets")]
+    ext_charset: Vec<(String, String)>,
+
+    #[arg(long, help = "指定客户端CA证书(PEM格式)，启用双向TLS，只接受出示了由该CA签发的证书的客户端，需同时启用HTTPS。")]
+    client_ca: Option<String>,
+
+    #[arg(long, help = "启用目录变化推送，列表页通过WebSocket连接{URL路径}/ws，文件新建、删除或修改时自动刷新。")]
+    enable_watch: bool,
+
+    #[arg(long, help = "在HTTPS监听上通过ALPN启用HTTP/2，需同时启用TLS，默认只使用HTTP/1.1。")]
+    http2: bool,
+
+    #[arg(long, default_value = "5s", value_parser = duration::parse_duration, help = "空闲连接保持的时长，如5s、1m，不带单位时按秒计算；为0时每个请求结束后关闭连接，默认为5s。")]
+    #[serde(serialize_with = "config::serialize_duration")]
+    keep_alive: std::time::Duration,
+
+    #[arg(long, default_value_t = 1024, help = "监听套接字等待接受的连接队列长度，范围1-65535，默认为1024；实际值不超过系统上限(如Linux的somaxconn)。")]
+    backlog: i32,
+
+    #[arg(long, help = "只检查参数和配置(目录、TLS证书、地址段、响应头等)后退出，不监听端口；检查通过时退出码为0，否则按失败原因为2(参数或配置)、3(共享目录)、5(TLS证书)或1(其他)。")]
+    check: bool,
+
+    #[arg(long, help = "以JSON格式输出合并命令行、配置文件和默认值后的最终配置后退出，密码等敏感值显示为***。")]
+    #[serde(skip)]
+    print_config: bool,
+
+    #[arg(long, help = "启用重命名和移动，POST {URL路径}/<文件>?action=rename&to=<新名称>，目标已存在时需加&overwrite=true。")]
+    enable_rename: bool,
+
+    #[arg(long, default_value = "name", value_parser = listing::SORT_KEYS, help = "目录列表的默认排序列(name、size或modified)，请求中的?sort=参数优先，默认为name。")]
+    default_sort: String,
+
+    #[arg(long, default_value = "asc", value_parser = listing::SORT_ORDERS, help = "目录列表的默认排序方向(asc或desc)，请求中的?order=参数优先，默认为asc。")]
+    default_order: String,
+
+    #[arg(long, help = "自定义界面文件所在目录，其中的listing.html、listing.css、watch.js、select.js替换内置版本，缺少的文件仍使用内置版本。")]
+    ui_dir: Option<String>,
+
+    #[arg(long, value_parser = duration::parse_duration, help = "定期删除修改时间早于该时长的文件(如12h、7d)，目录和隐藏文件不会被删除，默认不删除；不能与--read-only同时使用。")]
+    #[serde(serialize_with = "config::serialize_duration")]
+    max_file_age: Option<std::time::Duration>,
+
+    #[arg(long, default_value = "10m", value_parser = duration::parse_duration, help = "检查过期文件的间隔，默认为10m。")]
+    #[serde(serialize_with = "config::serialize_duration")]
+    cleanup_interval: std::time::Duration,
+
+    #[arg(long, value_parser = glob::parse_glob, help = "不会因过期被删除的文件，按相对于共享目录的路径匹配的通配符，如*.keep，可重复指定。")]
+    keep_glob: Vec<String>,
+
+    #[arg(long, value_parser = ipfilter::parse_cidr, help = "只接受来自这些地址段(CIDR)的转发头，可重复指定，需同时指定--trust-proxy；默认信任任何来源。")]
+    #[serde(serialize_with = "config::serialize_display")]
+    trusted_proxy: Vec<ipnet::IpNet>,
+
+    #[arg(long, default_value = "0", value_parser = size::parse_size, help = "限制整个服务器所有下载合计的速率(字节/秒)，各连接平分，与--rate-limit同时设置时较严格的一个生效，默认为0即不限速。")]
+    total_rate_limit: u64,
+
+    #[arg(long, value_parser = glob::parse_glob, help = "只在列表中显示并允许下载匹配的文件，按相对于共享目录的路径匹配的通配符，如*.txt，可重复指定；目录总是显示。")]
+    include_glob: Vec<String>,
+
+    #[arg(long, value_parser = glob::parse_glob, help = "从列表中隐藏并禁止访问匹配的文件和目录，如**/secret/*，可重复指定，优先于--include-glob。")]
+    exclude_glob: Vec<String>,
+
+    #[arg(long, value_parser = glob::parse_extension, help = "总是隐藏并以404拒绝这些扩展名的文件，如pem、key，不区分大小写，可重复指定，优先于--include-glob。")]
+    deny_ext: Vec<String>,
+
+    #[arg(long, default_value_t = 0, help = "共享目录以下最多可以列出或下载的层数，更深的路径返回404，打包下载、搜索和目录大小统计也不会进入；如1表示只能访问根目录中的文件，默认为0即不限制。")]
+    max_depth: usize,
+
+    #[arg(long, help = "访问目录时如果其中有首页文件(见--index-file)则返回该文件而不是文件列表，没有时仍显示列表。")]
+    serve_index: bool,
+
+    #[arg(long, default_value = "index.html", value_parser = listing::parse_index_file, help = "--serve-index使用的首页文件名，默认为index.html。")]
+    index_file: String,
+
+    #[arg(long, help = "运行中共享目录被删除时自动重新创建，与启动时目录不存在的处理相同；默认只记录警告，请求返回503。")]
+    recreate_dir: bool,
+
+    #[arg(long, help = "允许共享根目录、用户主目录和/etc等系统目录，默认拒绝启动，以免写错--file-dir时暴露整个文件系统。")]
+    allow_unsafe_dir: bool,
+
+    #[arg(long, default_value = "2GB", value_parser = size::parse_size, help = "单次上传的大小上限，支持KB、MB等单位，超过时返回413并删除已写入的部分；设为0不限制，默认为2GB。")]
+    max_upload_size: u64,
+
+    #[arg(long, help = "不显示目录列表，访问目录时返回404，只能按已知的地址下载文件；打包下载、搜索和目录变化推送也一并禁用，--serve-index的首页不受影响。")]
+    no_listing: bool,
+
+    #[arg(long, help = "目录列表中不显示文件类型图标，只显示文字。")]
+    no_icons: bool,
+
+    #[arg(long, default_value = "0", value_parser = size::parse_size, help = "磁盘可用空间低于该值时启动时给出警告，并以507拒绝上传，支持KB、MB等单位，默认为0即不检查。")]
+    min_free_space: u64,
+
+    #[arg(long, help = "目录列表页的标题，同时显示在页面顶部，默认为当前访问的路径。")]
+    title: Option<String>,
+
+    #[arg(long, help = "在每个目录列表页顶部显示的提示文字，如\"内部文件，请勿外传\"，默认不显示。")]
+    banner: Option<String>,
+
+    #[arg(long, default_value = mount::DEFAULT_CSP, value_parser = listing::parse_csp, help = "目录列表页和预览页的Content-Security-Policy响应头，默认为\"default-src 'self'\"，只允许加载本站的资源；设为空字符串不发送。")]
+    csp: String,
+
+    #[arg(long, default_value = "", value_parser = mount::parse_base_path, help = "经反向代理挂在子路径下时的外部路径前缀，如/share，代理转发时会去掉该前缀；只用于生成列表中的链接和跳转地址，路由仍按--url-path匹配。")]
+    base_path: String,
+}
+
+impl Args {
+    // --quiet 和 --verbose 优先于 --log-level
+    fn effective_log_level(&self) -> &str {
+        match (self.quiet, self.verbose) {
+            (true, _) => "error",
+            (_, true) => "debug",
+            _ => &self.log_level,
+        }
+    }
+
+    // 未指定 --allowed-methods 时总是允许 GET 和 HEAD，再按开启的写操作加上对应的方法；设置了 --cors-origin 时加上预检用的 OPTIONS
+    fn effective_methods(&self) -> Vec<String> {
+        let mut methods = match self.allowed_methods {
+            Some(ref methods) => methods.clone(),
+            None => {
+                let mut methods = vec!["GET", "HEAD"];
+                if !self.read_only {
+                    if self.enable_upload || self.enable_mkdir || self.enable_rename {
+                        methods.push("POST");
+                    }
+                    if self.enable_delete {
+                        methods.push("DELETE");
+                    }
+                    if self.enable_mkdir {
+                        methods.push("MKCOL");
+                    }
+                }
+                methods.into_iter().map(String::from).collect()
+            }
+        };
+        if !self.cors_origin.is_empty() && !methods.iter().any(|method| method == "OPTIONS") {
+            methods.push("OPTIONS".to_string());
+        }
+        methods
+    }
+
+    // --tls-1.3-only 优先于 --tls-min-version
+    fn tls_min_version(&self) -> &str {
+        if self.tls13_only { "1.3" } else { &self.tls_min_version }
+    }
+
+    // 所有挂载点，未指定 --mount 时使用 --url-path 和 --file-dir
+    fn mounts(&self) -> Vec<mount::Mount> {
+        let mut mounts = if self.mount.is_empty() {
+            vec![mount::Mount::new(&self.url_path, &self.file_dir)]
+        } else {
+            self.mount
+                .iter()
+                .map(|spec| {
+                    let mut mount = mount::Mount::new(&spec.url, &spec.dir);
+                    mount.credentials = spec.credentials.as_ref().map(|(user, pass)| format!("{}:{}", user, pass).into_bytes());
+                    mount
+                })
+                .collect()
+        };
+        for mount in &mut mounts {
+            mount.show_hidden = self.show_hidden;
+            mount.follow_symlinks = self.follow_symlinks;
+            mount.page_size = self.page_size;
+            mount.max_entries = self.listing_max_entries;
+            mount.thumbnails = self.thumbnails;
+            mount.show_dir_size = self.show_dir_size;
+            mount.dir_size_max_depth = self.dir_size_max_depth;
+            mount.watch = self.enable_watch;
+            mount.default_sort = self.default_sort.clone();
+            mount.default_desc = self.default_order == "desc";
+            // 只共享单个文件时没有忽略文件
+            let filter = glob::PathFilter::new(&self.include_glob, &self.exclude_glob)
+                .with_denied_extensions(&self.deny_ext)
+                .with_max_depth(self.max_depth);
+            mount.filter = match mount.dir.is_file() {
+                true => filter,
+                false => filter.with_ignore_file(mount.dir.join(glob::IGNORE_FILE)),
+            };
+            mount.index_file = self.serve_index.then(|| self.index_file.clone());
+            mount.listing = !self.no_listing;
+            mount.icons = !self.no_icons;
+            mount.zip = self.enable_zip;
+            mount.upload = self.enable_upload && !self.read_only;
+            mount.title = self.title.clone();
+            mount.banner = self.banner.clone();
+            mount.base_path = self.base_path.clone();
+            mount.csp = ntex::http::header::HeaderValue::from_str(&self.csp).ok().filter(|csp| !csp.is_empty());
+        }
+        mounts
+    }
+}
+
+// 解析 /url=/dir 或 /url=/dir:用户名:密码 格式的挂载点；
+// 从右侧拆分账号，目录中只有一个 : 时(如 Windows 的 C:\dir)仍视为目录
+fn parse_mount(value: &str) -> Result<mount::MountSpec, String> {
+    let (url, dir) = value
+        .split_once('=')
+        .ok_or_else(|| format!("挂载点 {} 格式错误，应为/url=/dir", value))?;
+    let (dir, credentials) = match dir.rsplitn(3, ':').collect::<Vec<_>>()[..] {
+        [pass, user, dir] if !dir.is_empty() => {
+            if user.is_empty() || pass.is_empty() {
+                return Err(format!("挂载点 {} 的用户名和密码不能为空", value));
+            }
+            (dir, Some((user.to_string(), pass.to_string())))
+        }
+        _ => (dir, None),
+    };
+    if !url.starts_with('/') {
+        return Err(format!("挂载点 {} 的URL路径必须以/开头", value));
+    }
+    if dir.is_empty() {
+        return Err(format!("挂载点 {} 缺少目录", value));
+    }
+    let url = match url.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+    Ok(mount::MountSpec {
+        url: url.to_string(),
+        dir: dir.to_string(),
+        credentials,
+    })
 }
 
+// 与 tokio 的默认值相同
+const DEFAULT_BLOCKING_THREADS: u32 = 512;
+
 // 默认工作线程数函数
 fn default_worker_count() -> usize {
     // 使用 available_parallelism 获取系统并行度
@@ -33,12 +595,254 @@ fn default_worker_count() -> usize {
 
 // 参数输出
 fn print_args(args: &Args) {
-    println!("运行参数:");
-    println!("  文件目录: {}", args.file_dir);
-    println!("  URL路径: {}", args.url_path);
-    println!("  日志级别: {}", args.log_level);
-    println!("  端口: {}", args.port);
-    println!("  工作线程数: {}", args.worker);
+    let line = |label: Msg, value: &dyn std::fmt::Display| println!("  {}: {}", t(label), value);
+    let enabled = |on: bool| t(if on { Msg::Enabled } else { Msg::Disabled });
+    let yes_no = |on: bool| t(if on { Msg::Yes } else { Msg::No });
+    let or_unset = |value: Option<&str>| value.unwrap_or(t(Msg::Unset)).to_string();
+
+    println!("{}", t(Msg::ArgsTitle));
+    line(Msg::FileDir, &args.file_dir);
+    line(Msg::UrlPath, &args.url_path);
+    for spec in &args.mount {
+        match spec.credentials {
+            Some((ref user, _)) => line(Msg::MountPoint, &tf(Msg::MountWithAuth, &[&spec.url, &spec.dir, user])),
+            None => line(Msg::MountPoint, &format!("{} -> {}", spec.url, spec.dir)),
+        }
+    }
+    line(Msg::LogLevel, &args.effective_log_level());
+    line(Msg::Verbose, &yes_no(args.verbose));
+    line(Msg::PanicHook, &enabled(!args.no_panic_hook));
+    line(Msg::Port, &args.port);
+    line(Msg::UnixSocket, &or_unset(args.unix_socket.as_deref()));
+    line(Msg::SocketMode, &args.socket_mode.map_or_else(|| t(Msg::Unset).to_string(), |mode| format!("{:o}", mode)));
+    line(Msg::Host, &args.host);
+    line(Msg::Ipv6Only, &yes_no(args.ipv6_only));
+    line(Msg::Worker, &args.worker);
+    line(Msg::ChunkSize, &tf(Msg::Bytes, &[&args.chunk_size]));
+    line(Msg::BlockingThreads, &tf(Msg::BlockingThreadsValue, &[&args.blocking_threads, &(args.blocking_threads as usize).saturating_mul(args.worker)]));
+    line(Msg::KeepAlive, &if args.keep_alive.is_zero() { t(Msg::Disabled).to_string() } else { humantime::format_duration(args.keep_alive).to_string() });
+    line(Msg::Backlog, &args.backlog);
+    line(Msg::TlsCert, &or_unset(args.tls_cert.as_deref()));
+    line(Msg::TlsKey, &or_unset(args.tls_key.as_deref()));
+    line(Msg::TlsMinVersion, &args.tls_min_version());
+    line(Msg::ClientCa, &or_unset(args.client_ca.as_deref()));
+    line(Msg::Http2, &enabled(args.http2));
+    line(Msg::Username, &or_unset(args.username.as_deref()));
+    line(Msg::Password, &or_unset(args.password.as_ref().map(|_| "******")));
+    line(Msg::AuthCommand, &or_unset(args.auth_command.as_deref()));
+    line(Msg::ConfigFile, &or_unset(args.config.as_deref()));
+    line(Msg::CheckOnly, &yes_no(args.check));
+    line(Msg::Upload, &enabled(args.enable_upload));
+    line(Msg::MinFreeSpace, &if args.min_free_space == 0 { t(Msg::Unset).to_string() } else { tf(Msg::Bytes, &[&args.min_free_space]) });
+    line(Msg::MaxUploadSize, &if args.max_upload_size == 0 { t(Msg::Unlimited).to_string() } else { tf(Msg::Bytes, &[&args.max_upload_size]) });
+    line(Msg::Delete, &enabled(args.enable_delete));
+    line(Msg::Mkdir, &enabled(args.enable_mkdir));
+    line(Msg::Rename, &enabled(args.enable_rename));
+    line(Msg::Zip, &enabled(args.enable_zip));
+    line(Msg::ZipCompression, &args.zip_compression);
+    line(Msg::Watch, &enabled(args.enable_watch));
+    line(Msg::Allow, &join_or_unset(&args.allow));
+    line(Msg::Deny, &join_or_unset(&args.deny));
+    line(Msg::TrustProxy, &yes_no(args.trust_proxy));
+    line(Msg::TrustedProxy, &join_or_unset(&args.trusted_proxy));
+    line(Msg::ShutdownTimeout, &humantime::format_duration(args.shutdown_timeout));
+    line(Msg::IdleShutdown, &if args.idle_shutdown.is_zero() { t(Msg::Disabled).to_string() } else { humantime::format_duration(args.idle_shutdown).to_string() });
+    line(Msg::PublicIp, &or_unset(args.public_ip.as_deref()));
+    line(Msg::Qr, &enabled(args.qr));
+    line(Msg::NotFoundPage, &args.not_found_page.as_deref().unwrap_or(t(Msg::BuiltIn)));
+    line(Msg::HealthCheckDir, &yes_no(args.health_check_dir));
+    line(Msg::Metrics, &enabled(args.enable_metrics));
+    line(Msg::ShowHidden, &yes_no(args.show_hidden));
+    line(Msg::FollowSymlinks, &yes_no(args.follow_symlinks));
+    line(Msg::RateLimit, &if args.rate_limit == 0 { t(Msg::Unlimited).to_string() } else { tf(Msg::BytesPerSecond, &[&args.rate_limit]) });
+    line(Msg::TotalRateLimit, &if args.total_rate_limit == 0 { t(Msg::Unlimited).to_string() } else { tf(Msg::BytesPerSecond, &[&args.total_rate_limit]) });
+    line(Msg::Open, &yes_no(args.open));
+    line(Msg::LogFormat, &args.log_format);
+    line(Msg::AccessLog, &or_unset(args.access_log.as_deref()));
+    line(Msg::Syslog, &or_unset(args.syslog.as_deref()));
+    line(Msg::StdoutLog, &enabled(!args.no_stdout_log));
+    line(Msg::SlowRequestThreshold, &if args.slow_request_threshold.is_zero() { t(Msg::Disabled).to_string() } else { humantime::format_duration(args.slow_request_threshold).to_string() });
+    line(Msg::LogSample, &if args.log_sample <= 1 { t(Msg::LogAll).to_string() } else { tf(Msg::LogSampleValue, &[&args.log_sample]) });
+    line(Msg::AccessLogMaxSize, &if args.access_log_max_size == 0 { t(Msg::NoRotate).to_string() } else { tf(Msg::Bytes, &[&args.access_log_max_size]) });
+    line(Msg::AutoPort, &if args.auto_port { tf(Msg::AutoPortTries, &[&args.auto_port_tries]) } else { t(Msg::No).to_string() });
+    line(Msg::CacheControl, &or_unset(args.cache_control.as_deref()));
+    line(Msg::ReadOnly, &yes_no(args.read_only));
+    line(Msg::CorsOrigin, &join_or_unset(&args.cors_origin));
+    line(Msg::AllowedMethods, &args.effective_methods().join(", "));
+    for (name, value) in &args.header {
+        line(Msg::CustomHeader, &format!("{}: {}", name, value.to_str().unwrap_or(t(Msg::NonAscii))));
+    }
+    line(Msg::MaxFileAge, &args.max_file_age.map_or_else(|| t(Msg::Disabled).to_string(), |age| tf(Msg::MaxFileAgeValue, &[&humantime::format_duration(age), &humantime::format_duration(args.cleanup_interval)])));
+    line(Msg::KeepGlob, &join_or_unset(&args.keep_glob));
+    line(Msg::IncludeGlob, &join_or_unset(&args.include_glob));
+    line(Msg::ExcludeGlob, &join_or_unset(&args.exclude_glob));
+    line(Msg::DenyExt, &join_or_unset(&args.deny_ext));
+    line(Msg::MaxDepth, &if args.max_depth == 0 { t(Msg::Unlimited).to_string() } else { args.max_depth.to_string() });
+    line(Msg::UiDir, &args.ui_dir.as_deref().unwrap_or(t(Msg::BuiltIn)));
+    line(Msg::DefaultSort, &format!("{} {}", args.default_sort, args.default_order));
+    line(Msg::RecreateDir, &yes_no(args.recreate_dir));
+    line(Msg::AllowUnsafeDir, &yes_no(args.allow_unsafe_dir));
+    line(Msg::Listing, &enabled(!args.no_listing));
+    line(Msg::Icons, &enabled(!args.no_icons));
+    line(Msg::ListingTitleArg, &or_unset(args.title.as_deref()));
+    line(Msg::Banner, &or_unset(args.banner.as_deref()));
+    line(Msg::BasePath, &or_unset(Some(args.base_path.as_str()).filter(|path| !path.is_empty())));
+    line(Msg::Csp, &or_unset(Some(args.csp.as_str()).filter(|csp| !csp.is_empty())));
+    line(Msg::ServeIndex, &if args.serve_index { args.index_file.clone() } else { t(Msg::Disabled).to_string() });
+    line(Msg::PageSize, &if args.page_size == 0 { t(Msg::NoPaging).to_string() } else { args.page_size.to_string() });
+    line(Msg::ListingMaxEntries, &if args.listing_max_entries == 0 { t(Msg::Unlimited).to_string() } else { args.listing_max_entries.to_string() });
+    line(Msg::SearchMaxResults, &args.search_max_results);
+    line(Msg::Language, &args.lang);
+    line(Msg::MaxConnections, &if args.max_connections == 0 { t(Msg::Unlimited).to_string() } else { args.max_connections.to_string() });
+    line(Msg::MaxPerIp, &if args.max_per_ip == 0 { t(Msg::Unlimited).to_string() } else { args.max_per_ip.to_string() });
+    line(Msg::RequestTimeout, &if args.request_timeout.is_zero() { t(Msg::Unset).to_string() } else { humantime::format_duration(args.request_timeout).to_string() });
+    line(Msg::Daemon, &yes_no(args.daemon));
+    line(Msg::PidFile, &or_unset(args.pid_file.as_deref()));
+    line(Msg::Thumbnails, &enabled(args.thumbnails));
+    line(Msg::PreviewMaxSize, &tf(Msg::Bytes, &[&args.preview_max_size]));
+    line(Msg::ShareSecret, &or_unset(args.share_secret.as_ref().map(|_| "******")));
+    line(Msg::Favicon, &args.favicon.as_deref().unwrap_or(t(Msg::BuiltIn)));
+    line(Msg::AllowedReferer, &join_or_unset(&args.allowed_referer));
+    line(Msg::AllowEmptyReferer, &yes_no(args.allow_empty_referer));
+    line(Msg::Hsts, &if args.hsts { format!("max-age={}", args.hsts_max_age.as_secs()) } else { t(Msg::Disabled).to_string() });
+    line(Msg::ShowDirSize, &enabled(args.show_dir_size));
+    line(Msg::DirSizeMaxDepth, &if args.dir_size_max_depth == 0 { t(Msg::Unlimited).to_string() } else { args.dir_size_max_depth.to_string() });
+    line(Msg::DownloadStatsFile, &or_unset(args.download_stats_file.as_deref()));
+    line(Msg::ForceDownload, &yes_no(args.force_download));
+    for (ext, mime) in &args.mime {
+        line(Msg::MimeType, &format!("{} -> {}", ext, mime.to_str().unwrap_or(t(Msg::NonAscii))));
+    }
+    line(Msg::MimeDb, &or_unset(args.mime_db.as_deref()));
+    line(Msg::TextCharset, &args.text_charset);
+    for (ext, charset) in &args.ext_charset {
+        line(Msg::ExtCharset, &format!("{} -> {}", ext, charset));
+    }
+    line(Msg::Precompressed, &enabled(args.precompressed));
+    line(Msg::Compress, &if args.compress { tf(Msg::CompressLevel, &[&args.compress_level]) } else { t(Msg::Disabled).to_string() });
+}
+
+// 列表参数输出，为空时显示未设置
+fn join_or_unset<T: std::fmt::Display>(values: &[T]) -> String {
+    if values.is_empty() {
+        i18n::t(i18n::Msg::Unset).to_string()
+    } else {
+        values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+// 退出码按失败原因区分，便于进程管理工具判断是否值得重启：参数或配置错误为2(与clap相同)，
+// 共享目录错误为3，监听失败为4，TLS证书错误为5，其他错误为1
+#[derive(Clone, Copy)]
+enum Exit {
+    Failure = 1,
+    Args = 2,
+    Dir = 3,
+    Bind = 4,
+    Tls = 5,
+}
+
+// 输出错误并以对应的退出码退出
+fn exit_with_error(exit: Exit, msg: impl std::fmt::Display) -> ! {
+    log::error!("{}", msg);
+    std::process::exit(exit as i32)
+}
+
+// 日志尚未初始化时输出错误并退出
+fn exit_before_logging(exit: Exit, msg: impl std::fmt::Display) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(exit as i32)
+}
+
+// 替换默认的 panic 输出，通过日志记录崩溃的线程、位置和原因，输出到 syslog 时同样可见；
+// 设置了 RUST_BACKTRACE 时附上调用栈
+fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("?");
+        let location = info.location().map_or_else(String::new, |location| location.to_string());
+        let thread = std::thread::current();
+        log::error!("{}", tf(Msg::Panicked, &[&thread.name().unwrap_or("?"), &message, &location]));
+        let backtrace = std::backtrace::Backtrace::capture();
+        if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            log::error!("{}", backtrace);
+        }
+    }));
+}
+
+// Linux 等系统上没有 DISPLAY 或 WAYLAND_DISPLAY 时无法启动浏览器
+fn has_display() -> bool {
+    if cfg!(all(unix, not(target_os = "macos"))) {
+        std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some()
+    } else {
+        true
+    }
+}
+
+// 绑定监听端口，端口被占用时依次尝试后面的端口，最多尝试 tries 个
+fn bind_listener(host: std::net::IpAddr, port: u16, tries: u16, ipv6_only: bool, backlog: i32) -> std::io::Result<std::net::TcpListener> {
+    let mut port = port;
+    let mut tries = tries;
+    loop {
+        match bind_tcp(host, port, ipv6_only, backlog) {
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && tries > 1 && port < u16::MAX => {
+                log::warn!("{}", tf(Msg::PortInUse, &[&port, &(port + 1)]));
+                port += 1;
+                tries -= 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// 通配地址改为监听 [::]，ipv6_only 为 false 时同一个套接字同时接受 IPv4 连接；
+// 系统未启用 IPv6 时退回只监听 0.0.0.0。标准库无法指定 backlog，统一用 socket2 创建
+fn bind_tcp(host: std::net::IpAddr, port: u16, ipv6_only: bool, backlog: i32) -> std::io::Result<std::net::TcpListener> {
+    if !host.is_unspecified() {
+        return listen_tcp(std::net::SocketAddr::from((host, port)), None, backlog);
+    }
+    match listen_tcp(std::net::SocketAddr::from((std::net::Ipv6Addr::UNSPECIFIED, port)), Some(ipv6_only), backlog) {
+        Err(e) if !ipv6_only && e.kind() != std::io::ErrorKind::AddrInUse => {
+            listen_tcp(std::net::SocketAddr::from((std::net::Ipv4Addr::UNSPECIFIED, port)), None, backlog)
+        }
+        result => result,
+    }
+}
+
+// 创建、绑定并开始监听 TCP 套接字，only_v6 不为空时设置 IPV6_V6ONLY
+fn listen_tcp(addr: std::net::SocketAddr, only_v6: Option<bool>, backlog: i32) -> std::io::Result<std::net::TcpListener> {
+    let socket = socket2::Socket::new(socket2::Domain::for_address(addr), socket2::Type::STREAM, None)?;
+    if let Some(only_v6) = only_v6 {
+        socket.set_only_v6(only_v6)?;
+    }
+    // 与标准库一致，Unix 上允许重用处于 TIME_WAIT 的端口
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(backlog)?;
+    Ok(socket.into())
+}
+
+// 监听方式：TCP 端口或 Unix 套接字
+enum Listener {
+    Tcp(std::net::TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+#[cfg(unix)]
+fn bind_unix_socket(path: &str, mode: Option<u32>, backlog: i32) -> Listener {
+    uds::bind(std::path::Path::new(path), mode, backlog)
+        .map(Listener::Unix)
+        .unwrap_or_else(|e| exit_with_error(Exit::Bind, tf(Msg::BindUnixFailed, &[&path, &e])))
+}
+
+#[cfg(not(unix))]
+fn bind_unix_socket(_path: &str, _mode: Option<u32>, _backlog: i32) -> Listener {
+    exit_with_error(Exit::Args, t(Msg::UnixUnsupported))
 }
 
 // 局域网ip
@@ -46,9 +850,18 @@ fn get_local_ip() -> String {
     local_ipaddress::get().unwrap_or_else(|| "127.0.0.1".to_string())
 }
 
+// 局域网IPv6地址，通过向公网地址"连接"UDP套接字获取出口地址，不会真正发送数据
+fn get_local_ipv6() -> Option<std::net::Ipv6Addr> {
+    let socket = std::net::UdpSocket::bind((std::net::Ipv6Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect("[2001:4860:4860::8888]:80").ok()?;
+    match socket.local_addr().ok()?.ip() {
+        std::net::IpAddr::V6(ip) if !ip.is_loopback() && !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
 // 绝对路径
-fn get_absolute_path(path_str: &str) -> std::path::PathBuf {
-    let path = std::path::Path::new(path_str);
+fn get_absolute_path(path: &std::path::Path) -> std::path::PathBuf {
 
     if path.is_absolute() {
         // 如果已经是绝对路径，直接返回
@@ -58,52 +871,706 @@ fn get_absolute_path(path_str: &str) -> std::path::PathBuf {
         match std::env::current_dir() {
             Ok(current_dir) => current_dir.join(path),
             Err(_) => {
-                log::error!("无法获取当前目录，使用相对路径");
+                log::error!("{}", t(Msg::NoCurrentDir));
                 path.to_path_buf()
             }
         }
     }
 }
 
-#[ntex::main]
-async fn main() -> std::io::Result<()>{
-    let args = Args::parse();
+fn main() -> std::io::Result<()> {
+    let matches = config::with_env(Args::command()).get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    print_args(&args);
+    // 合并配置文件，此时日志尚未初始化，错误直接输出到 stderr
+    if let Some(path) = args.config.clone() {
+        match config::load(&path) {
+            Ok(file_config) => config::merge(&mut args, &matches, file_config),
+            Err(e) => exit_before_logging(Exit::Args, e),
+        }
+    }
+
+    i18n::init(i18n::Lang::parse(&args.lang));
+
+    // 配置文件中的值没有经过命令行的范围检查
+    if args.blocking_threads == 0 {
+        exit_before_logging(Exit::Args, t(Msg::ZeroBlockingThreads));
+    }
+    // 命令行上同时指定时由 clap 报错，这里处理来自配置文件的组合
+    if args.quiet && args.verbose {
+        exit_before_logging(Exit::Args, t(Msg::QuietWithVerbose));
+    }
+
+    if args.print_config {
+        let mounts: Vec<_> = args
+            .mounts()
+            .iter()
+            .map(|mount| (mount.url_path.clone(), get_absolute_path(&mount.dir)))
+            .collect();
+        let file_dir = get_absolute_path(std::path::Path::new(&args.file_dir));
+        match config::to_json(&args, &file_dir, &mounts) {
+            Ok(json) => println!("{}", json),
+            Err(e) => exit_before_logging(Exit::Failure, e),
+        }
+        return Ok(());
+    }
+
+    // fork 须在启动运行时之前进行，否则子进程中的运行时状态不可用
+    let daemon = (args.daemon && !args.check).then(|| {
+        daemon::daemonize(args.access_log.as_deref()).unwrap_or_else(|e| exit_before_logging(Exit::Failure, e))
+    });
+
+    // 主线程和每个工作线程各自创建运行时，阻塞操作的线程池大小由 --blocking-threads 决定
+    let blocking_threads = args.blocking_threads as usize;
+    ntex::rt::System::build()
+        .name("main")
+        .block_on(move |fut| {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .max_blocking_threads(blocking_threads)
+                .build()
+                .unwrap_or_else(|e| exit_before_logging(Exit::Failure, tf(Msg::RuntimeFailed, &[&e])));
+            tokio::task::LocalSet::new().block_on(&rt, fut);
+        })
+        .finish()
+        .block_on(run(args, matches, daemon))
+}
+
+// 启动时以一行日志列出各项功能是否启用，读取合并配置文件后的参数；按 info 级别输出，--log-level error 时不显示
+fn log_features(args: &Args) {
+    let tls = args.tls_cert.is_some();
+    let features = [
+        (Msg::FeatureTls, tls),
+        (Msg::ClientCa, tls && args.client_ca.is_some()),
+        (Msg::Http2, tls && args.http2),
+        (Msg::Hsts, tls && args.hsts),
+        (Msg::FeatureAuth, args.username.is_some() || args.auth_command.is_some() || args.mount.iter().any(|spec| spec.credentials.is_some())),
+        (Msg::FeatureIpFilter, !args.allow.is_empty() || !args.deny.is_empty()),
+        (Msg::ReadOnly, args.read_only),
+        (Msg::Upload, args.enable_upload && !args.read_only),
+        (Msg::Delete, args.enable_delete && !args.read_only),
+        (Msg::Mkdir, args.enable_mkdir && !args.read_only),
+        (Msg::Rename, args.enable_rename && !args.read_only),
+        (Msg::Listing, !args.no_listing),
+        (Msg::Zip, args.enable_zip && !args.no_listing),
+        (Msg::FeatureShareLinks, args.share_secret.is_some()),
+        (Msg::Compress, args.compress),
+        (Msg::RateLimit, args.rate_limit > 0 || args.total_rate_limit > 0),
+        (Msg::Thumbnails, args.thumbnails),
+        (Msg::Watch, args.enable_watch && !args.no_listing),
+        (Msg::ServeIndex, args.serve_index),
+        (Msg::MaxFileAge, args.max_file_age.is_some()),
+        (Msg::Metrics, args.enable_metrics),
+        (Msg::AccessLog, args.access_log.is_some()),
+    ];
+    let summary = features
+        .iter()
+        .map(|(label, on)| format!("{} {}", if *on { '✓' } else { '✗' }, t(*label)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    log::info!("{}", tf(Msg::Features, &[&summary]));
+}
+
+async fn run(mut args: Args, matches: clap::ArgMatches, daemon: Option<daemon::Daemon>) -> std::io::Result<()> {
+    if !args.quiet {
+        print_args(&args);
+    }
+
+    // 日志输出到终端和 syslog，未设置 RUST_LOG 时由 log::set_max_level 控制级别，SIGHUP 重新加载时可以调整
+    let syslog_error = logger::init(!args.no_stdout_log, args.syslog.as_deref());
+    if !args.no_panic_hook {
+        install_panic_hook();
+    }
+    reload::set_log_level(args.effective_log_level());
+    if let Some(e) = syslog_error {
+        log::warn!("{}", tf(Msg::SyslogFailed, &[&e]));
+    }
+    log_features(&args);
 
-    // 设置环境变量来启用日志
-    env_logger::init_from_env(Env::default().default_filter_or(&args.log_level));
+    // 同一个URL路径只能挂载一次
+    let mut mounts = args.mounts();
+    // 没有挂载在 / 时，访问 / 跳转到第一个挂载点；只共享单个文件时直接跳转到文件地址
+    let root_redirect = mounts
+        .first()
+        .filter(|_| mount::find(&mounts, "/").is_none())
+        .map(|mount| match mount.dir.is_file() {
+            true => redirect::RootRedirect(mount.external(&mount.url_path)),
+            false => redirect::RootRedirect(format!("{}/", mount.external(&mount.url_path).trim_end_matches('/'))),
+        });
+    // 按注册顺序排序前记下第一个挂载点，--open 时打开它
+    let first_url_path = mounts.first().map(|mount| mount.url_path.clone());
+    for (i, mount) in mounts.iter().enumerate() {
+        if mounts[..i].iter().any(|other| other.url_path == mount.url_path) {
+            exit_with_error(Exit::Args, tf(Msg::DuplicateMount, &[&mount.url_path]));
+        }
+    }
+    // 较长的前缀先注册，避免被 / 之类的短前缀抢先匹配
+    mounts.sort_by_key(|mount| std::cmp::Reverse(mount.url_path.len()));
+
+    // 目录不存在就创建，--check 时只报告不创建；指向普通文件时只共享该文件
+    for mount in &mut mounts {
+        if mount.dir.is_file() {
+            mount.single_file = true;
+            mount.listing = false;
+            mount.thumbnails = false;
+            mount.watch = false;
+            log::info!("{}", tf(Msg::SingleFile, &[&mount.dir.display()]));
+            continue;
+        }
+        if !mount.dir.exists() && args.check {
+            exit_with_error(Exit::Dir, tf(Msg::DirNotFound, &[&mount.dir.display()]));
+        }
+        if !mount.dir.exists() {
+            log::warn!("{}", tf(Msg::DirMissing, &[&mount.dir.display()]));
+            std::fs::create_dir_all(&mount.dir)
+                .unwrap_or_else(|e| exit_with_error(Exit::Dir, tf(Msg::CreateDirFailed, &[&mount.dir.display(), &e, &std::env::current_dir().unwrap_or_default().display()])));
+            log::info!("{}", tf(Msg::DirCreated, &[&mount.dir.display()]));
+        }
+        // 路径是普通文件或没有读权限时，启动后每个请求都会失败，提前退出并给出原因
+        if !mount.dir.is_dir() {
+            exit_with_error(Exit::Dir, tf(Msg::NotADirectory, &[&mount.dir.display()]));
+        }
+        if let Err(e) = std::fs::read_dir(&mount.dir) {
+            exit_with_error(Exit::Dir, tf(Msg::DirUnreadable, &[&mount.dir.display(), &e]));
+        }
+        // 写错 --file-dir 时可能把整个文件系统共享出去，系统目录须显式允许
+        if mount::is_sensitive_dir(&mount.dir) {
+            if !args.allow_unsafe_dir {
+                exit_with_error(Exit::Dir, tf(Msg::UnsafeDir, &[&mount.dir.display()]));
+            }
+            log::warn!("{}", tf(Msg::UnsafeDirAllowed, &[&mount.dir.display()]));
+        }
+        if mount::is_world_writable(&mount.dir) {
+            log::warn!("{}", tf(Msg::WorldWritableDir, &[&mount.dir.display()]));
+        }
+        if args.min_free_space > 0
+            && let Some(available) = disk::available(&mount.dir)
+            && available < args.min_free_space
+        {
+            log::warn!("{}", tf(Msg::LowDiskSpace, &[&mount.dir.display(), &available, &args.min_free_space]));
+        }
+    }
 
-    // 目录不存在就创建
-    if !std::path::Path::new(&args.file_dir).exists() {
-        log::warn!("目录 {} 不存在，正在创建...", &args.file_dir);
-        std::fs::create_dir_all(&args.file_dir)
-            .unwrap_or_else(|e| panic!("创建目录 {} 失败: {} (当前目录: {})", &args.file_dir, e, std::env::current_dir().unwrap_or_default().display()));
-        log::info!("创建目录 {} 成功", &args.file_dir);
+    // 证书和私钥必须成对出现，启动时就完成解析
+    let tls_config = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(tls::load_rustls_config(cert, key, args.client_ca.as_deref(), args.tls_min_version()).unwrap_or_else(|e| exit_with_error(Exit::Tls, e))),
+        (None, None) => None,
+        _ => exit_with_error(Exit::Args, t(Msg::TlsPair)),
+    };
+    if args.client_ca.is_some() && tls_config.is_none() {
+        exit_with_error(Exit::Args, t(Msg::ClientCaWithoutTls));
+    }
+    // HTTP/2 依赖 TLS 的 ALPN 协商，明文监听时无法启用
+    if args.http2 && tls_config.is_none() {
+        exit_with_error(Exit::Args, t(Msg::Http2WithoutTls));
+    }
+    let scheme = if tls_config.is_some() { "https" } else { "http" };
+    // 明文 HTTP 下的 HSTS 头会被浏览器忽略，不发送以免误以为已生效
+    if args.hsts && tls_config.is_none() {
+        log::warn!("{}", t(Msg::HstsWithoutTls));
     }
 
-    // 输出访问路径
-    log::info!("共享文件夹绝对路径：{}", get_absolute_path(&args.file_dir).display());
-    log::info!("本机访问地址：http://127.0.0.1:{}{}", args.port, &args.url_path);
-    log::info!("局域网访问地址：http://{}:{}{}", get_local_ip(), args.port, &args.url_path);
+    // 用户名和密码同样必须成对出现
+    if args.username.is_some() != args.password.is_some() {
+        exit_with_error(Exit::Args, t(Msg::AuthPair));
+    }
+
+    // 外部认证程序与 Basic 认证都读取 Authorization 头，不能同时使用
+    let basic_auth = args.username.is_some() || args.mount.iter().any(|spec| spec.credentials.is_some());
+    if let Some(command) = &args.auth_command {
+        if basic_auth {
+            exit_with_error(Exit::Args, t(Msg::AuthCommandWithBasic));
+        }
+        if !std::path::Path::new(command).is_file() {
+            exit_with_error(Exit::Args, tf(Msg::AuthCommandNotFound, &[command]));
+        }
+    }
+
+    // 浏览器不允许 Access-Control-Allow-Origin: * 与凭据同时使用
+    let any_auth = basic_auth || args.auth_command.is_some();
+    if args.cors_origin.iter().any(|origin| origin == "*") && any_auth {
+        exit_with_error(Exit::Args, t(Msg::CorsWildcardAuth));
+    }
+
+    let not_found_page = notfound::load(args.not_found_page.as_deref()).unwrap_or_else(|e| exit_with_error(Exit::Args, e));
+    let favicon = favicon::load(args.favicon.as_deref()).unwrap_or_else(|e| exit_with_error(Exit::Args, e));
+    ui::init(ui::load(args.ui_dir.as_deref()).unwrap_or_else(|e| exit_with_error(Exit::Args, e)));
+    let downloads = downloads::Downloads::load(args.download_stats_file.as_deref()).unwrap_or_else(|e| exit_with_error(Exit::Args, e));
+    // --mime 的映射放在后面，覆盖 --mime-db 中同一扩展名的映射
+    let mut mime_types = match args.mime_db.as_deref() {
+        Some(path) => mime::load_db(path).unwrap_or_else(|e| exit_with_error(Exit::Args, e)),
+        None => Vec::new(),
+    };
+    mime_types.extend(args.mime.iter().cloned());
+
+    let host: std::net::IpAddr = args
+        .host
+        .parse()
+        .unwrap_or_else(|_| exit_with_error(Exit::Args, tf(Msg::InvalidHost, &[&args.host])));
+    if args.ipv6_only && host.is_ipv4() && !host.is_unspecified() {
+        exit_with_error(Exit::Args, tf(Msg::Ipv6OnlyWithV4, &[&host]));
+    }
+    if !args.trusted_proxy.is_empty() && !args.trust_proxy {
+        exit_with_error(Exit::Args, t(Msg::TrustedProxyWithoutTrust));
+    }
+    if args.cleanup_interval.is_zero() {
+        exit_with_error(Exit::Args, t(Msg::ZeroCleanupInterval));
+    }
+    // 只读模式保证不写入共享目录，定期删除过期文件与之矛盾
+    if args.read_only && args.max_file_age.is_some() {
+        exit_with_error(Exit::Args, t(Msg::MaxFileAgeReadOnly));
+    }
+    if !(1..=MAX_BACKLOG).contains(&args.backlog) {
+        exit_with_error(Exit::Args, tf(Msg::InvalidBacklog, &[&args.backlog, &MAX_BACKLOG]));
+    }
+    if args.unix_socket.is_some() && tls_config.is_some() {
+        exit_with_error(Exit::Args, t(Msg::UnixWithTls));
+    }
+    let cache_control = args.cache_control.as_deref().map(|value| cache::parse(value).unwrap_or_else(|e| exit_with_error(Exit::Args, e)));
+
+    // 所有不依赖监听的检查到此完成，--check 时不绑定端口直接退出
+    if args.check {
+        log::info!("{}", t(Msg::CheckPassed));
+        return Ok(());
+    }
+
+    if args.read_only {
+        log::info!("{}", t(Msg::ReadOnlyEnabled));
+    }
+
+    // 重新加载配置时与启动时的参数比较，需在 --auto-port 改写端口之前保存
+    let startup_args = args.clone();
+
+    // 先绑定端口，后面输出的地址使用实际监听的端口；指定 --unix-socket 时只监听套接字
+    let mut dual_stack = false;
+    let listener = match args.unix_socket {
+        Some(ref path) => {
+            if config::is_explicit(&matches, "port") {
+                log::warn!("{}", tf(Msg::PortIgnored, &[&args.port]));
+            }
+            bind_unix_socket(path, args.socket_mode, args.backlog)
+        }
+        None => {
+            let tries = if args.auto_port { args.auto_port_tries.max(1) } else { 1 };
+            let listener = bind_listener(host, args.port, tries, args.ipv6_only, args.backlog)
+                .unwrap_or_else(|e| exit_with_error(Exit::Bind, tf(Msg::BindFailed, &[&args.port, &e])));
+            args.port = listener.local_addr().map_or(args.port, |addr| addr.port());
+            dual_stack = host.is_unspecified() && listener.local_addr().is_ok_and(|addr| addr.is_ipv6());
+            Listener::Tcp(listener)
+        }
+    };
+
+    // 输出访问路径，监听指定地址时只能通过该地址访问；Unix 套接字没有可供浏览器访问的地址。
+    // 监听通配地址时 IPv4 和 IPv6 都可访问，检测到局域网 IPv6 地址时一并输出
+    // 指定 --public-ip 时不再自动检测，否则列出各网络接口的地址，自动检测的结果不对时可从中选择
+    let lan_host = match args.public_ip {
+        Some(ref host) => Some(netif::url_host(host)),
+        None => {
+            let addresses = netif::addresses();
+            if host.is_unspecified() && args.unix_socket.is_none() && !addresses.is_empty() {
+                let list = addresses.iter().map(|(name, ip)| format!("{} {}", name, ip)).collect::<Vec<_>>().join(", ");
+                log::info!("{}", tf(Msg::DetectedAddresses, &[&list]));
+            }
+            (!args.ipv6_only).then(get_local_ip)
+        }
+    };
+    let local_ipv6 = if dual_stack { get_local_ipv6() } else { None };
+    for mount in &mounts {
+        log::info!("{}", tf(Msg::SharedDir, &[&get_absolute_path(&mount.dir).display()]));
+        if args.unix_socket.is_some() {
+            continue;
+        }
+        let url = if host.is_unspecified() {
+            let mut lan_url = None;
+            if args.ipv6_only {
+                log::info!("{}", tf(Msg::LocalUrl, &[&format!("{}://[::1]:{}{}", scheme, args.port, mount.url_path)]));
+            } else {
+                log::info!("{}", tf(Msg::LocalUrl, &[&format!("{}://127.0.0.1:{}{}", scheme, args.port, mount.url_path)]));
+            }
+            if let Some(ref lan_host) = lan_host {
+                let url = format!("{}://{}:{}{}", scheme, lan_host, args.port, mount.url_path);
+                log::info!("{}", tf(Msg::LanUrl, &[&url]));
+                lan_url = Some(url);
+            }
+            if let Some(ip) = local_ipv6 {
+                let url = format!("{}://[{}]:{}{}", scheme, ip, args.port, mount.url_path);
+                log::info!("{}", tf(Msg::LanUrlV6, &[&url]));
+                lan_url.get_or_insert(url);
+            }
+            lan_url.unwrap_or_else(|| format!("{}://[::1]:{}{}", scheme, args.port, mount.url_path))
+        } else {
+            let url = format!("{}://{}{}", scheme, std::net::SocketAddr::new(host, args.port), mount.url_path);
+            log::info!("{}", tf(Msg::Url, &[&url]));
+            url
+        };
+        if args.qr && !args.quiet {
+            qr::print(&url);
+        }
+    }
+
+    if let Some(ref path) = args.unix_socket {
+        log::info!("{}", tf(Msg::ListenUnix, &[path]));
+    }
+
+    // --open 打开第一个挂载点的本机地址
+    let browser_host = if !host.is_unspecified() {
+        host
+    } else if args.ipv6_only {
+        std::net::IpAddr::from(std::net::Ipv6Addr::LOCALHOST)
+    } else {
+        std::net::IpAddr::from([127, 0, 0, 1])
+    };
+    let browser_url = first_url_path
+        .filter(|_| args.unix_socket.is_none())
+        .map(|url_path| format!("{}://{}{}", scheme, std::net::SocketAddr::new(browser_host, args.port), url_path));
 
     // 在 move 闭包之前克隆需要的值
-    let url_path = args.url_path.clone();
-    let file_dir = args.file_dir.clone();
-    let port = args.port;
     let worker = args.worker;
+    let username = args.username.clone();
+    let password = args.password.clone();
+    let enable_upload = args.enable_upload;
+    let max_upload_size = args.max_upload_size;
+    let min_free_space = args.min_free_space;
+    let enable_zip = args.enable_zip;
+    let zip_compression = archive::ZipCompression::parse(&args.zip_compression);
+    let enable_delete = args.enable_delete;
+    let enable_mkdir = args.enable_mkdir;
+    let enable_rename = args.enable_rename;
+    let trust_proxy = proxy::TrustProxy::new(args.trust_proxy, args.trusted_proxy.clone());
+    let compress = args.compress;
+    let precompressed = args.precompressed;
+    let chunk_size = args.chunk_size;
+    let read_only = args.read_only;
+    let cors_origin = args.cors_origin.clone();
+    let allowed_referer = args.allowed_referer.clone();
+    let allow_empty_referer = args.allow_empty_referer;
+    let mut custom_headers = args.header.clone();
+    if args.hsts && tls_config.is_some() {
+        custom_headers.push((
+            ntex::http::header::STRICT_TRANSPORT_SECURITY,
+            ntex::http::header::HeaderValue::from_str(&format!("max-age={}; includeSubDomains", args.hsts_max_age.as_secs())).expect("max-age 为数字，总是有效的响应头"),
+        ));
+    }
+    let search_max_results = args.search_max_results;
+    let preview_max_size = args.preview_max_size;
+    let share_secret = args.share_secret.clone();
+    let force_download = args.force_download;
+    let text_charset = args.text_charset.clone();
+    let ext_charsets = args.ext_charset.clone();
+    let allowed_methods = args.effective_methods();
+    let compress_level = args.compress_level;
+    let health_check_dir = args.health_check_dir;
+    // 目录已在上面创建，这里无法监视时直接退出，避免列表页一直重连
+    // 只共享单个文件的挂载点没有目录可以监视、清理和检查
+    let dir_mounts: Vec<_> = mounts.iter().filter(|mount| !mount.single_file).cloned().collect();
+    let ignore_files: Vec<_> = dir_mounts.iter().filter_map(|mount| mount.filter.ignore_file().cloned()).collect();
+    let watch_hub = (args.enable_watch && !args.no_listing).then(|| watch::Hub::start(&dir_mounts).unwrap_or_else(|e| exit_with_error(Exit::Failure, e)));
+    let live = reload::Live::new(reload::Settings::from_args(&args));
+    let live_settings = live.clone();
+    let total_bucket = throttle::TotalBucket::default();
+    let log_format = accesslog::LogFormat::parse(&args.log_format);
+    let access_log = args.access_log.as_deref().map(|path| {
+        accesslog::LogFile::open(path, args.access_log_max_size)
+            .unwrap_or_else(|e| exit_with_error(Exit::Failure, tf(Msg::OpenAccessLogFailed, &[&path, &e])))
+    });
+    let log_sampler = accesslog::Sampler::new(args.log_sample);
+    let slow_request_threshold = Some(args.slow_request_threshold).filter(|threshold| !threshold.is_zero());
+    let digest_cache = checksum::DigestCache::new();
+    let dir_sizes = dirsize::DirSizes::new();
+    let upload_chunks = upload::ChunkLocks::new();
+    let metrics = args.enable_metrics.then(metrics::Metrics::new);
+    let conn_limit = connlimit::ConnLimit::new(args.max_connections);
+    let per_ip_limit = perip::PerIpLimit::new(args.max_per_ip, trust_proxy.clone());
+    let auth_command = args.auth_command.clone();
+    let auth_cache = auth::AuthCache::new();
+    let request_timeout = duration::seconds(args.request_timeout);
+    let in_flight = inflight::InFlight::new();
+    let in_flight_counter = in_flight.clone();
+    let download_stats = downloads.clone();
 
-    web::HttpServer::new(move || {
-        web::App::new()
-            .wrap(web::middleware::Logger::default())
-            .service(
-                Files::new(&url_path, &file_dir)
+    // 过期文件清理在独立线程中进行，退出时等待当前这一轮扫描结束
+    let cleaner = args
+        .max_file_age
+        .map(|max_age| expiry::Cleaner::start(&dir_mounts, max_age, args.cleanup_interval, glob::build(&args.keep_glob)));
+
+    // 运行中共享目录被删除时记录日志，开启 --recreate-dir 时重新创建
+    let dir_monitor = availability::Monitor::start(&dir_mounts, args.recreate_dir);
+
+    let app_factory = move || {
+        // 预检响应中列出的方法，OPTIONS 本身不需要列出
+        let cors_methods: Vec<&str> = allowed_methods.iter().map(String::as_str).filter(|method| *method != "OPTIONS").collect();
+        let mut app = web::App::new()
+            .wrap(hidden::HiddenPaths::new(mounts.clone(), not_found_page.clone(), dir_sizes.clone(), downloads.clone()))
+            .wrap(redirect::TrailingSlash::new(mounts.clone()))
+            .wrap(chunk::ChunkSize::new(mounts.clone(), chunk_size))
+            .wrap(precompressed::Precompressed::new(mounts.clone(), precompressed))
+            .wrap(range::RangeLength)
+            .wrap(range::IfRange::new(mounts.clone()))
+            .wrap(downloads::DownloadCounter::new(mounts.clone(), downloads.clone()))
+            .wrap(cache::CacheControl::new(cache_control.clone(), mounts.clone()))
+            .wrap(disposition::Disposition::new(mounts.clone(), force_download))
+            .wrap(mime::MimeOverride::new(&mime_types, mounts.clone()))
+            .wrap(charset::Charset::new(&text_charset, &ext_charsets, mounts.clone()))
+            .wrap(listing::JsonListing::new(mounts.clone()))
+            .wrap(checksum::Checksum::new(mounts.clone(), digest_cache.clone()))
+            .wrap(stat::Stat::new(mounts.clone()))
+            .wrap(thumb::Thumbnails::new(mounts.clone(), read_only))
+            .wrap(preview::Preview::new(mounts.clone(), preview_max_size))
+            .wrap(search::Search::new(mounts.clone(), search_max_results))
+            .wrap(manifest::Manifest::new(mounts.clone(), digest_cache.clone()))
+            .wrap(share::ShareLinks::new(mounts.clone(), share_secret.as_deref()))
+            .wrap(archive::ZipDownload::new(mounts.clone(), enable_zip, zip_compression))
+            .wrap(delete::Delete::new(mounts.clone(), enable_delete))
+            .wrap(mkdir::Mkdir::new(mounts.clone(), enable_mkdir))
+            .wrap(rename::Rename::new(mounts.clone(), enable_rename))
+            .wrap(filter::GlobFilter::new(mounts.clone(), not_found_page.clone()))
+            .wrap(symlink::SymlinkGuard::new(mounts.clone()))
+            .wrap(availability::DirGuard::new(mounts.clone()))
+            .wrap(referer::RefererCheck::new(mounts.clone(), &allowed_referer, allow_empty_referer))
+            .wrap(compress::Compress::new(compress, compress_level))
+            .wrap(throttle::Throttle::new(live.clone(), total_bucket.clone()))
+            .wrap(head::HeadBody)
+            .wrap(readonly::ReadOnly::new(read_only))
+            .wrap(auth::BasicAuth::new(&mounts, username.as_deref(), password.as_deref()))
+            .wrap(auth::CommandAuth::new(auth_command.as_deref(), &mounts, auth_cache.clone()))
+            .wrap(share::ShareToken::new(share_secret.as_deref()))
+            .wrap(cors::Cors::new(&cors_origin, &cors_methods, any_auth))
+            .wrap(methods::AllowedMethods::new(&allowed_methods))
+            .wrap(per_ip_limit.clone())
+            .wrap(ipfilter::IpFilter::new(live.clone(), trust_proxy.clone()))
+            .wrap(headers::CustomHeaders::new(custom_headers.clone()))
+            .wrap(timeout::RequestTimeout::new(request_timeout))
+            .wrap(conn_limit.clone())
+            .wrap(accesslog::AccessLog::new(log_format, trust_proxy.clone(), access_log.clone(), log_sampler.clone(), slow_request_threshold))
+            .wrap(requestid::RequestIds)
+            .wrap(metrics::Recorder::new(metrics.clone(), mounts.clone()))
+            .wrap(in_flight_counter.clone())
+            .state(not_found_page.clone())
+            .default_service(web::to(notfound::not_found));
+
+        // 健康检查、版本信息、网站图标和界面资源需在挂载点之前注册，避免被挂载在 / 的文件服务抢先匹配
+        app = app.service(
+            web::resource(health::PATH)
+                .state(health::HealthCheck {
+                    dirs: dir_mounts.iter().map(|mount| mount.dir.clone()).collect(),
+                    check_dir: health_check_dir,
+                })
+                .route(web::get().to(health::healthz)),
+        );
+        app = app.service(web::resource(version::PATH).route(web::get().to(version::version)));
+        app = app.service(web::resource(format!("{}/{{name}}", ui::PREFIX)).route(web::get().to(ui::asset)));
+        app = app.service(
+            web::resource(favicon::PATH)
+                .state(favicon.clone())
+                .route(web::get().to(favicon::favicon)),
+        );
+        if let Some(ref root_redirect) = root_redirect {
+            app = app.service(
+                web::resource("/")
+                    .state(root_redirect.clone())
+                    .route(web::get().to(redirect::root)),
+            );
+        }
+
+        if let Some(ref metrics) = metrics {
+            app = app.service(
+                web::resource(metrics::PATH)
+                    .state(metrics.clone())
+                    .route(web::get().to(metrics::metrics)),
+            );
+            app = app.service(
+                web::resource(disk::PATH)
+                    .state(disk::DiskInfo {
+                        mounts: mounts.iter().map(|mount| (mount.url_path.clone(), mount.dir.clone())).collect(),
+                    })
+                    .route(web::get().to(disk::diskinfo)),
+            );
+        }
+
+        // 每个挂载点注册上传、目录监视接口和文件服务，这些接口需在文件服务之前注册
+        for mount in &mounts {
+            if mount.single_file {
+                app = app.service(
+                    web::resource(&mount.url_path)
+                        .state(single::SingleFile { path: mount.dir.clone() })
+                        .route(web::get().to(single::serve))
+                        .route(web::head().to(single::serve)),
+                );
+                continue;
+            }
+            let listing_mount = mount.clone();
+            let listing_dir_sizes = dir_sizes.clone();
+            let listing_downloads = downloads.clone();
+            let upload_resource = web::resource(format!("{}/upload", mount.url_path.trim_end_matches('/')))
+                .state(upload::UploadTarget {
+                    mount: mount.clone(),
+                    enabled: enable_upload,
+                    read_only,
+                    chunks: upload_chunks.clone(),
+                    max_size: max_upload_size,
+                    min_free: min_free_space,
+                })
+                .route(web::post().guard(web::guard::fn_guard(upload::is_chunk)).to(upload::upload_chunk))
+                .route(web::post().to(upload::upload));
+            // 启用上传时 GET 同一地址返回拖放上传页面
+            app = app.service(if enable_upload { upload_resource.route(web::get().to(upload::upload_page)) } else { upload_resource });
+            if mount.zip && mount.listing {
+                app = app.service(
+                    web::resource(format!("{}/zip", mount.url_path.trim_end_matches('/')))
+                        .state(archive::ZipTarget {
+                            mount: mount.clone(),
+                            compression: zip_compression,
+                        })
+                        .route(web::get().to(archive::zip_selection))
+                        .route(web::post().to(archive::zip_selection)),
+                );
+            }
+            if let Some(ref hub) = watch_hub {
+                app = app.service(
+                    web::resource(format!("{}{}", mount.url_path.trim_end_matches('/'), watch::SUFFIX))
+                        .state(watch::WatchTarget {
+                            hub: hub.clone(),
+                            url_path: mount.external(&mount.url_path),
+                        })
+                        .route(web::get().to(watch::watch)),
+                );
+            }
+            app = app.service(
+                Files::new(&mount.url_path, &mount.dir)
                     .show_files_listing()
+                    .files_listing_renderer(move |dir: &Directory, req: &web::HttpRequest| listing::render_html(dir, req, &listing_mount, &listing_dir_sizes, &listing_downloads))
                     .disable_content_disposition(),
-            )
-    })
-    .workers(worker)
-    .bind(("0.0.0.0", port))?
-    .run()
-    .await
+            );
+        }
+        app
+    };
+
+    // 每个工作线程接受的连接也不超过上限，避免未发出请求的空闲连接耗尽文件描述符
+    let maxconn = if args.max_connections == 0 { DEFAULT_MAXCONN } else { args.max_connections };
+    let shutdown_timeout = duration::seconds(args.shutdown_timeout);
+    // --keep-alive 为0时每个请求结束后关闭连接；监听套接字已按 --backlog 创建，HttpServer 的设置与之保持一致
+    let keep_alive = match duration::seconds(args.keep_alive) {
+        seconds if seconds.is_zero() => ntex::http::KeepAlive::Disabled,
+        seconds => ntex::http::KeepAlive::Timeout(seconds),
+    };
+    let server = web::HttpServer::new(app_factory.clone())
+        .workers(worker)
+        .maxconn(maxconn)
+        .backlog(args.backlog)
+        .keep_alive(keep_alive)
+        .disable_signals()
+        .shutdown_timeout(shutdown_timeout);
+
+    // 请求头须在超时时间内收完，不因客户端持续发送少量数据而延长，防止慢速攻击
+    let server = if request_timeout.is_zero() {
+        server
+    } else {
+        server.headers_read_rate(request_timeout, request_timeout, u16::MAX)
+    };
+
+    // 收到信号后停止接收新连接，等待已有请求完成
+    let server = match (listener, tls_config) {
+        (Listener::Tcp(listener), Some(config)) => tls::serve(
+            app_factory,
+            listener,
+            config,
+            tls::ServeOptions {
+                workers: worker,
+                maxconn,
+                shutdown_timeout,
+                request_timeout,
+                keep_alive,
+                http2: args.http2,
+            },
+        )
+        .unwrap_or_else(|e| exit_with_error(Exit::Tls, tf(Msg::TlsListenFailed, &[&e]))),
+        (Listener::Tcp(listener), None) => server
+            .listen(listener)
+            .unwrap_or_else(|e| exit_with_error(Exit::Bind, tf(Msg::ListenFailed, &[&e])))
+            .run(),
+        #[cfg(unix)]
+        (Listener::Unix(listener), _) => server
+            .listen_uds(listener)
+            .unwrap_or_else(|e| exit_with_error(Exit::Bind, tf(Msg::ListenFailed, &[&e])))
+            .run(),
+    };
+
+    // 绑定成功后写入 PID 文件，后台运行时再通知父进程退出
+    if let Some(ref path) = args.pid_file
+        && let Err(e) = daemon::write_pid_file(path)
+    {
+        exit_with_error(Exit::Failure, tf(Msg::WritePidFileFailed, &[path, &e]));
+    }
+    if let Some(daemon) = daemon {
+        daemon.notify_ready();
+    }
+
+    // 绑定成功后再打开浏览器，没有图形界面时只给出警告
+    if args.open
+        && let Some(url) = browser_url
+    {
+        if !has_display() {
+            log::warn!("{}", t(Msg::NoDisplay));
+        } else {
+            match open::that_detached(&url) {
+                Ok(()) => log::info!("{}", tf(Msg::BrowserOpened, &[&url])),
+                Err(e) => log::warn!("{}", tf(Msg::BrowserFailed, &[&e])),
+            }
+        }
+    }
+
+    // 收到 SIGHUP 时重新加载配置文件中可在运行时修改的部分和各共享目录下的忽略文件
+    reload::spawn(live_settings, matches, startup_args, ignore_files);
+
+    let handle = server.clone();
+    ntex::rt::spawn(async move {
+        shutdown::wait_for_signal().await;
+        log::info!("{}", t(Msg::ShuttingDown));
+        handle.stop(true).await;
+    });
+    // 连续 --idle-shutdown 时长没有请求时走与退出信号相同的优雅关闭流程
+    if !args.idle_shutdown.is_zero() {
+        let handle = server.clone();
+        let in_flight = in_flight.clone();
+        let idle = args.idle_shutdown;
+        ntex::rt::spawn(async move {
+            in_flight.wait_idle(idle).await;
+            log::info!("{}", t(Msg::IdleShutdownReached));
+            handle.stop(true).await;
+        });
+    }
+    server.await?;
+
+    if let Some(cleaner) = cleaner {
+        cleaner.stop();
+    }
+    dir_monitor.stop();
+
+    if let Some(ref path) = args.download_stats_file
+        && let Err(e) = download_stats.save(path)
+    {
+        log::warn!("{}", tf(Msg::SaveDownloadStatsFailed, &[path, &e]));
+    }
+
+    // ntex 停止监听时通常已删除套接字文件，这里确保不留下残留
+    if let Some(ref path) = args.unix_socket
+        && let Err(e) = std::fs::remove_file(path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        log::warn!("{}", tf(Msg::RemoveSocketFailed, &[path, &e]));
+    }
+
+    if let Some(ref path) = args.pid_file
+        && let Err(e) = std::fs::remove_file(path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        log::warn!("{}", tf(Msg::RemovePidFileFailed, &[path, &e]));
+    }
+
+    let pending = in_flight.count();
+    if pending > 0 {
+        exit_with_error(Exit::Failure, tf(Msg::DrainTimeout, &[&pending]));
+    }
+    log::info!("{}", t(Msg::Stopped));
+    Ok(())
 }
@@ -0,0 +1,269 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use futures::channel::mpsc;
+use ntex::http::Method;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::archive::ChannelWriter;
+use crate::checksum::DigestCache;
+use crate::i18n::{Msg, tf};
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 生成清单时遍历的最大目录深度，防止目录层级过深；--max-depth 更小时以它为准
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Csv,
+}
+
+// 目录请求带 ?manifest=1 时，列出该目录下所有文件的相对路径和大小，每行一个 JSON 对象(NDJSON)，
+// 加 &format=csv 时输出 CSV，加 &checksums=1 时附上 sha256。边遍历边发送，不会把整个清单放在内存里；
+// 与列表相同，隐藏文件和被通配符、忽略文件、--deny-ext、--max-depth 过滤掉的文件不会出现在清单中
+#[derive(Clone)]
+pub struct Manifest {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    cache: DigestCache,
+}
+
+impl Manifest {
+    pub fn new(mounts: Vec<Mount>, cache: DigestCache) -> Self {
+        Manifest {
+            inner: Rc::new(Inner { mounts, cache }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Manifest {
+    type Service = ManifestMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ManifestMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct ManifestMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ManifestMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if *req.method() != Method::GET || !query::flag(req.query_string(), "manifest") {
+            return ctx.call(&self.service, req).await;
+        }
+        let resolved = mount::find(&self.inner.mounts, req.path()).and_then(|mount| Some((mount.clone(), mount.resolve(req.path())?)));
+        let (mount, dir) = match resolved {
+            Some((mount, dir)) if mount.listing && dir.is_dir() => (mount, dir),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let format = match query::get(req.query_string(), "format").as_deref() {
+            Some("csv") => Format::Csv,
+            _ => Format::Json,
+        };
+        let checksums = query::flag(req.query_string(), "checksums");
+        let content_type = match format {
+            Format::Json => "application/x-ndjson; charset=utf-8",
+            Format::Csv => "text/csv; charset=utf-8",
+        };
+        let cache = self.inner.cache.clone();
+        let response = HttpResponse::Ok()
+            .content_type(content_type)
+            .streaming(stream(dir, mount, format, checksums, cache));
+        Ok(req.into_response(response))
+    }
+}
+
+// 与打包下载相同，在独立线程中遍历，通过有界通道交给响应体，客户端断开时停止遍历
+fn stream(dir: PathBuf, mount: Mount, format: Format, checksums: bool, cache: DigestCache) -> mpsc::Receiver<Result<Bytes, io::Error>> {
+    let (tx, rx) = mpsc::channel(4);
+    std::thread::spawn(move || {
+        let mut writer = ChannelWriter::new(tx);
+        let mut result = Ok(());
+        if format == Format::Csv {
+            result = writeln!(writer, "{}", if checksums { "path,size,sha256" } else { "path,size" });
+        }
+        let walker = Walker {
+            mount: &mount,
+            format,
+            checksums: checksums.then_some(&cache),
+        };
+        let result = result
+            .and_then(|_| walker.walk(&mut writer, &dir, "", 0))
+            .and_then(|_| writer.flush());
+        if let Err(e) = result {
+            log::debug!("{}", tf(Msg::ManifestAborted, &[&dir.display(), &e]));
+        }
+    });
+    rx
+}
+
+struct Walker<'a> {
+    mount: &'a Mount,
+    format: Format,
+    checksums: Option<&'a DigestCache>,
+}
+
+impl Walker<'_> {
+    // 读取失败的目录和文件跳过，只有写入失败(客户端断开)时返回错误
+    fn walk(&self, writer: &mut ChannelWriter, dir: &Path, prefix: &str, depth: usize) -> io::Result<()> {
+        if depth > MAX_DEPTH {
+            log::warn!("{}", tf(Msg::ManifestTooDeep, &[&dir.display(), &MAX_DEPTH]));
+            return Ok(());
+        }
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(()),
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !self.mount.is_visible(&name) {
+                continue;
+            }
+            let path = entry.path();
+            // 未启用 --follow-symlinks 时不进入指向共享目录之外的符号链接
+            let is_symlink = entry.file_type().is_ok_and(|file_type| file_type.is_symlink());
+            if is_symlink && !self.mount.follow_symlinks && self.mount.escapes(&path) {
+                continue;
+            }
+            let metadata = match std::fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            // 与目录大小统计相同，不进入指向目录的符号链接，避免 a/loop -> .. 这样的环在每一层重复遍历
+            if is_symlink && metadata.is_dir() {
+                continue;
+            }
+            if !self.mount.allows(&path, metadata.is_dir()) {
+                continue;
+            }
+
+            let relative = format!("{}{}", prefix, name);
+            if metadata.is_dir() {
+                self.walk(writer, &path, &format!("{}/", relative), depth + 1)?;
+                continue;
+            }
+            let sha256 = match self.checksums {
+                Some(cache) => match cache.digest(&path, "sha256") {
+                    Ok(digest) => Some(digest),
+                    Err(e) => {
+                        log::warn!("{}", tf(Msg::ChecksumFailed, &[&path.display(), &e]));
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            self.write_line(writer, &relative, metadata.len(), sha256.as_deref())?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&self, writer: &mut ChannelWriter, path: &str, size: u64, sha256: Option<&str>) -> io::Result<()> {
+        match self.format {
+            Format::Json => {
+                let mut value = serde_json::json!({ "path": path, "size": size });
+                if let Some(sha256) = sha256 {
+                    value["sha256"] = sha256.into();
+                }
+                writeln!(writer, "{}", value)
+            }
+            Format::Csv => match sha256 {
+                Some(sha256) => writeln!(writer, "{},{},{}", csv_field(path), size, sha256),
+                None => writeln!(writer, "{},{}", csv_field(path), size),
+            },
+        }
+    }
+}
+
+// 含逗号、引号或换行的字段加引号，其中的引号写两次
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::glob::PathFilter;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn streams_filtered_manifest_with_checksums() {
+        let dir = TempDir::new();
+        dir.write("a.txt", "a");
+        dir.write("docs/b,c.txt", "bc");
+        dir.write("docs/key.pem", "secret");
+        dir.write(".hidden", "h");
+        let mut mount = dir.mount("/download/files");
+        mount.filter = PathFilter::default().with_denied_extensions(&["pem".to_string()]);
+        let app = test::init_service(
+            web::App::new()
+                .wrap(Manifest::new(vec![mount.clone()], DigestCache::new()))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::with_uri(uri).to_request();
+
+        let body = test::read_response(&app, get("/download/files/?manifest=1&checksums=1")).await;
+        let mut lines: Vec<serde_json::Value> = String::from_utf8_lossy(&body).lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        lines.sort_by_key(|line| line["path"].as_str().unwrap().to_string());
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["path"], "a.txt");
+        assert_eq!(lines[0]["sha256"], "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb");
+        assert_eq!(lines[1]["path"], "docs/b,c.txt");
+        assert_eq!(lines[1]["size"], 2);
+
+        let body = test::read_response(&app, get("/download/files/docs/?manifest=1&format=csv")).await;
+        assert_eq!(String::from_utf8_lossy(&body), "path,size\n\"b,c.txt\",2\n");
+    }
+
+    #[cfg(unix)]
+    #[ntex::test]
+    async fn skips_directory_symlinks() {
+        let dir = TempDir::new();
+        dir.write("a/b.txt", "b");
+        std::os::unix::fs::symlink("..", dir.path().join("a/loop")).unwrap();
+        std::os::unix::fs::symlink("b.txt", dir.path().join("a/link.txt")).unwrap();
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(
+            web::App::new()
+                .wrap(Manifest::new(vec![mount.clone()], DigestCache::new()))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+
+        let body = test::read_response(&app, test::TestRequest::with_uri("/download/files/?manifest=1&format=csv").to_request()).await;
+        let mut lines: Vec<&str> = std::str::from_utf8(&body).unwrap().lines().collect();
+        lines.sort();
+        assert_eq!(lines, ["a/b.txt,1", "a/link.txt,1", "path,size"]);
+    }
+}
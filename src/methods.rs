@@ -0,0 +1,112 @@
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::header::{ALLOW, HeaderValue};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, tf};
+
+// 检查 --allowed-methods 中的方法名，统一转为大写
+pub fn parse_method(value: &str) -> Result<String, String> {
+    let method = value.trim().to_ascii_uppercase();
+    match Method::from_bytes(method.as_bytes()) {
+        Ok(_) if !method.is_empty() => Ok(method),
+        _ => Err(tf(Msg::InvalidMethod, &[&value])),
+    }
+}
+
+// 只放行 --allowed-methods 中的方法，其余返回 405 并在 Allow 头中列出允许的方法
+#[derive(Clone)]
+pub struct AllowedMethods {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    methods: Vec<Method>,
+    allow: HeaderValue,
+}
+
+impl AllowedMethods {
+    pub fn new(methods: &[String]) -> Self {
+        let allow = HeaderValue::from_str(&methods.join(", ")).expect("方法名已在解析参数时检查");
+        AllowedMethods {
+            inner: Rc::new(Inner {
+                methods: methods.iter().filter_map(|method| Method::from_bytes(method.as_bytes()).ok()).collect(),
+                allow,
+            }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for AllowedMethods {
+    type Service = AllowedMethodsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        AllowedMethodsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct AllowedMethodsMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for AllowedMethodsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if !self.inner.methods.contains(req.method()) {
+            log::debug!("{}", tf(Msg::MethodNotAllowed, &[&req.method(), &req.path()]));
+            let res = HttpResponse::MethodNotAllowed().header(ALLOW, self.inner.allow.clone()).finish();
+            return Ok(req.into_response(res));
+        }
+        ctx.call(&self.service, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::{self, test};
+
+    use super::*;
+
+    #[ntex::test]
+    async fn rejects_methods_outside_the_allowlist() {
+        let methods = ["get", "HEAD", "mkcol"].map(|method| parse_method(method).unwrap());
+        let app = test::init_service(
+            web::App::new()
+                .wrap(AllowedMethods::new(&methods))
+                .default_service(web::to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+
+        for (method, status) in [
+            ("GET", StatusCode::OK),
+            ("HEAD", StatusCode::OK),
+            ("MKCOL", StatusCode::OK),
+            ("POST", StatusCode::METHOD_NOT_ALLOWED),
+            ("PROPFIND", StatusCode::METHOD_NOT_ALLOWED),
+        ] {
+            let req = test::TestRequest::with_uri("/").method(Method::from_bytes(method.as_bytes()).unwrap()).to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), status, "{}", method);
+            if status == StatusCode::METHOD_NOT_ALLOWED {
+                assert_eq!(res.headers().get(ALLOW).unwrap(), "GET, HEAD, MKCOL");
+            }
+        }
+        assert!(parse_method("GE T").is_err());
+    }
+}
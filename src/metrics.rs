@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::types::State;
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::mount::{self, Mount};
+
+// 指标接口路径
+pub const PATH: &str = "/metrics";
+
+// 响应耗时直方图的分桶上限(秒)
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Labels {
+    method: String,
+    status: &'static str,
+    prefix: String,
+}
+
+#[derive(Default)]
+struct Series {
+    requests: u64,
+    bytes: u64,
+    aborted: u64,
+    buckets: [u64; BUCKETS.len()],
+    seconds: f64,
+}
+
+// 所有工作线程共享的指标表
+#[derive(Clone, Default)]
+pub struct Metrics {
+    series: Arc<Mutex<BTreeMap<Labels, Series>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    fn observe(&self, labels: &Labels, bytes: u64, elapsed: Duration, aborted: bool) {
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let series = series.entry(labels.clone()).or_default();
+        let seconds = elapsed.as_secs_f64();
+        series.requests += 1;
+        series.bytes += bytes;
+        series.aborted += aborted as u64;
+        series.seconds += seconds;
+        for (count, bound) in series.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *count += 1;
+            }
+        }
+    }
+
+    // 输出 Prometheus 文本格式
+    fn render(&self) -> String {
+        let series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+
+        out.push_str("# HELP fileshare_requests_total 请求总数\n# TYPE fileshare_requests_total counter\n");
+        for (labels, series) in series.iter() {
+            let _ = writeln!(out, "fileshare_requests_total{{{}}} {}", format_labels(labels), series.requests);
+        }
+
+        out.push_str("# HELP fileshare_response_bytes_total 已发送的响应体字节数\n# TYPE fileshare_response_bytes_total counter\n");
+        for (labels, series) in series.iter() {
+            let _ = writeln!(out, "fileshare_response_bytes_total{{{}}} {}", format_labels(labels), series.bytes);
+        }
+
+        out.push_str("# HELP fileshare_aborted_responses_total 客户端在响应体发送完毕前断开的请求数\n# TYPE fileshare_aborted_responses_total counter\n");
+        for (labels, series) in series.iter() {
+            let _ = writeln!(out, "fileshare_aborted_responses_total{{{}}} {}", format_labels(labels), series.aborted);
+        }
+
+        out.push_str("# HELP fileshare_request_duration_seconds 请求耗时，响应体发送完毕为止\n# TYPE fileshare_request_duration_seconds histogram\n");
+        for (labels, series) in series.iter() {
+            let labels = format_labels(labels);
+            for (count, bound) in series.buckets.iter().zip(BUCKETS) {
+                let _ = writeln!(out, "fileshare_request_duration_seconds_bucket{{{},le=\"{}\"}} {}", labels, bound, count);
+            }
+            let _ = writeln!(out, "fileshare_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, series.requests);
+            let _ = writeln!(out, "fileshare_request_duration_seconds_sum{{{}}} {}", labels, series.seconds);
+            let _ = writeln!(out, "fileshare_request_duration_seconds_count{{{}}} {}", labels, series.requests);
+        }
+        out
+    }
+}
+
+fn format_labels(labels: &Labels) -> String {
+    format!(
+        "method=\"{}\",status=\"{}\",prefix=\"{}\"",
+        escape_label(&labels.method),
+        labels.status,
+        escape_label(&labels.prefix)
+    )
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn status_class(status: u16) -> &'static str {
+    match status {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+// 输出所有指标
+pub async fn metrics(metrics: State<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(metrics.render())
+}
+
+// 按实际发送的数据块统计字节数，范围请求和压缩后的响应同样准确；
+// 响应体发送完毕或连接断开时记录本次请求，中途断开的只计入已发送的部分
+struct MeteredBody {
+    body: ResponseBody<Body>,
+    metrics: Metrics,
+    labels: Labels,
+    start: Instant,
+    bytes: u64,
+    finished: bool,
+}
+
+impl MessageBody for MeteredBody {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        let poll = self.body.poll_next_chunk(cx);
+        match poll {
+            Poll::Ready(Some(Ok(ref chunk))) => self.bytes += chunk.len() as u64,
+            Poll::Ready(None) => self.finished = true,
+            _ => {}
+        }
+        poll
+    }
+}
+
+impl Drop for MeteredBody {
+    fn drop(&mut self) {
+        self.metrics.observe(&self.labels, self.bytes, self.start.elapsed(), !self.finished);
+    }
+}
+
+// 记录每个请求的方法、状态码类别和所属挂载点
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    metrics: Option<Metrics>,
+    mounts: Vec<Mount>,
+}
+
+impl Recorder {
+    // 未启用指标时传入 None，中间件直接放行
+    pub fn new(metrics: Option<Metrics>, mounts: Vec<Mount>) -> Self {
+        Recorder {
+            inner: Rc::new(Inner { metrics, mounts }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Recorder {
+    type Service = RecorderMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RecorderMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct RecorderMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for RecorderMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        // 指标接口本身不计入统计
+        let metrics = match self.inner.metrics {
+            Some(ref metrics) if req.path() != PATH => metrics.clone(),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let start = Instant::now();
+        let method = req.method().to_string();
+        let prefix = mount::find(&self.inner.mounts, req.path()).map_or("other", |mount| mount.url_path.as_str()).to_string();
+
+        let res = ctx.call(&self.service, req).await?;
+        let labels = Labels {
+            method,
+            status: status_class(res.status().as_u16()),
+            prefix,
+        };
+        Ok(res.map_body(move |_, body| {
+            // 没有响应体时不会被读取，直接视为已发送完毕
+            let finished = matches!(body.size(), BodySize::None | BodySize::Empty | BodySize::Sized(0));
+            ResponseBody::Other(Body::from_message(MeteredBody {
+                body,
+                metrics,
+                labels,
+                start,
+                bytes: 0,
+                finished,
+            }))
+        }))
+    }
+}
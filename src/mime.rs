@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use ntex::http::header::{CONTENT_TYPE, HeaderValue};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{WebRequest, WebResponse};
+
+use crate::i18n::{Msg, tf};
+use crate::mount::{self, Mount};
+
+// 解析 ext=type 格式的 MIME 类型映射，扩展名不区分大小写，可带前导的 .
+pub fn parse_mime(value: &str) -> Result<(String, HeaderValue), String> {
+    let (ext, mime) = value
+        .split_once('=')
+        .ok_or_else(|| tf(Msg::InvalidMimeMapping, &[&value]))?;
+    let ext = ext.trim().trim_start_matches('.').to_ascii_lowercase();
+    if ext.is_empty() {
+        return Err(tf(Msg::MimeMappingMissingExt, &[&value]));
+    }
+    let mime = mime.trim();
+    if !mime.contains('/') {
+        return Err(tf(Msg::InvalidMimeType, &[&value]));
+    }
+    let mime = HeaderValue::from_str(mime).map_err(|_| tf(Msg::InvalidMimeType, &[&value]))?;
+    Ok((ext, mime))
+}
+
+// 读取 Apache 格式的 mime.types 文件(--mime-db)：每行一个类型，后跟以空白分隔的扩展名，# 之后为注释；
+// 只有类型没有扩展名的行不产生映射，格式错误的行跳过并给出警告，文件无法读取时返回错误
+pub fn load_db(path: &str) -> Result<Vec<(String, HeaderValue)>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| tf(Msg::ReadMimeDbFailed, &[&path, &e]))?;
+    Ok(parse_db(path, &content))
+}
+
+fn parse_db(path: &str, content: &str) -> Vec<(String, HeaderValue)> {
+    let mut types = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let mut fields = line.split('#').next().unwrap_or_default().split_whitespace();
+        let mime = match fields.next() {
+            Some(mime) => mime,
+            None => continue,
+        };
+        let valid = mime.split_once('/').is_some_and(|(kind, subtype)| !kind.is_empty() && !subtype.is_empty());
+        let value = match HeaderValue::from_str(mime) {
+            Ok(value) if valid => value,
+            _ => {
+                log::warn!("{}", tf(Msg::InvalidMimeDbLine, &[&path, &(number + 1)]));
+                continue;
+            }
+        };
+        for ext in fields.map(|ext| ext.trim_start_matches('.').to_ascii_lowercase()).filter(|ext| !ext.is_empty()) {
+            types.push((ext, value.clone()));
+        }
+    }
+    types
+}
+
+// 按 --mime-db 和 --mime 指定的映射改写文件响应的 Content-Type，其他扩展名保持 ntex-files 的推断结果
+#[derive(Clone)]
+pub struct MimeOverride {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    types: HashMap<String, HeaderValue>,
+    mounts: Vec<Mount>,
+}
+
+impl MimeOverride {
+    // 同一扩展名出现多次时后面的优先
+    pub fn new(types: &[(String, HeaderValue)], mounts: Vec<Mount>) -> Self {
+        MimeOverride {
+            inner: Rc::new(Inner {
+                types: types.iter().cloned().collect(),
+                mounts,
+            }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for MimeOverride {
+    type Service = MimeOverrideMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        MimeOverrideMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct MimeOverrideMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for MimeOverrideMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if self.inner.types.is_empty() {
+            return ctx.call(&self.service, req).await;
+        }
+        let mime = mount::find(&self.inner.mounts, req.path())
+            .and_then(|mount| mount.resolve(req.path()))
+            .filter(|path| path.is_file())
+            .and_then(|path| path.extension().map(|ext| ext.to_string_lossy().to_ascii_lowercase()))
+            .and_then(|ext| self.inner.types.get(&ext).cloned());
+        let mime = match mime {
+            Some(mime) => mime,
+            None => return ctx.call(&self.service, req).await,
+        };
+
+        let mut res = ctx.call(&self.service, req).await?;
+        if res.status().is_success() {
+            res.headers_mut().insert(CONTENT_TYPE, mime);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_apache_mime_types() {
+        let content = "# 注释\napplication/x-custom\tcst .CST2 # 行尾注释\n\ntext/x-empty\nbroken cst3\ntext/x-later\tcst\n";
+        let types: Vec<(String, String)> = parse_db("mime.types", content)
+            .into_iter()
+            .map(|(ext, mime)| (ext, mime.to_str().unwrap().to_string()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("cst".to_string(), "application/x-custom".to_string()),
+                ("cst2".to_string(), "application/x-custom".to_string()),
+                ("cst".to_string(), "text/x-later".to_string()),
+            ]
+        );
+    }
+}
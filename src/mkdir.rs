@@ -0,0 +1,153 @@
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::error::BlockingError;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use percent_encoding::percent_decode_str;
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 新建目录: POST {URL路径}/<目录>?action=mkdir 或 MKCOL {URL路径}/<目录>，中间缺少的目录一并创建
+#[derive(Clone)]
+pub struct Mkdir {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    enabled: bool,
+}
+
+impl Mkdir {
+    pub fn new(mounts: Vec<Mount>, enabled: bool) -> Self {
+        Mkdir {
+            inner: Rc::new(Inner { mounts, enabled }),
+        }
+    }
+}
+
+// 是否为新建目录请求
+fn is_mkdir(method: &Method, query_string: &str) -> bool {
+    method.as_str() == "MKCOL" || (*method == Method::POST && query::get(query_string, "action").as_deref() == Some("mkdir"))
+}
+
+impl<S> Middleware<S> for Mkdir {
+    type Service = MkdirMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        MkdirMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct MkdirMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for MkdirMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let mount = match mount::find(&self.inner.mounts, req.path()) {
+            Some(mount) if is_mkdir(req.method(), req.query_string()) => mount,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        if !self.inner.enabled {
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::MkdirDisabled))));
+        }
+
+        let path = match mount.resolve(req.path()) {
+            Some(path) if path != mount.dir => path,
+            _ => return Ok(req.into_response(HttpResponse::BadRequest().body(t(Msg::InvalidPath)))),
+        };
+
+        let name = percent_decode_str(req.path()).decode_utf8_lossy().into_owned();
+        let response = match create(path.clone()).await {
+            Ok(true) => {
+                log::info!("{}", tf(Msg::DirCreated, &[&path.display()]));
+                HttpResponse::Created().json(&serde_json::json!({ "created": name }))
+            }
+            Ok(false) => HttpResponse::Ok().json(&serde_json::json!({ "created": name })),
+            // 目标或某一级上级路径是已有的文件
+            Err(e) if matches!(e.kind(), io::ErrorKind::AlreadyExists | io::ErrorKind::NotADirectory) => {
+                HttpResponse::Conflict().body(tf(Msg::MkdirConflict, &[&name]))
+            }
+            Err(e) => {
+                log::error!("{}", tf(Msg::MkdirError, &[&path.display(), &e]));
+                HttpResponse::InternalServerError().body(tf(Msg::MkdirFailed, &[&name]))
+            }
+        };
+        Ok(req.into_response(response))
+    }
+}
+
+// 在线程池中创建目录，返回是否为新建；目录已存在时返回 false
+async fn create(path: PathBuf) -> io::Result<bool> {
+    let result = web::block(move || {
+        if path.is_dir() {
+            return Ok(false);
+        }
+        if path.exists() {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+        }
+        std::fs::create_dir_all(&path).map(|_| true)
+    })
+    .await;
+    match result {
+        Ok(created) => Ok(created),
+        Err(BlockingError::Error(e)) => Err(e),
+        Err(BlockingError::Canceled) => Err(io::Error::other(t(Msg::MkdirInterrupted))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::test;
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn creates_nested_directory_and_rejects_traversal() {
+        let dir = TempDir::new();
+        dir.write("file.txt", "hello");
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(web::App::new().wrap(Mkdir::new(vec![mount.clone()], true)).service(testutil::files(&mount))).await;
+        let mkdir = |uri: &str| test::TestRequest::with_uri(uri).method(Method::POST).to_request();
+
+        let res = test::call_service(&app, mkdir("/download/files/a/b/c?action=mkdir")).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert!(dir.path().join("a/b/c").is_dir());
+        let listing = test::read_response(&app, test::TestRequest::with_uri("/download/files/a/b/").to_request()).await;
+        assert!(String::from_utf8_lossy(&listing).contains("href=\"/download/files/a/b/c/\""));
+
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/a/b/d").method(Method::from_bytes(b"MKCOL").unwrap()).to_request()).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let res = test::call_service(&app, mkdir("/download/files/file.txt?action=mkdir")).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        for uri in ["/download/files/../escape?action=mkdir", "/download/files/%2e%2e/escape?action=mkdir", "/download/files/%2Fescape?action=mkdir", "/download/files/a/..%2F..%2Fescape?action=mkdir"] {
+            let res = test::call_service(&app, mkdir(uri)).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+        assert!(!dir.path().parent().unwrap().join("escape").exists());
+        assert!(!std::path::Path::new("/escape").exists());
+    }
+}
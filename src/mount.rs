@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use ntex::http::header::HeaderValue;
+use percent_encoding::percent_decode_str;
+
+use crate::glob::PathFilter;
+use crate::i18n::{Msg, tf};
+
+// 命令行或配置文件中的一个挂载点: /url=/dir，或带单独账号的 /url=/dir:用户名:密码
+#[derive(Clone, Debug)]
+pub struct MountSpec {
+    pub url: String,
+    pub dir: String,
+    pub credentials: Option<(String, String)>,
+}
+
+// 列表页和预览页只引用本站的样式和脚本，文件名中的 HTML 即使漏掉转义也不会执行
+pub const DEFAULT_CSP: &str = "default-src 'self'";
+
+// 挂载点：URL路径前缀与共享目录的对应关系
+#[derive(Clone, Debug)]
+pub struct Mount {
+    pub url_path: String,
+    pub dir: PathBuf,
+    pub show_hidden: bool,
+    pub follow_symlinks: bool,
+    pub page_size: usize,
+    // 目录列表最多读取的条目数(--listing-max-entries)，0 表示不限制
+    pub max_entries: usize,
+    pub thumbnails: bool,
+    pub show_dir_size: bool,
+    pub dir_size_max_depth: usize,
+    pub watch: bool,
+    pub default_sort: String,
+    pub default_desc: bool,
+    pub filter: PathFilter,
+    pub index_file: Option<String>,
+    pub listing: bool,
+    // 列表中每个条目前是否显示文件类型图标
+    pub icons: bool,
+    // 列表中是否显示复选框，可勾选多个文件打包下载(--enable-zip)
+    pub zip: bool,
+    // 是否可以上传(--enable-upload 且不是只读模式)，为真时空的根目录列表显示上传入口
+    pub upload: bool,
+    // 列表页的标题和顶部提示，未设置标题时使用请求路径
+    pub title: Option<String>,
+    pub banner: Option<String>,
+    // 列表页和预览页的 Content-Security-Policy 响应头
+    pub csp: Option<HeaderValue>,
+    // dir 指向普通文件，挂载点只提供这一个文件，目录相关的功能都不启用
+    pub single_file: bool,
+    // 反向代理去掉的外部路径前缀(--base-path)，只用于生成链接和跳转地址，路由仍按 url_path 匹配
+    pub base_path: String,
+    // 该挂载点单独的 Basic 认证账号，格式为 "用户名:密码"，优先于 --username/--password
+    pub credentials: Option<Vec<u8>>,
+}
+
+impl Mount {
+    pub fn new(url_path: &str, dir: &str) -> Self {
+        Mount {
+            url_path: url_path.to_string(),
+            dir: PathBuf::from(dir),
+            show_hidden: false,
+            follow_symlinks: false,
+            page_size: 0,
+            max_entries: 0,
+            thumbnails: false,
+            show_dir_size: false,
+            dir_size_max_depth: 0,
+            watch: false,
+            default_sort: "name".to_string(),
+            default_desc: false,
+            filter: PathFilter::default(),
+            index_file: None,
+            listing: true,
+            icons: true,
+            zip: false,
+            upload: false,
+            title: None,
+            banner: None,
+            csp: HeaderValue::from_str(DEFAULT_CSP).ok(),
+            single_file: false,
+            base_path: String::new(),
+            credentials: None,
+        }
+    }
+
+    // 是否对客户端展示该文件，默认隐藏 . 开头的文件和目录
+    pub fn is_visible(&self, name: &str) -> bool {
+        self.show_hidden || !name.starts_with('.')
+    }
+
+    // 共享目录下的路径是否通过 --include-glob/--exclude-glob 过滤；不在共享目录下的路径不受限制
+    pub fn allows(&self, path: &Path, is_dir: bool) -> bool {
+        match path.strip_prefix(&self.dir) {
+            Ok(relative) if !relative.as_os_str().is_empty() => self.filter.allows(relative, is_dir),
+            _ => true,
+        }
+    }
+
+    // 请求路径在浏览器中的地址，加上 --base-path 前缀
+    pub fn external(&self, path: &str) -> String {
+        format!("{}{}", self.base_path, path)
+    }
+
+    // 去掉挂载点前缀，返回剩余的请求路径；不属于该挂载点时返回 None
+    pub fn strip<'a>(&self, path: &'a str) -> Option<&'a str> {
+        strip_url_prefix(&self.url_path, path)
+    }
+
+    // 将请求路径映射到共享目录下的文件路径，拒绝 .. 和隐藏文件等越界路径
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let rest = self.strip(path)?;
+        let mut buf = self.dir.clone();
+        for segment in rest.split('/').filter(|s| !s.is_empty()) {
+            let segment = percent_decode_str(segment).decode_utf8().ok()?;
+            if !self.is_valid_segment(&segment) {
+                return None;
+            }
+            buf.push(segment.as_ref());
+        }
+        Some(buf)
+    }
+
+    // 将相对于挂载点根目录、未经百分号编码的路径(如表单中提交的 docs/a.txt)映射到共享目录下，规则与 resolve 相同
+    pub fn resolve_relative(&self, relative: &str) -> Option<PathBuf> {
+        let mut buf = self.dir.clone();
+        for segment in relative.split('/').filter(|s| !s.is_empty()) {
+            if !self.is_valid_segment(segment) {
+                return None;
+            }
+            buf.push(segment);
+        }
+        Some(buf)
+    }
+
+    fn is_valid_segment(&self, segment: &str) -> bool {
+        segment != "." && segment != ".." && self.is_visible(segment) && !segment.contains(['/', '\\', '\0'])
+    }
+
+    // 路径经过符号链接后是否指向共享目录之外；路径尚不存在时检查最近的已存在上级目录
+    pub fn escapes(&self, path: &Path) -> bool {
+        let root = match self.dir.canonicalize() {
+            Ok(root) => root,
+            Err(_) => return false,
+        };
+        match path.ancestors().find_map(|ancestor| ancestor.canonicalize().ok()) {
+            Some(target) => !target.starts_with(&root),
+            None => false,
+        }
+    }
+}
+
+// 检查 --base-path，必须以 / 开头，去掉末尾的 /，只有 / 时视为不设置
+pub fn parse_base_path(value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    if !value.starts_with('/') {
+        return Err(tf(Msg::BasePathNoSlash, &[&value]));
+    }
+    if value.contains(['?', '#', '"', '<', '>', '\\']) {
+        return Err(tf(Msg::BasePathInvalidChars, &[&value]));
+    }
+    Ok(value.trim_end_matches('/').to_string())
+}
+
+// 判断 path 是否位于 prefix 之下，返回去掉前缀后的部分
+pub fn strip_url_prefix<'a>(prefix: &str, path: &'a str) -> Option<&'a str> {
+    let rest = path.strip_prefix(prefix.trim_end_matches('/'))?;
+    if rest.is_empty() || rest.starts_with('/') { Some(rest) } else { None }
+}
+
+// 按注册顺序查找请求所属的挂载点
+pub fn find<'a>(mounts: &'a [Mount], path: &str) -> Option<&'a Mount> {
+    mounts.iter().find(|mount| mount.strip(path).is_some())
+}
+
+// 不应整体共享的系统目录；各项也先规范化，/bin 这类指向 /usr/bin 的链接同样能匹配
+const SENSITIVE_DIRS: [&str; 16] = [
+    "/etc", "/root", "/home", "/Users", "/usr", "/bin", "/sbin", "/lib", "/boot", "/dev", "/proc", "/sys", "/var", "/opt",
+    "C:\\Windows", "C:\\Users",
+];
+
+// 共享目录是否为根目录、用户主目录或系统目录，先规范化，指向这些目录的符号链接同样算在内
+pub fn is_sensitive_dir(dir: &Path) -> bool {
+    let Ok(dir) = dir.canonicalize() else {
+        return false;
+    };
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    dir.parent().is_none()
+        || home.and_then(|home| Path::new(&home).canonicalize().ok()).is_some_and(|home| home == dir)
+        || SENSITIVE_DIRS.iter().any(|sensitive| Path::new(sensitive).canonicalize().is_ok_and(|sensitive| sensitive == dir))
+}
+
+// 目录是否所有用户可写，其他用户可以放入或替换共享出去的文件
+#[cfg(unix)]
+pub fn is_world_writable(dir: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(dir).is_ok_and(|metadata| metadata.permissions().mode() & 0o002 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn is_world_writable(_dir: &Path) -> bool {
+    false
+}
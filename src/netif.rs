@@ -0,0 +1,55 @@
+use std::net::IpAddr;
+
+// 本机各网络接口上的地址(接口名, 地址)，不含回环地址和 IPv6 链路本地地址；
+// 容器中自动检测到的局域网地址往往是内部地址，启动时列出这些地址供 --public-ip 选择
+#[cfg(unix)]
+pub fn addresses() -> Vec<(String, IpAddr)> {
+    use std::ffi::CStr;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let mut list = Vec::new();
+    let mut head: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut head) } != 0 {
+        return list;
+    }
+    let mut current = head;
+    while !current.is_null() {
+        let ifa = unsafe { &*current };
+        current = ifa.ifa_next;
+        if ifa.ifa_addr.is_null() {
+            continue;
+        }
+        let ip = match i32::from(unsafe { (*ifa.ifa_addr).sa_family }) {
+            libc::AF_INET => {
+                let addr = unsafe { &*(ifa.ifa_addr as *const libc::sockaddr_in) };
+                IpAddr::V4(Ipv4Addr::from(addr.sin_addr.s_addr.to_ne_bytes()))
+            }
+            libc::AF_INET6 => {
+                let addr = unsafe { &*(ifa.ifa_addr as *const libc::sockaddr_in6) };
+                IpAddr::V6(Ipv6Addr::from(addr.sin6_addr.s6_addr))
+            }
+            _ => continue,
+        };
+        let link_local = matches!(ip, IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80);
+        if ip.is_loopback() || ip.is_unspecified() || link_local {
+            continue;
+        }
+        let name = unsafe { CStr::from_ptr(ifa.ifa_name) }.to_string_lossy().into_owned();
+        list.push((name, ip));
+    }
+    unsafe { libc::freeifaddrs(head) };
+    list
+}
+
+#[cfg(not(unix))]
+pub fn addresses() -> Vec<(String, IpAddr)> {
+    Vec::new()
+}
+
+// 地址中的主机部分，IPv6 地址加上方括号，其他(IPv4 地址或域名)原样使用
+pub fn url_host(host: &str) -> String {
+    match host.parse::<std::net::Ipv6Addr>() {
+        Ok(ip) => format!("[{}]", ip),
+        Err(_) => host.to_string(),
+    }
+}
@@ -0,0 +1,37 @@
+use ntex::util::Bytes;
+use ntex::web::HttpResponse;
+use ntex::web::types::State;
+
+use crate::i18n::{Msg, t, tf};
+
+// 内置的 404 页面
+const DEFAULT_PAGE: &str = include_str!("templates/404.html");
+
+// 启动时加载的 404 页面内容，所有请求共享
+#[derive(Clone)]
+pub struct NotFoundPage(pub Bytes);
+
+// 读取自定义 404 页面，未指定时使用内置页面
+pub fn load(path: Option<&str>) -> Result<NotFoundPage, String> {
+    match path {
+        Some(path) => std::fs::read(path)
+            .map(|content| NotFoundPage(Bytes::from(content)))
+            .map_err(|e| tf(Msg::ReadNotFoundPageFailed, &[&path, &e])),
+        None => Ok(NotFoundPage(Bytes::from(
+            DEFAULT_PAGE
+                .replace("{{title}}", t(Msg::NotFoundTitle))
+                .replace("{{message}}", t(Msg::NotFoundMessage)),
+        ))),
+    }
+}
+
+pub fn response(page: &NotFoundPage) -> HttpResponse {
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .body(page.0.clone())
+}
+
+// 没有路由匹配或文件不存在时返回 404 页面
+pub async fn not_found(page: State<NotFoundPage>) -> HttpResponse {
+    response(&page)
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, t, tf};
+use crate::proxy::{self, TrustProxy};
+use crate::inflight;
+
+// 限制同一客户端地址同时进行中的请求数，超出时返回 429；
+// 计数在所有工作线程间共享，响应体发送完毕或连接中途断开时释放
+#[derive(Clone)]
+pub struct PerIpLimit {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    max: usize,
+    trust_proxy: TrustProxy,
+    active: Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl PerIpLimit {
+    // max 为 0 时不限制
+    pub fn new(max: usize, trust_proxy: TrustProxy) -> Self {
+        PerIpLimit {
+            inner: Arc::new(Inner {
+                max,
+                trust_proxy,
+                active: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+impl Inner {
+    // 占用该地址的一个名额，已达上限时返回 false
+    fn acquire(&self, ip: IpAddr) -> bool {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        let count = active.entry(ip).or_insert(0);
+        if *count >= self.max {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    // 计数归零时移除该地址，避免表无限增长
+    fn release(&self, ip: IpAddr) {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = active.get_mut(&ip) {
+            *count -= 1;
+            if *count == 0 {
+                active.remove(&ip);
+            }
+        }
+    }
+}
+
+// 请求名额守卫，释放时归还名额
+struct Guard {
+    inner: Arc<Inner>,
+    ip: IpAddr,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.inner.release(self.ip);
+    }
+}
+
+impl<S> Middleware<S> for PerIpLimit {
+    type Service = PerIpLimitMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        PerIpLimitMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct PerIpLimitMiddleware<S> {
+    service: S,
+    inner: Arc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for PerIpLimitMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if self.inner.max == 0 {
+            return ctx.call(&self.service, req).await;
+        }
+        let ip = match proxy::client_ip(req.headers(), req.peer_addr(), &self.inner.trust_proxy) {
+            Some(ip) => ip,
+            None => return ctx.call(&self.service, req).await,
+        };
+        if !self.inner.acquire(ip) {
+            log::debug!("{}", tf(Msg::PerIpLimitReached, &[&ip, &self.inner.max]));
+            let response = HttpResponse::TooManyRequests()
+                .header("Retry-After", "1")
+                .body(t(Msg::TooManyRequests));
+            return Ok(req.into_response(response));
+        }
+
+        // 守卫在处理出错时随本函数返回释放，否则随响应体释放
+        let guard = Guard {
+            inner: self.inner.clone(),
+            ip,
+        };
+        let res = ctx.call(&self.service, req).await?;
+        Ok(inflight::attach(res, guard))
+    }
+}
@@ -0,0 +1,155 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, HeaderValue, VARY};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{WebRequest, WebResponse};
+use ntex_files::NamedFile;
+
+use crate::compress::{self, Encoding};
+use crate::i18n::{Msg, tf};
+use crate::mount::{self, Mount};
+
+// 开启 --precompressed 时，客户端接受 gzip/brotli 且文件旁边有 <文件>.gz/<文件>.br 时直接发送该文件，
+// Content-Type 仍按原文件推断；没有预压缩文件时照常发送原文件
+#[derive(Clone)]
+pub struct Precompressed {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    enabled: bool,
+}
+
+impl Precompressed {
+    pub fn new(mounts: Vec<Mount>, enabled: bool) -> Self {
+        Precompressed {
+            inner: Rc::new(Inner { mounts, enabled }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Precompressed {
+    type Service = PrecompressedMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        PrecompressedMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct PrecompressedMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+// 原文件旁边的预压缩文件，被隐藏或被通配符过滤掉的不算
+fn sibling(mount: &Mount, path: &Path, encoding: Encoding) -> Option<PathBuf> {
+    let mut name = path.file_name()?.to_os_string();
+    name.push(".");
+    name.push(encoding.extension());
+    let sibling = path.with_file_name(name);
+    (sibling.is_file() && mount.allows(&sibling, false)).then_some(sibling)
+}
+
+impl<S, E> Service<WebRequest<E>> for PrecompressedMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if !self.inner.enabled || !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return ctx.call(&self.service, req).await;
+        }
+        let resolved = mount::find(&self.inner.mounts, req.path()).and_then(|mount| Some((mount, mount.resolve(req.path())?)));
+        let (mount, path) = match resolved {
+            Some((mount, path)) if path.is_file() => (mount, path),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let accepted = req
+            .headers()
+            .get(ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(compress::preferences)
+            .unwrap_or_default();
+        let found = accepted
+            .into_iter()
+            .find_map(|encoding| Some((encoding, sibling(mount, &path, encoding)?)));
+        let (encoding, sibling_path) = match found {
+            Some(found) => found,
+            None => {
+                // 有预压缩文件时响应随 Accept-Encoding 变化，提示缓存分开保存
+                let has_sibling = [Encoding::Brotli, Encoding::Gzip].into_iter().any(|encoding| sibling(mount, &path, encoding).is_some());
+                let mut res = ctx.call(&self.service, req).await?;
+                if has_sibling {
+                    res.headers_mut().append(VARY, HeaderValue::from_static("accept-encoding"));
+                }
+                return Ok(res);
+            }
+        };
+
+        let file = match NamedFile::open(&sibling_path) {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("{}", tf(Msg::OpenPrecompressedFailed, &[&sibling_path.display(), &e]));
+                return ctx.call(&self.service, req).await;
+            }
+        };
+        let content_type = ntex_files::file_extension_to_mime(path.extension().and_then(|ext| ext.to_str()).unwrap_or(""));
+        let (req, _) = req.into_parts();
+        let mut res = file.set_content_type(content_type).disable_content_disposition().into_response(&req);
+        res.headers_mut().insert(CONTENT_ENCODING, encoding.header_value());
+        res.headers_mut().append(VARY, HeaderValue::from_static("accept-encoding"));
+        Ok(WebResponse::new(res, req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::header::CONTENT_TYPE;
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn serves_precompressed_sibling_when_accepted() {
+        let dir = TempDir::new();
+        dir.write("app.js", "plain");
+        dir.write("app.js.gz", "gzipped");
+        dir.write("style.css", "css");
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(
+            web::App::new()
+                .wrap(Precompressed::new(vec![mount.clone()], true))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+        let get = |uri: &str, accept: &str| test::TestRequest::with_uri(uri).header(ACCEPT_ENCODING, accept).to_request();
+
+        let res = test::call_service(&app, get("/download/files/app.js", "br, gzip")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert!(res.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap().contains("javascript"));
+        assert_eq!(test::read_body(res).await, "gzipped");
+
+        let res = test::call_service(&app, get("/download/files/app.js", "identity")).await;
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(res.headers().get(VARY).unwrap(), "accept-encoding");
+        assert_eq!(test::read_body(res).await, "plain");
+
+        let res = test::call_service(&app, get("/download/files/style.css", "gzip")).await;
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(test::read_body(res).await, "css");
+    }
+}
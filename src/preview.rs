@@ -0,0 +1,148 @@
+use std::io::{self, Read};
+use std::path::Path;
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::header::CONTENT_SECURITY_POLICY;
+use ntex::http::error::BlockingError;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event, Options, Parser, html};
+
+use crate::i18n::{Msg, t, tf};
+use crate::listing::escape_html;
+use crate::mount::{self, Mount};
+use crate::{query, ui};
+
+// 预览页面模板
+const TEMPLATE: &str = include_str!("templates/preview.html");
+
+// 检查是否为二进制文件时读取的字节数
+const SNIFF_LEN: usize = 8192;
+
+// 按 Markdown 渲染的扩展名，其他文本文件显示为纯文本
+const MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| MARKDOWN_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+// 读取文本文件内容；超过 max_size、含有 NUL 字节或不是 UTF-8 时视为不可预览，返回 None
+fn read_text(path: &Path, max_size: u64) -> io::Result<Option<String>> {
+    let file = std::fs::File::open(path)?;
+    if file.metadata()?.len() > max_size {
+        return Ok(None);
+    }
+    let mut content = Vec::new();
+    file.take(max_size).read_to_end(&mut content)?;
+    if content[..content.len().min(SNIFF_LEN)].contains(&0) {
+        return Ok(None);
+    }
+    Ok(String::from_utf8(content).ok())
+}
+
+// Markdown 转为 HTML，其中的原始 HTML 按文本输出，避免预览页面执行文件中的脚本
+fn render_markdown(text: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let parser = Parser::new_ext(text, options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, parser);
+    out
+}
+
+// 文件请求带 ?preview=1 时在浏览器中直接显示：Markdown 渲染为 HTML，其他文本放在 <pre> 中；
+// 二进制文件和超过 --preview-max-size 的文件忽略该参数，照常下载
+#[derive(Clone)]
+pub struct Preview {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    max_size: u64,
+}
+
+impl Preview {
+    pub fn new(mounts: Vec<Mount>, max_size: u64) -> Self {
+        Preview {
+            inner: Rc::new(Inner { mounts, max_size }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Preview {
+    type Service = PreviewMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        PreviewMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct PreviewMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for PreviewMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if *req.method() != Method::GET || !query::flag(req.query_string(), "preview") {
+            return ctx.call(&self.service, req).await;
+        }
+        let resolved = mount::find(&self.inner.mounts, req.path()).and_then(|mount| Some((mount, mount.resolve(req.path())?)));
+        let (mount, path) = match resolved {
+            Some((mount, path)) if path.is_file() => (mount, path),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let max_size = self.inner.max_size;
+        let file = path.clone();
+        let text = match web::block(move || read_text(&file, max_size)).await {
+            Ok(Some(text)) => text,
+            Ok(None) => return ctx.call(&self.service, req).await,
+            Err(BlockingError::Error(e)) if e.kind() == io::ErrorKind::NotFound => return ctx.call(&self.service, req).await,
+            Err(e) => {
+                log::error!("{}", tf(Msg::ReadFileFailed, &[&path.display(), &e]));
+                return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+            }
+        };
+
+        let content = if is_markdown(&path) {
+            format!("<article class=\"markdown\">\n{}</article>", render_markdown(&text))
+        } else {
+            format!("<pre>{}</pre>", escape_html(&text))
+        };
+        let name = req.path().rsplit('/').next().unwrap_or("");
+        let html = TEMPLATE
+            .replace("{{stylesheet}}", &format!("<link rel=\"stylesheet\" href=\"{}\">", escape_html(&mount.external(&ui::current().css_url))))
+            .replace("{{title}}", &escape_html(&percent_decode_str(name).decode_utf8_lossy()))
+            .replace("{{parent}}", "./")
+            .replace("{{back_label}}", t(Msg::BackToListing))
+            .replace("{{download}}", &escape_html(name))
+            .replace("{{download_label}}", t(Msg::Download))
+            .replace("{{content}}", &content);
+        let mut res = HttpResponse::Ok();
+        if let Some(ref csp) = mount.csp {
+            res.header(CONTENT_SECURITY_POLICY, csp.clone());
+        }
+        Ok(req.into_response(res.content_type("text/html; charset=utf-8").body(html)))
+    }
+}
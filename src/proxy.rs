@@ -0,0 +1,85 @@
+use std::net::{IpAddr, SocketAddr};
+
+use ipnet::IpNet;
+use ntex::http::HeaderMap;
+
+// 反向代理设置：未启用 --trust-proxy 时忽略转发头，防止客户端伪造地址；
+// 指定了 --trusted-proxy 时只接受来自这些地址段的转发头
+#[derive(Clone, Debug, Default)]
+pub struct TrustProxy {
+    enabled: bool,
+    proxies: Vec<IpNet>,
+}
+
+impl TrustProxy {
+    pub fn new(enabled: bool, proxies: Vec<IpNet>) -> Self {
+        TrustProxy { enabled, proxies }
+    }
+
+    // 未限制代理地址时信任任何来源
+    fn trusts(&self, ip: IpAddr) -> bool {
+        self.proxies.is_empty() || self.proxies.iter().any(|net| net.contains(&ip))
+    }
+}
+
+// 获取客户端地址，只有连接来自受信任的代理时才读取 X-Forwarded-For 或 X-Real-IP；
+// 双栈监听时 IPv4 客户端表现为 ::ffff:a.b.c.d，统一还原为 IPv4 地址
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, trust: &TrustProxy) -> Option<IpAddr> {
+    let peer = peer.map(|addr| addr.ip().to_canonical());
+    if !trust.enabled || !peer.is_some_and(|ip| trust.trusts(ip)) {
+        return peer;
+    }
+    forwarded_for(headers, trust)
+        .or_else(|| {
+            headers
+                .get("x-real-ip")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<IpAddr>().ok())
+        })
+        .map(|ip| ip.to_canonical())
+        .or(peer)
+}
+
+// 未限制代理地址时取最左侧的地址；否则从右向左跳过受信任的代理，
+// 第一个不受信任的地址才是客户端，更左侧的部分可能由客户端伪造
+fn forwarded_for(headers: &HeaderMap, trust: &TrustProxy) -> Option<IpAddr> {
+    let value = headers.get("x-forwarded-for")?.to_str().ok()?;
+    let mut hops = value.split(',').map(|hop| hop.trim().parse::<IpAddr>().ok().map(|ip| ip.to_canonical()));
+    if trust.proxies.is_empty() {
+        return hops.next()?;
+    }
+    let hops: Vec<_> = hops.collect::<Option<_>>()?;
+    hops.iter().rev().find(|ip| !trust.trusts(**ip)).or(hops.first()).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::header::{HeaderName, HeaderValue};
+
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(HeaderName::from_static(name), HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn honors_forwarded_headers_only_from_trusted_proxies() {
+        let peer = Some("10.0.0.2:40000".parse().unwrap());
+        let xff = headers(&[("x-forwarded-for", "1.2.3.4, 203.0.113.9, 10.0.0.3")]);
+
+        assert_eq!(client_ip(&xff, peer, &TrustProxy::default()), Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(client_ip(&xff, peer, &TrustProxy::new(true, Vec::new())), Some("1.2.3.4".parse().unwrap()));
+
+        let trusted = TrustProxy::new(true, vec!["10.0.0.0/8".parse().unwrap()]);
+        assert_eq!(client_ip(&xff, peer, &trusted), Some("203.0.113.9".parse().unwrap()));
+        let outside = Some("198.51.100.7:40000".parse().unwrap());
+        assert_eq!(client_ip(&xff, outside, &trusted), Some("198.51.100.7".parse().unwrap()));
+
+        let real_ip = headers(&[("x-real-ip", "203.0.113.5")]);
+        assert_eq!(client_ip(&real_ip, peer, &trusted), Some("203.0.113.5".parse().unwrap()));
+    }
+}
@@ -0,0 +1,19 @@
+use qrcode::QrCode;
+use qrcode::render::unicode::Dense1x2;
+
+use crate::i18n::{Msg, tf};
+
+// 在终端输出地址的二维码，手机扫码即可访问；生成失败只给出警告
+pub fn print(url: &str) {
+    match QrCode::new(url.as_bytes()) {
+        Ok(code) => {
+            let image = code
+                .render::<Dense1x2>()
+                .dark_color(Dense1x2::Light)
+                .light_color(Dense1x2::Dark)
+                .build();
+            println!("{}", image);
+        }
+        Err(e) => log::warn!("{}", tf(Msg::QrFailed, &[&e])),
+    }
+}
@@ -0,0 +1,11 @@
+// 从查询字符串中取出指定参数的值
+pub fn get(query: &str, name: &str) -> Option<String> {
+    form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+// 判断开关型参数是否开启，如 ?zip=1 或 ?zip=true
+pub fn flag(query: &str, name: &str) -> bool {
+    matches!(get(query, name).as_deref(), Some("1" | "true"))
+}
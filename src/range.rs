@@ -0,0 +1,277 @@
+use std::error::Error;
+use std::fs::Metadata;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::UNIX_EPOCH;
+
+use ntex::http::StatusCode;
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::http::header::{CONTENT_RANGE, IF_RANGE, RANGE};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{WebRequest, WebResponse};
+
+use crate::mount::{self, Mount};
+
+// ntex-files 对范围请求使用分块传输，不带 Content-Length，
+// 部分下载工具因此无法断点续传，这里按 Content-Range 补上响应体长度
+#[derive(Clone, Default)]
+pub struct RangeLength;
+
+// 从 "bytes 100-199/1000" 中计算出范围长度
+fn range_length(value: &str) -> Option<u64> {
+    let (range, _) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+    end.checked_sub(start).map(|len| len + 1)
+}
+
+// 长度已知的响应体，数据原样转发
+struct SizedBody {
+    body: ResponseBody<Body>,
+    size: u64,
+}
+
+impl MessageBody for SizedBody {
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.size)
+    }
+
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        self.body.poll_next_chunk(cx)
+    }
+}
+
+impl<S> Middleware<S> for RangeLength {
+    type Service = RangeLengthMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RangeLengthMiddleware { service }
+    }
+}
+
+pub struct RangeLengthMiddleware<S> {
+    service: S,
+}
+
+impl<S, E> Service<WebRequest<E>> for RangeLengthMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let res = ctx.call(&self.service, req).await?;
+        if res.status() != StatusCode::PARTIAL_CONTENT || res.response().body().size() != BodySize::Stream {
+            return Ok(res);
+        }
+
+        match res.headers().get(CONTENT_RANGE).and_then(|value| value.to_str().ok()).and_then(range_length) {
+            Some(size) => Ok(res.map_body(move |_, body| ResponseBody::Other(Body::from_message(SizedBody { body, size })))),
+            None => Ok(res),
+        }
+    }
+}
+
+// ntex-files 不处理 If-Range，文件修改后续传的请求仍会拿到新文件的片段，拼出损坏的文件。
+// If-Range 与文件当前的 ETag 或 Last-Modified 不一致时去掉 Range，返回完整的 200 响应。
+// 需注册在 Precompressed 之外，让预压缩文件的请求也按此处理
+#[derive(Clone)]
+pub struct IfRange {
+    mounts: Rc<Vec<Mount>>,
+}
+
+impl IfRange {
+    pub fn new(mounts: Vec<Mount>) -> Self {
+        IfRange { mounts: Rc::new(mounts) }
+    }
+}
+
+// 与 ntex-files 生成的强 ETag 格式相同: "inode:长度:秒:纳秒"，均为十六进制
+fn etag(metadata: &Metadata) -> Option<String> {
+    #[cfg(unix)]
+    let ino = std::os::unix::fs::MetadataExt::ino(metadata);
+    #[cfg(not(unix))]
+    let ino = 0;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!("\"{:x}:{:x}:{:x}:{:x}\"", ino, metadata.len(), modified.as_secs(), modified.subsec_nanos()))
+}
+
+// If-Range 只做强比较：弱 ETag 一律不匹配；日期须与 Last-Modified(精确到秒)完全相同
+fn if_range_matches(value: &str, metadata: &Metadata) -> bool {
+    let value = value.trim();
+    if value.starts_with('"') {
+        return etag(metadata).is_some_and(|etag| etag == value);
+    }
+    if value.starts_with("W/") {
+        return false;
+    }
+    let since = match jiff::fmt::rfc2822::DateTimeParser::new().parse_timestamp(value) {
+        Ok(since) => since,
+        Err(_) => return false,
+    };
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .is_some_and(|modified| i64::try_from(modified.as_secs()).is_ok_and(|secs| secs == since.as_second()))
+}
+
+impl<S> Middleware<S> for IfRange {
+    type Service = IfRangeMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        IfRangeMiddleware {
+            service,
+            mounts: self.mounts.clone(),
+        }
+    }
+}
+
+pub struct IfRangeMiddleware<S> {
+    service: S,
+    mounts: Rc<Vec<Mount>>,
+}
+
+impl<S, E> Service<WebRequest<E>> for IfRangeMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, mut req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if !req.headers().contains_key(RANGE) {
+            return ctx.call(&self.service, req).await;
+        }
+        let if_range = match req.headers().get(IF_RANGE) {
+            Some(value) => value.to_str().unwrap_or_default().to_string(),
+            None => return ctx.call(&self.service, req).await,
+        };
+        let metadata = match mount::find(&self.mounts, req.path())
+            .and_then(|mount| mount.resolve(req.path()))
+            .and_then(|path| std::fs::metadata(path).ok())
+        {
+            Some(metadata) if metadata.is_file() => metadata,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        if !if_range_matches(&if_range, &metadata) {
+            req.headers_mut().remove(RANGE);
+        }
+        ctx.call(&self.service, req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use ntex::http::header::{ETAG, HeaderName, HeaderValue, LAST_MODIFIED};
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    fn contents() -> Vec<u8> {
+        (0..1000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    async fn fetch_range(range: &str) -> WebResponse {
+        let dir = TempDir::new();
+        dir.write("data.bin", contents());
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(web::App::new().wrap(RangeLength).service(testutil::files(&mount))).await;
+        let req = test::TestRequest::with_uri("/download/files/data.bin").header(RANGE, range).to_request();
+        test::call_service(&app, req).await
+    }
+
+    // Content-Length 由 HTTP 层按响应体声明的长度写出
+    fn content_length(res: &WebResponse) -> Option<u64> {
+        match res.response().body().size() {
+            BodySize::Sized(size) => Some(size),
+            _ => None,
+        }
+    }
+
+    fn header(res: &WebResponse, name: HeaderName) -> Option<&HeaderValue> {
+        res.headers().get(name)
+    }
+
+    #[ntex::test]
+    async fn bounded_range_returns_slice() {
+        let res = fetch_range("bytes=100-199").await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&res, CONTENT_RANGE).unwrap(), "bytes 100-199/1000");
+        assert_eq!(content_length(&res), Some(100));
+        assert_eq!(test::read_body(res).await, contents()[100..200]);
+    }
+
+    #[ntex::test]
+    async fn open_ended_range_returns_rest_of_file() {
+        let res = fetch_range("bytes=500-").await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&res, CONTENT_RANGE).unwrap(), "bytes 500-999/1000");
+        assert_eq!(content_length(&res), Some(500));
+        assert_eq!(test::read_body(res).await, contents()[500..]);
+    }
+
+    #[ntex::test]
+    async fn unsatisfiable_range_returns_416() {
+        let res = fetch_range("bytes=2000-2999").await;
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(header(&res, CONTENT_RANGE).unwrap(), "bytes */1000");
+        assert!(test::read_body(res).await.is_empty());
+    }
+
+    #[ntex::test]
+    async fn if_range_resumes_only_unchanged_file() {
+        let dir = TempDir::new();
+        let path = dir.write("data.bin", contents());
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(
+            web::App::new()
+                .wrap(RangeLength)
+                .wrap(IfRange::new(vec![mount.clone()]))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+        let resume = |if_range: &str| {
+            test::TestRequest::with_uri("/download/files/data.bin")
+                .header(RANGE, "bytes=100-199")
+                .header(IF_RANGE, if_range)
+                .to_request()
+        };
+
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/data.bin").to_request()).await;
+        let etag = header(&res, ETAG).unwrap().to_str().unwrap().to_string();
+        let last_modified = header(&res, LAST_MODIFIED).unwrap().to_str().unwrap().to_string();
+        for validator in [&etag, &last_modified] {
+            let res = test::call_service(&app, resume(validator)).await;
+            assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT, "{}", validator);
+            assert_eq!(test::read_body(res).await, contents()[100..200]);
+        }
+        let res = test::call_service(&app, resume(&format!("W/{}", etag))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // 文件被替换后旧的 ETag 和修改时间都不再匹配，返回完整的新文件
+        let changed: Vec<u8> = contents().iter().rev().copied().collect();
+        dir.write("data.bin", &changed);
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+        for validator in [&etag, &last_modified] {
+            let res = test::call_service(&app, resume(validator)).await;
+            assert_eq!(res.status(), StatusCode::OK, "{}", validator);
+            assert!(header(&res, CONTENT_RANGE).is_none());
+            assert_eq!(test::read_body(res).await, changed);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use ntex::http::Method;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, t};
+
+// 只读模式：拒绝 GET、HEAD、OPTIONS 以外的所有请求。
+// 上传、删除、新建目录等写操作都不是这几个方法，无论各自的开关是否打开都会在这里被拦截
+#[derive(Clone)]
+pub struct ReadOnly {
+    enabled: bool,
+}
+
+impl ReadOnly {
+    pub fn new(enabled: bool) -> Self {
+        ReadOnly { enabled }
+    }
+}
+
+impl<S> Middleware<S> for ReadOnly {
+    type Service = ReadOnlyMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ReadOnlyMiddleware {
+            service,
+            enabled: self.enabled,
+        }
+    }
+}
+
+pub struct ReadOnlyMiddleware<S> {
+    service: S,
+    enabled: bool,
+}
+
+impl<S, E> Service<WebRequest<E>> for ReadOnlyMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if self.enabled && !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::ReadOnlyRejected))));
+        }
+        ctx.call(&self.service, req).await
+    }
+}
@@ -0,0 +1,112 @@
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::header::LOCATION;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::types::State;
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::mount::{self, Mount};
+
+// 没有挂载在 / 时，访问 / 跳转到的挂载点路径
+#[derive(Clone)]
+pub struct RootRedirect(pub String);
+
+pub async fn root(target: State<RootRedirect>) -> HttpResponse {
+    HttpResponse::Found().header(LOCATION, target.0.as_str()).finish()
+}
+
+// 目录地址缺少结尾的 / 时以 301 跳转到带 / 的地址并保留查询参数，否则列表中的相对链接和面包屑会指向上一级目录；
+// 文件地址不跳转。需注册在 HiddenPaths 之外，被过滤或隐藏的目录先由外层返回 404，不会因跳转暴露其存在
+#[derive(Clone)]
+pub struct TrailingSlash {
+    mounts: Rc<Vec<Mount>>,
+}
+
+impl TrailingSlash {
+    pub fn new(mounts: Vec<Mount>) -> Self {
+        TrailingSlash { mounts: Rc::new(mounts) }
+    }
+}
+
+impl<S> Middleware<S> for TrailingSlash {
+    type Service = TrailingSlashMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        TrailingSlashMiddleware {
+            service,
+            mounts: self.mounts.clone(),
+        }
+    }
+}
+
+pub struct TrailingSlashMiddleware<S> {
+    service: S,
+    mounts: Rc<Vec<Mount>>,
+}
+
+impl<S, E> Service<WebRequest<E>> for TrailingSlashMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if !matches!(*req.method(), Method::GET | Method::HEAD) || req.path().ends_with('/') {
+            return ctx.call(&self.service, req).await;
+        }
+        // 不显示列表也没有首页文件的挂载点中目录与不存在的路径一样返回 404，同样不跳转
+        let mount = match mount::find(&self.mounts, req.path()) {
+            Some(mount) if mount.listing || mount.index_file.is_some() => mount,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        if !mount.resolve(req.path()).is_some_and(|path| path.is_dir()) {
+            return ctx.call(&self.service, req).await;
+        }
+        let location = match req.query_string() {
+            "" => format!("{}/", mount.external(req.path())),
+            query => format!("{}/?{}", mount.external(req.path()), query),
+        };
+        Ok(req.into_response(HttpResponse::MovedPermanently().header(LOCATION, location).finish()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn redirects_directories_missing_trailing_slash() {
+        let dir = TempDir::new();
+        dir.write("docs/a.txt", "a");
+        let mut mount = dir.mount("/download/files");
+        mount.base_path = "/share".to_string();
+        let app = test::init_service(
+            web::App::new()
+                .wrap(TrailingSlash::new(vec![mount.clone()]))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::with_uri(uri).to_request();
+
+        let res = test::call_service(&app, get("/download/files/docs?sort=size")).await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(res.headers().get(LOCATION).unwrap(), "/share/download/files/docs/?sort=size");
+        let res = test::call_service(&app, get("/download/files")).await;
+        assert_eq!(res.headers().get(LOCATION).unwrap(), "/share/download/files/");
+
+        let res = test::call_service(&app, get("/download/files/docs/")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = test::call_service(&app, get("/download/files/docs/a.txt")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
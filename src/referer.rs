@@ -0,0 +1,118 @@
+use std::rc::Rc;
+
+use ntex::http::header::REFERER;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::health;
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+
+// 取出 URL 中的主机名，去掉协议、用户信息和端口
+fn host_of(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    hostname(rest.split(['/', '?', '#']).next()?)
+}
+
+// host[:port] 或 [ipv6]:port 中的主机名
+fn hostname(authority: &str) -> Option<&str> {
+    let host = authority.rsplit('@').next()?;
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+// 主机名是否匹配规则：example.com 完全匹配，*.example.com 匹配其所有子域名
+fn matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host.len() > domain.len() + 1 && {
+            let (sub, tail) = host.split_at(host.len() - domain.len());
+            sub.ends_with('.') && tail.eq_ignore_ascii_case(domain)
+        },
+        None => pattern.eq_ignore_ascii_case(host),
+    }
+}
+
+// 设置 --allowed-referer 时防止盗链：文件请求的 Referer 主机不在允许列表中时返回 403。
+// 来自本站页面的请求始终允许；没有 Referer 的请求由 --allow-empty-referer 决定；目录列表不受限制
+#[derive(Clone)]
+pub struct RefererCheck {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    allowed: Vec<String>,
+    allow_empty: bool,
+}
+
+impl RefererCheck {
+    pub fn new(mounts: Vec<Mount>, allowed: &[String], allow_empty: bool) -> Self {
+        RefererCheck {
+            inner: Rc::new(Inner {
+                mounts,
+                allowed: allowed.to_vec(),
+                allow_empty,
+            }),
+        }
+    }
+}
+
+impl Inner {
+    fn permits<E>(&self, req: &WebRequest<E>) -> bool {
+        let referer = match req.headers().get(REFERER) {
+            Some(referer) => referer,
+            None => return self.allow_empty,
+        };
+        let host = match referer.to_str().ok().and_then(host_of) {
+            Some(host) => host,
+            None => return false,
+        };
+        let own = req.connection_info().host().to_string();
+        hostname(&own).is_some_and(|own| own.eq_ignore_ascii_case(host)) || self.allowed.iter().any(|pattern| matches(pattern, host))
+    }
+}
+
+impl<S> Middleware<S> for RefererCheck {
+    type Service = RefererCheckMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RefererCheckMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct RefererCheckMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for RefererCheckMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        if self.inner.allowed.is_empty() || req.path() == health::PATH {
+            return ctx.call(&self.service, req).await;
+        }
+        let is_file = mount::find(&self.inner.mounts, req.path())
+            .and_then(|mount| mount.resolve(req.path()))
+            .is_some_and(|path| path.is_file());
+        if is_file && !self.inner.permits(&req) {
+            log::warn!("{}", tf(Msg::HotlinkRejected, &[&req.path(), &format!("{:?}", req.headers().get(REFERER))]));
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::HotlinkForbidden))));
+        }
+        ctx.call(&self.service, req).await
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::{Arc, RwLock};
+
+use clap::{ArgMatches, FromArgMatches};
+use ipnet::IpNet;
+
+use crate::Args;
+use crate::config;
+use crate::glob::IgnoreFile;
+use crate::i18n::{Msg, t, tf};
+
+// 运行期间可以通过 SIGHUP 重新加载的配置
+#[derive(Debug, PartialEq)]
+pub struct Settings {
+    pub log_level: String,
+    pub rate_limit: u64,
+    pub total_rate_limit: u64,
+    pub allow: Vec<IpNet>,
+    pub deny: Vec<IpNet>,
+}
+
+impl Settings {
+    pub fn from_args(args: &Args) -> Self {
+        Settings {
+            log_level: args.effective_log_level().to_string(),
+            rate_limit: args.rate_limit,
+            total_rate_limit: args.total_rate_limit,
+            allow: args.allow.clone(),
+            deny: args.deny.clone(),
+        }
+    }
+}
+
+// 所有工作线程共享的当前配置，重新加载时整体替换，已在处理的请求继续使用旧配置
+#[derive(Clone)]
+pub struct Live {
+    current: Arc<RwLock<Arc<Settings>>>,
+}
+
+impl Live {
+    pub fn new(settings: Settings) -> Self {
+        Live {
+            current: Arc::new(RwLock::new(Arc::new(settings))),
+        }
+    }
+
+    pub fn load(&self) -> Arc<Settings> {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn store(&self, settings: Settings) {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(settings);
+    }
+}
+
+// 设置了 RUST_LOG 时日志级别由它决定，否则按 --log-level 调整
+pub fn set_log_level(level: &str) {
+    if std::env::var_os("RUST_LOG").is_none()
+        && let Ok(level) = level.parse()
+    {
+        log::set_max_level(level);
+    }
+}
+
+// 收到 SIGHUP 时重新读取配置文件；startup 为启动时生效的参数，用于判断哪些修改需要重启
+#[cfg(unix)]
+pub fn spawn(live: Live, matches: ArgMatches, startup: Args, ignore_files: Vec<IgnoreFile>) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    ntex::rt::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                log::error!("{}", tf(Msg::ListenSighupFailed, &[&e]));
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            // 忽略文件不依赖配置文件，未指定 --config 时也重新读取
+            for file in &ignore_files {
+                file.reload();
+            }
+            reload(&live, &matches, &startup);
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn(_live: Live, _matches: ArgMatches, _startup: Args, _ignore_files: Vec<IgnoreFile>) {}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+fn reload(live: &Live, matches: &ArgMatches, startup: &Args) {
+    let path = match startup.config {
+        Some(ref path) => path,
+        None => {
+            log::warn!("{}", t(Msg::ReloadWithoutConfig));
+            return;
+        }
+    };
+    // 与启动时相同：命令行显式指定的值优先于配置文件
+    let file_config = match config::load(path) {
+        Ok(file_config) => file_config,
+        Err(e) => {
+            log::error!("{}", tf(Msg::ReloadFailed, &[&e]));
+            return;
+        }
+    };
+    let mut args = match Args::from_arg_matches(matches) {
+        Ok(args) => args,
+        Err(e) => {
+            log::error!("{}", tf(Msg::ReloadFailed, &[&e]));
+            return;
+        }
+    };
+    config::merge(&mut args, matches, file_config);
+
+    let restart_required = [
+        ("port", args.port != startup.port),
+        ("host", args.host != startup.host),
+        ("tls_cert", args.tls_cert != startup.tls_cert),
+        ("tls_key", args.tls_key != startup.tls_key),
+        ("client_ca", args.client_ca != startup.client_ca),
+        ("http2", args.http2 != startup.http2),
+        ("keep_alive", args.keep_alive != startup.keep_alive),
+        ("backlog", args.backlog != startup.backlog),
+    ];
+    for (name, _) in restart_required.iter().filter(|(_, changed)| *changed) {
+        log::warn!("{}", tf(Msg::RestartRequired, &[name]));
+    }
+
+    let settings = Settings::from_args(&args);
+    if *live.load() == settings {
+        log::info!("{}", t(Msg::ReloadUnchanged));
+        return;
+    }
+    set_log_level(&settings.log_level);
+    live.store(settings);
+    log::info!("{}", tf(Msg::Reloaded, &[path]));
+}
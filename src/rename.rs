@@ -0,0 +1,230 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::error::BlockingError;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 重命名或移动: POST {URL路径}/<文件>?action=rename&to=<新名称>。
+// to 不以 / 开头时相对于原文件所在目录，以 / 开头时相对于挂载点根目录；目标已存在时需带 ?overwrite=true
+#[derive(Clone)]
+pub struct Rename {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    enabled: bool,
+}
+
+impl Rename {
+    pub fn new(mounts: Vec<Mount>, enabled: bool) -> Self {
+        Rename {
+            inner: Rc::new(Inner { mounts, enabled }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Rename {
+    type Service = RenameMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RenameMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct RenameMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for RenameMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let mount = match mount::find(&self.inner.mounts, req.path()) {
+            Some(mount) if *req.method() == Method::POST && query::get(req.query_string(), "action").as_deref() == Some("rename") => mount,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        if !self.inner.enabled {
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::RenameDisabled))));
+        }
+
+        let source = match mount.resolve(req.path()) {
+            Some(path) if path != mount.dir => path,
+            _ => return Ok(req.into_response(HttpResponse::BadRequest().body(t(Msg::InvalidPath)))),
+        };
+        let target = match query::get(req.query_string(), "to").and_then(|to| target(mount, &source, &to)) {
+            Some(target) if target != source && !target.starts_with(&source) => target,
+            _ => return Ok(req.into_response(HttpResponse::BadRequest().body(t(Msg::InvalidTarget)))),
+        };
+        // 请求路径已由 SymlinkGuard 检查，目标路径需要单独检查
+        if !mount.follow_symlinks && mount.escapes(&target) {
+            log::warn!("{}", tf(Msg::RenameSymlinkRejected, &[&target.display()]));
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::OutsideShare))));
+        }
+        // 目标不能是被 --include/--exclude、--deny-ext、--max-depth 或忽略文件过滤的路径
+        if !mount.allows(&target, source.is_dir()) {
+            log::warn!("{}", tf(Msg::RenameFilteredRejected, &[&target.display()]));
+            return Ok(req.into_response(HttpResponse::Forbidden().body(tf(Msg::RenameNotAllowed, &[&url(mount, &target)]))));
+        }
+
+        let from = url(mount, &source);
+        let to = url(mount, &target);
+        let overwrite = query::flag(req.query_string(), "overwrite");
+        let response = match rename(source.clone(), target.clone(), overwrite).await {
+            Ok(()) => {
+                log::info!("{}", tf(Msg::Renamed, &[&source.display(), &target.display()]));
+                HttpResponse::Ok().json(&serde_json::json!({ "from": from, "to": to }))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HttpResponse::NotFound().body(tf(Msg::NotExists, &[&from])),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                HttpResponse::Conflict().body(tf(Msg::AlreadyExists, &[&to]))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotADirectory => HttpResponse::Conflict().body(tf(Msg::ParentMissing, &[&to])),
+            Err(e) if matches!(e.kind(), io::ErrorKind::IsADirectory | io::ErrorKind::DirectoryNotEmpty) => {
+                HttpResponse::Conflict().body(tf(Msg::OverwriteDir, &[&to]))
+            }
+            Err(e) => {
+                log::error!("{}", tf(Msg::RenameError, &[&source.display(), &target.display(), &e]));
+                HttpResponse::InternalServerError().body(tf(Msg::RenameFailed, &[&from]))
+            }
+        };
+        Ok(req.into_response(response))
+    }
+}
+
+// 解析 to 参数对应的文件路径，与 Mount::resolve 一样拒绝 ..、隐藏文件和反斜杠等越界写法
+fn target(mount: &Mount, source: &Path, to: &str) -> Option<PathBuf> {
+    let mut buf = match to.strip_prefix('/') {
+        Some(_) => mount.dir.clone(),
+        None => source.parent()?.to_path_buf(),
+    };
+    for segment in to.split('/').filter(|s| !s.is_empty()) {
+        if segment == "." || segment == ".." || !mount.is_visible(segment) || segment.contains(['\\', '\0']) {
+            return None;
+        }
+        buf.push(segment);
+    }
+    (buf != mount.dir).then_some(buf)
+}
+
+// 共享目录下的文件路径对应的URL路径，用于响应
+fn url(mount: &Mount, path: &Path) -> String {
+    let mut url = mount.url_path.trim_end_matches('/').to_string();
+    for component in path.strip_prefix(&mount.dir).unwrap_or(path).components() {
+        url.push('/');
+        url.push_str(&component.as_os_str().to_string_lossy());
+    }
+    url
+}
+
+// 在线程池中重命名；不覆盖时先检查目标是否存在，目标的上级目录必须已存在
+async fn rename(source: PathBuf, target: PathBuf, overwrite: bool) -> io::Result<()> {
+    let result = web::block(move || {
+        std::fs::symlink_metadata(&source)?;
+        if !target.parent().is_some_and(Path::is_dir) {
+            return Err(io::Error::from(io::ErrorKind::NotADirectory));
+        }
+        match std::fs::symlink_metadata(&target) {
+            Ok(_) if !overwrite => return Err(io::Error::from(io::ErrorKind::AlreadyExists)),
+            Ok(metadata) if metadata.is_dir() => return Err(io::Error::from(io::ErrorKind::IsADirectory)),
+            _ => {}
+        }
+        std::fs::rename(&source, &target)
+    })
+    .await;
+    match result {
+        Ok(()) => Ok(()),
+        Err(BlockingError::Error(e)) => Err(e),
+        Err(BlockingError::Canceled) => Err(io::Error::other(t(Msg::RenameInterrupted))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::test;
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn renames_within_share_and_refuses_overwrite() {
+        let dir = TempDir::new();
+        dir.write("a.txt", "a");
+        dir.write("b.txt", "b");
+        dir.write("sub/c.txt", "c");
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(web::App::new().wrap(Rename::new(vec![mount.clone()], true)).service(testutil::files(&mount))).await;
+        let rename = |uri: &str| test::TestRequest::with_uri(uri).method(Method::POST).to_request();
+
+        let res = test::call_service(&app, rename("/download/files/a.txt?action=rename&to=b.txt")).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert_eq!(std::fs::read_to_string(dir.path().join("b.txt")).unwrap(), "b");
+
+        let body = test::read_response(&app, rename("/download/files/a.txt?action=rename&to=%2Fsub%2Fd.txt")).await;
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["from"], "/download/files/a.txt");
+        assert_eq!(json["to"], "/download/files/sub/d.txt");
+        assert_eq!(std::fs::read_to_string(dir.path().join("sub/d.txt")).unwrap(), "a");
+
+        let res = test::call_service(&app, rename("/download/files/sub/d.txt?action=rename&to=c.txt&overwrite=true")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(dir.path().join("sub/c.txt")).unwrap(), "a");
+
+        for uri in [
+            "/download/files/b.txt?action=rename&to=../escape.txt",
+            "/download/files/b.txt?action=rename&to=%2F..%2Fescape.txt",
+            "/download/files/b.txt?action=rename&to=.hidden",
+            "/download/files/b.txt?action=rename&to=/",
+            "/download/files/sub?action=rename&to=sub/inner",
+            "/download/files/%2e%2e/x?action=rename&to=y",
+        ] {
+            let res = test::call_service(&app, rename(uri)).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+        assert!(dir.path().join("b.txt").exists());
+        assert!(!dir.path().parent().unwrap().join("escape.txt").exists());
+    }
+
+    #[ntex::test]
+    async fn refuses_filtered_targets() {
+        let dir = TempDir::new();
+        dir.write("a.txt", "a");
+        dir.write(crate::glob::IGNORE_FILE, "secret/\n");
+        let mut mount = dir.mount("/download/files");
+        mount.filter = crate::glob::PathFilter::default()
+            .with_denied_extensions(&["exe".to_string()])
+            .with_ignore_file(dir.path().join(crate::glob::IGNORE_FILE));
+        let app = test::init_service(web::App::new().wrap(Rename::new(vec![mount.clone()], true)).service(testutil::files(&mount))).await;
+        let rename = |uri: &str| test::TestRequest::with_uri(uri).method(Method::POST).to_request();
+
+        std::fs::create_dir(dir.path().join("secret")).unwrap();
+        for uri in [
+            "/download/files/a.txt?action=rename&to=a.exe",
+            "/download/files/a.txt?action=rename&to=secret%2Fa.txt",
+        ] {
+            let res = test::call_service(&app, rename(uri)).await;
+            assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", uri);
+        }
+        assert!(dir.path().join("a.txt").exists());
+    }
+}
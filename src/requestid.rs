@@ -0,0 +1,79 @@
+use ntex::http::header::{HeaderName, HeaderValue};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{WebRequest, WebResponse};
+
+pub const HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// 客户端或前置代理传入的请求ID最长保留的字符数
+const MAX_LEN: usize = 128;
+
+// 请求ID，保存在请求的 extensions 中供访问日志读取
+#[derive(Clone)]
+pub struct RequestId(pub String);
+
+// 为每个请求生成随机的 UUID，写入 X-Request-Id 响应头；请求中已带有合法的 X-Request-Id 时沿用，
+// 便于把客户端看到的响应头与访问日志对应起来。需在访问日志中间件之外注册
+#[derive(Clone)]
+pub struct RequestIds;
+
+impl<S> Middleware<S> for RequestIds {
+    type Service = RequestIdsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RequestIdsMiddleware { service }
+    }
+}
+
+pub struct RequestIdsMiddleware<S> {
+    service: S,
+}
+
+impl<S, E> Service<WebRequest<E>> for RequestIdsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let id = req
+            .headers()
+            .get(&HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| is_valid(value))
+            .map_or_else(generate, str::to_string);
+        req.extensions_mut().insert(RequestId(id.clone()));
+
+        let mut res = ctx.call(&self.service, req).await?;
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            res.headers_mut().insert(HEADER, value);
+        }
+        Ok(res)
+    }
+}
+
+// 只接受字母、数字和 -_.: 组成的ID，避免在日志中写入换行等控制字符
+fn is_valid(value: &str) -> bool {
+    !value.is_empty() && value.len() <= MAX_LEN && value.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_.:".contains(&b))
+}
+
+// 随机生成的 UUID v4
+fn generate() -> String {
+    let mut bytes = [0u8; 16];
+    if getrandom::fill(&mut bytes).is_err() {
+        // 系统随机源不可用时退化为时间和计数器，仍能区分不同请求
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        bytes[..8].copy_from_slice(&nanos.to_be_bytes());
+        bytes[8..].copy_from_slice(&COUNTER.fetch_add(1, Ordering::Relaxed).to_be_bytes());
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
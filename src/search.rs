@@ -0,0 +1,156 @@
+use std::path::Path;
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use percent_encoding::utf8_percent_encode;
+use serde::Serialize;
+
+use crate::i18n::{Msg, tf};
+use crate::listing::SEGMENT;
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 搜索时遍历的最大目录深度
+const MAX_DEPTH: usize = 16;
+
+#[derive(Serialize)]
+struct Hit {
+    path: String,
+    name: String,
+    is_dir: bool,
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct SearchResult {
+    query: String,
+    results: Vec<Hit>,
+    truncated: bool,
+}
+
+// 目录请求带 ?search=关键字 时，在该目录下按文件名(不区分大小写)搜索，返回 JSON
+#[derive(Clone)]
+pub struct Search {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    max_results: usize,
+}
+
+impl Search {
+    pub fn new(mounts: Vec<Mount>, max_results: usize) -> Self {
+        Search {
+            inner: Rc::new(Inner { mounts, max_results }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Search {
+    type Service = SearchMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        SearchMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct SearchMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for SearchMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let term = match query::get(req.query_string(), "search") {
+            Some(term) if *req.method() == Method::GET && !term.trim().is_empty() => term.trim().to_string(),
+            _ => return ctx.call(&self.service, req).await,
+        };
+        let resolved = mount::find(&self.inner.mounts, req.path()).and_then(|mount| Some((mount.clone(), mount.resolve(req.path())?)));
+        let (mount, dir) = match resolved {
+            Some((mount, dir)) if mount.listing && dir.is_dir() => (mount, dir),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let base = format!("{}/", mount.external(req.path()).trim_end_matches('/'));
+        let max_results = self.inner.max_results;
+        let needle = term.to_lowercase();
+        let result = web::block(move || {
+            let mut results = Vec::new();
+            let truncated = walk(&mount, &dir, &base, &needle, 0, max_results, &mut results);
+            Ok::<_, std::io::Error>((results, truncated))
+        })
+        .await;
+
+        let response = match result {
+            Ok((results, truncated)) => HttpResponse::Ok().json(&SearchResult { query: term, results, truncated }),
+            Err(e) => {
+                log::error!("{}", tf(Msg::SearchFailed, &[&req.path(), &e]));
+                HttpResponse::InternalServerError().finish()
+            }
+        };
+        Ok(req.into_response(response))
+    }
+}
+
+// 递归搜索，结果达到上限时停止并返回 true；读取失败的目录直接跳过
+fn walk(mount: &Mount, dir: &Path, base: &str, needle: &str, depth: usize, max_results: usize, results: &mut Vec<Hit>) -> bool {
+    if depth > MAX_DEPTH {
+        return false;
+    }
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !mount.is_visible(&name) {
+            continue;
+        }
+        let path = entry.path();
+        // 未启用 --follow-symlinks 时不进入指向共享目录之外的符号链接
+        let is_symlink = entry.file_type().is_ok_and(|file_type| file_type.is_symlink());
+        if is_symlink && !mount.follow_symlinks && mount.escapes(&path) {
+            continue;
+        }
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if !mount.allows(&path, metadata.is_dir()) {
+            continue;
+        }
+
+        let href = format!("{}{}", base, utf8_percent_encode(&name, SEGMENT));
+        if name.to_lowercase().contains(needle) {
+            if results.len() >= max_results {
+                return true;
+            }
+            results.push(Hit {
+                path: if metadata.is_dir() { format!("{}/", href) } else { href.clone() },
+                name,
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+            });
+        }
+        if metadata.is_dir() && walk(mount, &path, &format!("{}/", href), needle, depth + 1, max_results, results) {
+            return true;
+        }
+    }
+    false
+}
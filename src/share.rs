@@ -0,0 +1,196 @@
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use ntex::http::Method;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+use percent_encoding::percent_decode_str;
+use sha2::Sha256;
+
+use crate::i18n::{Msg, t};
+use crate::mount::{self, Mount};
+use crate::query;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 请求已通过分享链接授权，BasicAuth 看到该标记时不再要求账号密码
+pub struct Granted;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+// 对解码后的请求路径和过期时间计算 HMAC-SHA256
+fn mac(secret: &[u8], path: &str, expires: u64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC 接受任意长度的密钥");
+    let path = percent_decode_str(path).decode_utf8_lossy();
+    mac.update(format!("{}\n{}", path, expires).as_bytes());
+    mac
+}
+
+fn sign(secret: &[u8], path: &str, expires: u64) -> String {
+    mac(secret, path, expires)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// 常量时间校验签名，签名不是合法的十六进制时直接失败
+fn verify(secret: &[u8], path: &str, expires: u64, token: &str) -> bool {
+    if !token.len().is_multiple_of(2) || !token.is_ascii() {
+        return false;
+    }
+    let bytes: Option<Vec<u8>> = (0..token.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&token[i..i + 2], 16).ok())
+        .collect();
+    bytes.is_some_and(|bytes| mac(secret, path, expires).verify_slice(&bytes).is_ok())
+}
+
+// 校验 ?token=...&expires=... 分享链接，有效时标记请求已授权，过期或被篡改时返回 403；
+// 需放在 BasicAuth 外层，未设置 --share-secret 时不处理
+#[derive(Clone)]
+pub struct ShareToken {
+    secret: Option<Rc<Vec<u8>>>,
+}
+
+impl ShareToken {
+    pub fn new(secret: Option<&str>) -> Self {
+        ShareToken {
+            secret: secret.map(|secret| Rc::new(secret.as_bytes().to_vec())),
+        }
+    }
+}
+
+impl<S> Middleware<S> for ShareToken {
+    type Service = ShareTokenMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ShareTokenMiddleware {
+            service,
+            secret: self.secret.clone(),
+        }
+    }
+}
+
+pub struct ShareTokenMiddleware<S> {
+    service: S,
+    secret: Option<Rc<Vec<u8>>>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ShareTokenMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let (secret, token) = match (&self.secret, query::get(req.query_string(), "token")) {
+            (Some(secret), Some(token)) if matches!(*req.method(), Method::GET | Method::HEAD) => (secret, token),
+            _ => return ctx.call(&self.service, req).await,
+        };
+
+        let expires = query::get(req.query_string(), "expires").and_then(|value| value.parse::<u64>().ok());
+        match expires {
+            Some(expires) if expires >= now() && verify(secret, req.path(), expires, &token) => {
+                req.extensions_mut().insert(Granted);
+                ctx.call(&self.service, req).await
+            }
+            _ => Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::ShareLinkInvalid)))),
+        }
+    }
+}
+
+// 已登录的用户对文件请求 ?share=秒数 时生成有效期为该秒数的分享链接，返回 JSON
+#[derive(Clone)]
+pub struct ShareLinks {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+    secret: Option<Vec<u8>>,
+}
+
+impl ShareLinks {
+    pub fn new(mounts: Vec<Mount>, secret: Option<&str>) -> Self {
+        ShareLinks {
+            inner: Rc::new(Inner {
+                mounts,
+                secret: secret.map(|secret| secret.as_bytes().to_vec()),
+            }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for ShareLinks {
+    type Service = ShareLinksMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ShareLinksMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct ShareLinksMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ShareLinksMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let requested = match query::get(req.query_string(), "share") {
+            Some(requested) if *req.method() == Method::GET => requested,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        let resolved = mount::find(&self.inner.mounts, req.path()).and_then(|mount| Some((mount, mount.resolve(req.path())?)));
+        let (mount, path) = match resolved {
+            Some(resolved) => resolved,
+            None => return ctx.call(&self.service, req).await,
+        };
+        // 通过分享链接访问的用户不能再生成新的链接
+        if req.extensions().get::<Granted>().is_some() {
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::ShareLinkReshare))));
+        }
+        let secret = match self.inner.secret {
+            Some(ref secret) => secret,
+            None => return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::ShareSecretMissing)))),
+        };
+        let ttl = match requested.parse::<u64>() {
+            Ok(ttl) if ttl > 0 => ttl,
+            _ => return Ok(req.into_response(HttpResponse::BadRequest().body(t(Msg::InvalidShareDuration)))),
+        };
+        if !path.is_file() {
+            return Ok(req.into_response(HttpResponse::BadRequest().body(t(Msg::ShareFileOnly))));
+        }
+
+        let expires = now().saturating_add(ttl);
+        let token = sign(secret, req.path(), expires);
+        let url = {
+            let info = req.connection_info();
+            format!("{}://{}{}?expires={}&token={}", info.scheme(), info.host(), mount.external(req.path()), expires, token)
+        };
+        let body = serde_json::json!({ "url": url, "expires": expires });
+        Ok(req.into_response(HttpResponse::Ok().json(&body)))
+    }
+}
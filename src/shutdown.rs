@@ -0,0 +1,31 @@
+use crate::i18n::{Msg, tf};
+
+// 等待 SIGINT(Ctrl-C) 或 SIGTERM
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use futures::future::{Either, select};
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                let ctrl_c = std::pin::pin!(tokio::signal::ctrl_c());
+                let term = std::pin::pin!(term.recv());
+                if let Either::Left((Err(e), _)) = select(ctrl_c, term).await {
+                    log::error!("{}", tf(Msg::ListenSignalFailed, &[&e]));
+                }
+            }
+            Err(e) => {
+                log::error!("{}", tf(Msg::ListenSigtermFailed, &[&e]));
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("{}", tf(Msg::ListenSignalFailed, &[&e]));
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use ntex::web::types::State;
+use ntex::web::{HttpRequest, HttpResponse};
+use ntex_files::NamedFile;
+
+use crate::i18n::{Msg, tf};
+
+// --file-dir 或 --mount 指向普通文件时，挂载点路径上只提供这一个文件
+pub struct SingleFile {
+    pub path: PathBuf,
+}
+
+// 由 ntex-files 处理类型、Range、ETag 和 Last-Modified；地址中没有文件名，保留默认的 Content-Disposition 让浏览器按原文件名保存
+pub async fn serve(file: State<SingleFile>, req: HttpRequest) -> HttpResponse {
+    match NamedFile::open(&file.path) {
+        Ok(named) => named.into_response(&req),
+        Err(e) => {
+            log::warn!("{}", tf(Msg::OpenSharedFileFailed, &[&file.path.display(), &e]));
+            HttpResponse::NotFound().finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::http::header::{CONTENT_RANGE, CONTENT_TYPE, RANGE};
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[ntex::test]
+    async fn serves_only_the_shared_file() {
+        let dir = TempDir::new();
+        dir.write("disk.txt", "0123456789");
+        let app = test::init_service(
+            web::App::new().service(
+                web::resource("/download/files")
+                    .state(SingleFile { path: dir.path().join("disk.txt") })
+                    .route(web::get().to(serve))
+                    .route(web::head().to(serve)),
+            ),
+        )
+        .await;
+
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap().starts_with("text/plain"));
+        assert_eq!(test::read_body(res).await, "0123456789");
+
+        let req = test::TestRequest::with_uri("/download/files").header(RANGE, "bytes=2-4").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers().get(CONTENT_RANGE).unwrap(), "bytes 2-4/10");
+        assert_eq!(test::read_body(res).await, "234");
+
+        let res = test::call_service(&app, test::TestRequest::with_uri("/download/files/other").to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::i18n::{Msg, tf};
+
+// 解析带单位的大小，如 5MB、512K、2G、10MiB、1048576；单位不区分大小写，均按 1024 进制，
+// 所有表示大小的参数(--rate-limit、--max-upload-size、--preview-max-size 等)都使用它
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let upper = value.to_ascii_uppercase();
+    let (number, unit) = match upper.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(pos) => upper.split_at(pos),
+        None => (upper.as_str(), ""),
+    };
+    let multiplier = match unit.trim() {
+        "" | "B" => 1u64,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(tf(Msg::UnknownSizeUnit, &[&value])),
+    };
+    let number: f64 = number.parse().map_err(|_| tf(Msg::InvalidSize, &[&value]))?;
+    let bytes = number * multiplier as f64;
+    if bytes >= u64::MAX as f64 {
+        return Err(tf(Msg::SizeOutOfRange, &[&value]));
+    }
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_suffixes() {
+        assert_eq!(parse_size("1048576"), Ok(1048576));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size("10m"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("2G"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("1.5KiB"), Ok(1536));
+        assert_eq!(parse_size("3 MiB"), Ok(3 * 1024 * 1024));
+        assert!(parse_size("10X").unwrap_err().contains("10X"));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::fs::Metadata;
+use std::io;
+use std::rc::Rc;
+
+use ntex::http::Method;
+use ntex::http::error::BlockingError;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use serde::Serialize;
+
+use crate::i18n::{Msg, tf};
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 单个文件或目录的元数据，时间为 RFC 3339 格式，平台不支持时为 null
+#[derive(Serialize)]
+struct FileStat {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<String>,
+    created: Option<String>,
+    permissions: Option<String>,
+}
+
+impl FileStat {
+    fn new(name: String, metadata: &Metadata) -> Self {
+        let format = |time: io::Result<std::time::SystemTime>| time.ok().map(|t| humantime::format_rfc3339_seconds(t).to_string());
+        FileStat {
+            name,
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: format(metadata.modified()),
+            created: format(metadata.created()),
+            permissions: permissions(metadata),
+        }
+    }
+}
+
+// Unix 上为八进制的权限位，如 644
+#[cfg(unix)]
+fn permissions(metadata: &Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(format!("{:o}", metadata.permissions().mode() & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn permissions(_metadata: &Metadata) -> Option<String> {
+    None
+}
+
+// GET {URL路径}/<文件>?stat=1 返回文件或目录的元数据而不下载内容，不存在时返回 404
+#[derive(Clone)]
+pub struct Stat {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+}
+
+impl Stat {
+    pub fn new(mounts: Vec<Mount>) -> Self {
+        Stat {
+            inner: Rc::new(Inner { mounts }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Stat {
+    type Service = StatMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        StatMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct StatMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for StatMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let wants_stat = matches!(*req.method(), Method::GET | Method::HEAD) && query::flag(req.query_string(), "stat");
+        let (path, mount) = match mount::find(&self.inner.mounts, req.path()).and_then(|mount| Some((mount.resolve(req.path())?, mount))) {
+            Some((path, mount)) if wants_stat => (path, mount),
+            _ => return ctx.call(&self.service, req).await,
+        };
+        // 挂载点根目录的名称为空
+        let name = match path.file_name() {
+            Some(name) if path != mount.dir => name.to_string_lossy().into_owned(),
+            _ => String::new(),
+        };
+
+        let file = path.clone();
+        let response = match web::block(move || std::fs::metadata(&file)).await {
+            Ok(metadata) => HttpResponse::Ok().json(&FileStat::new(name, &metadata)),
+            // 不存在时交给文件服务返回 404 页面
+            Err(BlockingError::Error(e)) if e.kind() == io::ErrorKind::NotFound => return ctx.call(&self.service, req).await,
+            Err(e) => {
+                log::error!("{}", tf(Msg::ReadMetadataFailed, &[&path.display(), &e]));
+                HttpResponse::InternalServerError().finish()
+            }
+        };
+        Ok(req.into_response(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::test;
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn returns_metadata_for_files_and_directories() {
+        let dir = TempDir::new();
+        dir.write("sub/a.txt", "hello");
+        let mount = dir.mount("/download/files");
+        let app = test::init_service(web::App::new().wrap(Stat::new(vec![mount.clone()])).service(testutil::files(&mount))).await;
+        let get = |uri: &str| test::TestRequest::with_uri(uri).to_request();
+
+        let body = test::read_response(&app, get("/download/files/sub/a.txt?stat=1")).await;
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["name"], "a.txt");
+        assert_eq!(json["size"], 5);
+        assert_eq!(json["is_dir"], false);
+        assert!(json["modified"].is_string());
+
+        let body = test::read_response(&app, get("/download/files/sub?stat=1")).await;
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["is_dir"], true);
+
+        let res = test::call_service(&app, get("/download/files/missing.txt?stat=1")).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
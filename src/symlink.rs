@@ -0,0 +1,66 @@
+use std::rc::Rc;
+
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{HttpResponse, WebRequest, WebResponse};
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+
+// 未启用 --follow-symlinks 时，拒绝经符号链接指向共享目录之外的请求。
+// 需在打包下载、删除、新建目录等中间件之外注册，所有按路径访问文件的功能都受它保护
+#[derive(Clone)]
+pub struct SymlinkGuard {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    mounts: Vec<Mount>,
+}
+
+impl SymlinkGuard {
+    pub fn new(mounts: Vec<Mount>) -> Self {
+        SymlinkGuard {
+            inner: Rc::new(Inner { mounts }),
+        }
+    }
+}
+
+impl<S> Middleware<S> for SymlinkGuard {
+    type Service = SymlinkGuardMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        SymlinkGuardMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct SymlinkGuardMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for SymlinkGuardMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let escapes = mount::find(&self.inner.mounts, req.path())
+            .filter(|mount| !mount.follow_symlinks)
+            .and_then(|mount| Some(mount.escapes(&mount.resolve(req.path())?)))
+            .unwrap_or(false);
+        if escapes {
+            log::warn!("{}", tf(Msg::SymlinkRejected, &[&req.path()]));
+            return Ok(req.into_response(HttpResponse::Forbidden().body(t(Msg::OutsideShare))));
+        }
+        ctx.call(&self.service, req).await
+    }
+}
@@ -0,0 +1,10 @@
+// 表头的全选框：勾选或取消时同步当前列表中所有条目的复选框
+(function () {
+    var all = document.getElementById("select-all");
+    if (!all) return;
+    all.addEventListener("change", function () {
+        document.querySelectorAll("tbody input[name=file]").forEach(function (box) {
+            box.checked = all.checked;
+        });
+    });
+})();
@@ -0,0 +1,51 @@
+// 订阅目录变化，当前目录中有文件删除时移除对应的行，新建或修改时重新获取列表
+(function () {
+    var wsPath = document.currentScript.dataset.ws;
+    var dir = decodeURIComponent(location.pathname.replace(/\/?$/, "/"));
+    var timer = null;
+
+    function refresh() {
+        clearTimeout(timer);
+        timer = setTimeout(function () {
+            fetch(location.href).then(function (res) {
+                return res.ok ? res.text() : null;
+            }).then(function (html) {
+                if (!html) return;
+                var doc = new DOMParser().parseFromString(html, "text/html");
+                ["tbody", "div.gallery", "nav.pagination"].forEach(function (selector) {
+                    var fresh = doc.querySelector(selector);
+                    var current = document.querySelector(selector);
+                    if (fresh && current) current.replaceWith(fresh);
+                });
+            });
+        }, 200);
+    }
+
+    function removeRow(path) {
+        document.querySelectorAll("tbody a").forEach(function (link) {
+            var target = decodeURIComponent(new URL(link.href).pathname).replace(/\/$/, "");
+            if (target === path) link.closest("tr").remove();
+        });
+    }
+
+    function connect() {
+        var scheme = location.protocol === "https:" ? "wss://" : "ws://";
+        var socket = new WebSocket(scheme + location.host + wsPath);
+        socket.onmessage = function (msg) {
+            var change = JSON.parse(msg.data);
+            var parent = change.path.slice(0, change.path.lastIndexOf("/") + 1);
+            if (parent !== dir) return;
+            if (change.type === "removed") {
+                removeRow(change.path);
+            } else {
+                refresh();
+            }
+        };
+        // 服务器重启或网络中断后稍等再重连
+        socket.onclose = function () {
+            setTimeout(connect, 5000);
+        };
+    }
+
+    connect();
+})();
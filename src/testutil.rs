@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ntex::web::{self, DefaultError};
+use ntex_files::{Directory, Files};
+
+use crate::dirsize::DirSizes;
+use crate::downloads::Downloads;
+use crate::listing;
+use crate::mount::Mount;
+
+// 测试用的临时目录，释放时连同其中的文件一起删除
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("fileshare-test-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        // 与启动时的共享目录一样使用规范化的绝对路径
+        TempDir(path.canonicalize().unwrap())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    // 写入相对路径下的文件，上级目录不存在时一并创建
+    pub fn write(&self, relative: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub fn mount(&self, url_path: &str) -> Mount {
+        Mount::new(url_path, self.0.to_str().unwrap())
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// 与 main 中注册的文件服务相同：使用自定义的目录列表，不加 Content-Disposition
+pub fn files(mount: &Mount) -> Files<DefaultError> {
+    let listing_mount = mount.clone();
+    Files::new(&mount.url_path, &mount.dir)
+        .show_files_listing()
+        .files_listing_renderer(move |dir: &Directory, req: &web::HttpRequest| listing::render_html(dir, req, &listing_mount, &DirSizes::new(), &Downloads::default()))
+        .disable_content_disposition()
+}
@@ -0,0 +1,222 @@
+use std::error::Error;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::time::{Millis, Sleep, sleep};
+use ntex::util::Bytes;
+use ntex::web::{WebRequest, WebResponse};
+
+use crate::reload::Live;
+
+// 所有工作线程共享的令牌桶，限制整个服务器的发送速率(--total-rate-limit)
+#[derive(Clone, Default)]
+pub struct TotalBucket {
+    inner: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Default)]
+struct Bucket {
+    tokens: f64,
+    last: Option<Instant>,
+    active: usize,
+}
+
+impl TotalBucket {
+    // 取走最多 wanted 字节的令牌，令牌不足时返回需要等待的时间
+    fn take(&self, wanted: usize, rate: u64) -> Result<usize, Duration> {
+        let mut bucket = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let elapsed = bucket.last.map_or(1.0, |last| now.duration_since(last).as_secs_f64());
+        bucket.tokens = (bucket.tokens + elapsed * rate as f64).min(rate as f64);
+        bucket.last = Some(now);
+
+        let granted = wanted.min(bucket.tokens as usize);
+        if granted == 0 {
+            return Err(Duration::from_secs_f64((wanted as f64 - bucket.tokens) / rate as f64));
+        }
+        bucket.tokens -= granted as f64;
+        Ok(granted)
+    }
+
+    // 正在发送的响应数
+    fn active(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).active
+    }
+
+    fn register(&self) {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).active += 1;
+    }
+
+    fn unregister(&self) {
+        let mut bucket = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.active = bucket.active.saturating_sub(1);
+    }
+}
+
+// 按令牌桶限制每个响应的发送速率，桶容量为一秒的流量；
+// 客户端读取慢时令牌会累积到上限为止，不会阻塞其他连接。
+// 设置了总速率时每个响应的速率不超过总速率按正在发送的响应数平分的一份，各响应按比例变慢而不是被一个响应占满，
+// 再从共享的令牌桶中取得令牌保证合计不超过总速率；rate 为 0 时不单独限制每个响应
+struct ThrottledBody {
+    body: ResponseBody<Body>,
+    rate: u64,
+    tokens: f64,
+    last: Instant,
+    total: Option<(TotalBucket, u64)>,
+    pending: Option<Bytes>,
+    sleep: Option<Sleep>,
+}
+
+impl ThrottledBody {
+    fn new(body: ResponseBody<Body>, rate: u64, total: Option<(TotalBucket, u64)>) -> Self {
+        if let Some((ref bucket, _)) = total {
+            bucket.register();
+        }
+        let mut body = ThrottledBody {
+            body,
+            rate,
+            tokens: 0.0,
+            last: Instant::now(),
+            total,
+            pending: None,
+            sleep: None,
+        };
+        body.tokens = body.effective_rate() as f64;
+        body
+    }
+
+    // 单个响应的速率与平分后的总速率中较小的一个
+    fn effective_rate(&self) -> u64 {
+        let share = self.total.as_ref().map(|(bucket, rate)| (rate / bucket.active().max(1) as u64).max(1));
+        match (self.rate, share) {
+            (0, Some(share)) => share,
+            (rate, Some(share)) => rate.min(share),
+            (rate, None) => rate,
+        }
+    }
+
+    fn refill(&mut self, rate: u64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate as f64).min(rate as f64);
+        self.last = now;
+    }
+}
+
+impl MessageBody for ThrottledBody {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        loop {
+            if let Some(ref sleep) = self.sleep {
+                ready!(sleep.poll_elapsed(cx));
+                self.sleep = None;
+            }
+
+            let mut chunk = match self.pending.take() {
+                Some(chunk) => chunk,
+                None => match ready!(self.body.poll_next_chunk(cx)) {
+                    Some(Ok(chunk)) if !chunk.is_empty() => chunk,
+                    other => return Poll::Ready(other),
+                },
+            };
+
+            let rate = self.effective_rate();
+            self.refill(rate);
+            let mut allowed = self.tokens as usize;
+            if allowed == 0 {
+                // 至少攒够十分之一秒的流量再发送，避免定时器过于频繁
+                let wanted = chunk.len().min((rate as usize / 10).max(1));
+                let wait = (wanted as f64 - self.tokens) / rate as f64;
+                self.pending = Some(chunk);
+                self.sleep = Some(sleep(Millis((wait * 1000.0).ceil() as u32)));
+                continue;
+            }
+            if let Some((ref bucket, total_rate)) = self.total {
+                match bucket.take(allowed.min(chunk.len()), total_rate) {
+                    Ok(granted) => allowed = granted,
+                    Err(wait) => {
+                        self.pending = Some(chunk);
+                        self.sleep = Some(sleep(Millis((wait.as_secs_f64() * 1000.0).ceil() as u32)));
+                        continue;
+                    }
+                }
+            }
+
+            if chunk.len() > allowed {
+                self.pending = Some(chunk.split_off(allowed));
+            }
+            self.tokens -= chunk.len() as f64;
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+    }
+}
+
+impl Drop for ThrottledBody {
+    fn drop(&mut self) {
+        if let Some((ref bucket, _)) = self.total {
+            bucket.unregister();
+        }
+    }
+}
+
+// 限制每个响应以及整个服务器的带宽，速率为 0 时不限速；速率可通过 SIGHUP 重新加载，只影响之后的请求
+#[derive(Clone)]
+pub struct Throttle {
+    live: Live,
+    total: TotalBucket,
+}
+
+impl Throttle {
+    pub fn new(live: Live, total: TotalBucket) -> Self {
+        Throttle { live, total }
+    }
+}
+
+impl<S> Middleware<S> for Throttle {
+    type Service = ThrottleMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ThrottleMiddleware {
+            service,
+            live: self.live.clone(),
+            total: self.total.clone(),
+        }
+    }
+}
+
+pub struct ThrottleMiddleware<S> {
+    service: S,
+    live: Live,
+    total: TotalBucket,
+}
+
+impl<S, E> Service<WebRequest<E>> for ThrottleMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let res = ctx.call(&self.service, req).await?;
+        let settings = self.live.load();
+        let rate = settings.rate_limit;
+        let total = (settings.total_rate_limit > 0).then(|| (self.total.clone(), settings.total_rate_limit));
+        if rate == 0 && total.is_none() {
+            return Ok(res);
+        }
+
+        Ok(res.map_body(move |_, body| ResponseBody::Other(Body::from_message(ThrottledBody::new(body, rate, total)))))
+    }
+}
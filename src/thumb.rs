@@ -0,0 +1,235 @@
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+use image::ImageFormat;
+use ntex::http::Method;
+use ntex::http::error::BlockingError;
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use ntex_files::NamedFile;
+use sha2::{Digest, Sha256};
+
+use crate::i18n::{Msg, t, tf};
+use crate::mount::{self, Mount};
+use crate::query;
+
+// 缩略图缓存目录，位于共享目录下，以 . 开头因此默认不会被列出
+const CACHE_DIR: &str = ".thumbs";
+
+// 允许请求的缩略图边长范围(像素)
+const MIN_SIZE: u32 = 16;
+const MAX_SIZE: u32 = 1024;
+
+// 支持生成缩略图的图片扩展名
+const EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "gif", "webp"];
+
+// 临时文件序号，区分同时生成同一缩略图的多个线程
+static TMP_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+// 是否为可以生成缩略图的图片
+pub fn is_image(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+// 缓存文件名由源文件的相对路径、边长和修改时间决定，源文件修改后自然失效；
+// JPEG 源文件生成 JPEG 缩略图，其他格式生成 PNG 以保留透明度
+fn cache_path(mount: &Mount, source: &Path, size: u32) -> io::Result<(PathBuf, String, ImageFormat)> {
+    let mtime = std::fs::metadata(source)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let relative = source.strip_prefix(&mount.dir).unwrap_or(source);
+    let key: String = Sha256::digest(relative.to_string_lossy().as_bytes())
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let prefix = format!("{}_{}_", key, size);
+    let is_jpeg = source
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "jpg" | "jpeg"));
+    let (format, ext) = if is_jpeg { (ImageFormat::Jpeg, "jpg") } else { (ImageFormat::Png, "png") };
+    let path = mount.dir.join(CACHE_DIR).join(format!("{}{}.{}", prefix, mtime, ext));
+    Ok((path, prefix, format))
+}
+
+enum Thumbnail {
+    Cached(PathBuf),
+    Rendered(Vec<u8>, ImageFormat),
+}
+
+// 返回缓存的缩略图路径，不存在时生成；同时删除同一文件此前生成的过期缩略图。
+// 只读模式下不写入共享目录，没有缓存时每次在内存中生成
+fn thumbnail(mount: &Mount, source: &Path, size: u32, read_only: bool) -> io::Result<Thumbnail> {
+    let (path, prefix, format) = cache_path(mount, source, size)?;
+    if path.is_file() {
+        return Ok(Thumbnail::Cached(path));
+    }
+
+    let image = image::open(source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let thumb = image.thumbnail(size, size);
+    if read_only {
+        let mut bytes = Cursor::new(Vec::new());
+        let result = match format {
+            ImageFormat::Jpeg => thumb.to_rgb8().write_to(&mut bytes, format),
+            _ => thumb.write_to(&mut bytes, format),
+        };
+        result.map_err(io::Error::other)?;
+        return Ok(Thumbnail::Rendered(bytes.into_inner(), format));
+    }
+    let dir = mount.dir.join(CACHE_DIR);
+    std::fs::create_dir_all(&dir)?;
+    for entry in std::fs::read_dir(&dir)?.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+
+    // 先写入临时文件再重命名，同时请求同一缩略图时不会读到写了一半的文件
+    let tmp = dir.join(format!(".{}{}.{}.tmp", prefix, std::process::id(), TMP_SEQ.fetch_add(1, Ordering::Relaxed)));
+    let result = match format {
+        ImageFormat::Jpeg => thumb.to_rgb8().save_with_format(&tmp, format),
+        _ => thumb.save_with_format(&tmp, format),
+    };
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(io::Error::other(e));
+    }
+    std::fs::rename(&tmp, &path)?;
+    Ok(Thumbnail::Cached(path))
+}
+
+// 启用 --thumbnails 时，图片请求带 ?thumb=200 返回边长不超过该值的缩略图，缓存在共享目录的 .thumbs 下；
+// --read-only 时不写入缓存
+#[derive(Clone)]
+pub struct Thumbnails {
+    mounts: Rc<Vec<Mount>>,
+    read_only: bool,
+}
+
+impl Thumbnails {
+    pub fn new(mounts: Vec<Mount>, read_only: bool) -> Self {
+        Thumbnails {
+            mounts: Rc::new(mounts),
+            read_only,
+        }
+    }
+}
+
+impl<S> Middleware<S> for Thumbnails {
+    type Service = ThumbnailsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ThumbnailsMiddleware {
+            service,
+            mounts: self.mounts.clone(),
+            read_only: self.read_only,
+        }
+    }
+}
+
+pub struct ThumbnailsMiddleware<S> {
+    service: S,
+    mounts: Rc<Vec<Mount>>,
+    read_only: bool,
+}
+
+impl<S, E> Service<WebRequest<E>> for ThumbnailsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        let requested = match query::get(req.query_string(), "thumb") {
+            Some(requested) if matches!(*req.method(), Method::GET | Method::HEAD) => requested,
+            _ => return ctx.call(&self.service, req).await,
+        };
+        let resolved = mount::find(&self.mounts, req.path()).and_then(|mount| Some((mount.clone(), mount.resolve(req.path())?)));
+        let (mount, source) = match resolved {
+            Some((mount, source)) if mount.thumbnails && source.is_file() => (mount, source),
+            _ => return ctx.call(&self.service, req).await,
+        };
+        let size = match requested.parse::<u32>() {
+            Ok(size) if (MIN_SIZE..=MAX_SIZE).contains(&size) => size,
+            _ => {
+                let body = tf(Msg::InvalidThumbSize, &[&MIN_SIZE, &MAX_SIZE]);
+                return Ok(req.into_response(HttpResponse::BadRequest().body(body)));
+            }
+        };
+        if !is_image(&source.to_string_lossy()) {
+            return Ok(req.into_response(HttpResponse::BadRequest().body(t(Msg::ThumbUnsupported))));
+        }
+
+        let file = source.clone();
+        let read_only = self.read_only;
+        let path = match web::block(move || thumbnail(&mount, &file, size, read_only)).await {
+            Ok(Thumbnail::Cached(path)) => path,
+            Ok(Thumbnail::Rendered(bytes, format)) => {
+                return Ok(req.into_response(HttpResponse::Ok().content_type(format.to_mime_type()).body(bytes)));
+            }
+            Err(BlockingError::Error(e)) if e.kind() == io::ErrorKind::InvalidData => {
+                log::warn!("{}", tf(Msg::DecodeImageFailed, &[&source.display(), &e]));
+                return Ok(req.into_response(HttpResponse::UnprocessableEntity().body(t(Msg::ImageUndecodable))));
+            }
+            Err(e) => {
+                log::error!("{}", tf(Msg::ThumbnailFailed, &[&source.display(), &e]));
+                return Ok(req.into_response(HttpResponse::InternalServerError().finish()));
+            }
+        };
+
+        let (req, _) = req.into_parts();
+        match NamedFile::open(&path) {
+            Ok(file) => {
+                let res = file.disable_content_disposition().into_response(&req);
+                Ok(WebResponse::new(res, req))
+            }
+            Err(e) => {
+                log::error!("{}", tf(Msg::ReadThumbnailFailed, &[&path.display(), &e]));
+                Ok(WebResponse::new(HttpResponse::InternalServerError().finish(), req))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::StatusCode;
+    use ntex::web::{self, test};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    #[ntex::test]
+    async fn read_only_does_not_write_the_cache() {
+        let dir = TempDir::new();
+        image::RgbImage::new(64, 32).save(dir.path().join("photo.png")).unwrap();
+        let mut mount = dir.mount("/download/files");
+        mount.thumbnails = true;
+        let app = test::init_service(
+            web::App::new()
+                .wrap(Thumbnails::new(vec![mount.clone()], true))
+                .service(testutil::files(&mount)),
+        )
+        .await;
+
+        let response = test::call_service(&app, test::TestRequest::with_uri("/download/files/photo.png?thumb=16").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+        let thumb = image::load_from_memory(&test::read_body(response).await).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (16, 8));
+        assert!(!dir.path().join(".thumbs").exists());
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use ntex::http::StatusCode;
+use ntex::http::header::{CONTENT_LENGTH, TRANSFER_ENCODING};
+use ntex::service::{Middleware, Service, ServiceCtx};
+use ntex::time::Seconds;
+use ntex::web::{DefaultError, WebRequest, WebResponse, WebResponseError};
+
+use crate::i18n::{Msg, t, tf};
+
+// 处理超时的错误，渲染为 408 响应
+#[derive(Debug)]
+pub struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(t(Msg::RequestTimedOut))
+    }
+}
+
+impl WebResponseError<DefaultError> for TimedOut {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::REQUEST_TIMEOUT
+    }
+}
+
+// 处理请求超过 --request-timeout 仍未返回响应时中止并返回 408；
+// 只限制生成响应头之前的时间，已经开始发送的下载不受影响
+#[derive(Clone)]
+pub struct RequestTimeout {
+    timeout: Seconds,
+}
+
+impl RequestTimeout {
+    // timeout 为 0 时不限制
+    pub fn new(timeout: Seconds) -> Self {
+        RequestTimeout { timeout }
+    }
+}
+
+impl<S> Middleware<S> for RequestTimeout {
+    type Service = RequestTimeoutMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RequestTimeoutMiddleware {
+            service,
+            timeout: self.timeout,
+        }
+    }
+}
+
+pub struct RequestTimeoutMiddleware<S> {
+    service: S,
+    timeout: Seconds,
+}
+
+impl<S, E> Service<WebRequest<E>> for RequestTimeoutMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    S::Error: From<TimedOut>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_poll!(service);
+    ntex::forward_ready!(service);
+    ntex::forward_shutdown!(service);
+
+    async fn call(&self, req: WebRequest<E>, ctx: ServiceCtx<'_, Self>) -> Result<Self::Response, Self::Error> {
+        // 带请求体的上传在读完请求体后才返回响应，耗时取决于文件大小，不做限制
+        let has_body = req.headers().contains_key(TRANSFER_ENCODING)
+            || req
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value != "0");
+        if self.timeout.is_zero() || has_body {
+            return ctx.call(&self.service, req).await;
+        }
+
+        // 请求已交给内层处理，超时后无法再用它构造响应，以错误的形式返回 408
+        let path = req.path().to_string();
+        match ntex::time::timeout(self.timeout, ctx.call(&self.service, req)).await {
+            Ok(res) => res,
+            Err(()) => {
+                log::warn!("{}", tf(Msg::RequestAborted, &[&path, &self.timeout.seconds()]));
+                Err(TimedOut.into())
+            }
+        }
+    }
+}
@@ -0,0 +1,456 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::fmt;
+use std::future::poll_fn;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::task::{Poll, ready};
+
+use ntex::http::body::MessageBody;
+use ntex::http::{HttpService, KeepAlive, Request, Response, ResponseError, ServiceConfig};
+use ntex::io::types::HttpProtocol;
+use ntex::io::{Filter, FilterLayer, Io, IoRef, Layer, ReadBuf, WriteBuf};
+use ntex::server::{Server, SslError};
+use ntex::service::{IntoServiceFactory, ServiceFactory, chain_factory, fn_service, map_config};
+use ntex::time::Seconds;
+use ntex::tls::rustls::PeerCert;
+use ntex::web::dev::AppConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{ProtocolVersion, RootCertStore, ServerConfig, ServerConnection, SupportedProtocolVersion};
+
+use crate::i18n::{Msg, t, tf};
+
+pub const TLS_VERSIONS: [&str; 2] = ["1.2", "1.3"];
+
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+// --tls-min-version 对应的协议版本，低于该版本的客户端在握手阶段被拒绝
+fn protocol_versions(min_version: &str) -> &'static [&'static SupportedProtocolVersion] {
+    match min_version {
+        "1.3" => TLS13_ONLY,
+        _ => rustls::DEFAULT_VERSIONS,
+    }
+}
+
+// 加载证书链和私钥，构建 rustls 服务端配置；指定 client_ca 时要求客户端出示由该CA签发的证书
+// 在启动阶段完成解析和校验，避免绑定端口后才在首个请求时报错
+pub fn load_rustls_config(cert_path: &str, key_path: &str, client_ca: Option<&str>, min_version: &str) -> Result<ServerConfig, String> {
+    let certs = read_certs(cert_path).map_err(|e| tf(Msg::ReadTlsCertFailed, &[&cert_path, &e]))?;
+    if certs.is_empty() {
+        return Err(tf(Msg::NoTlsCert, &[&cert_path]));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| tf(Msg::ReadTlsKeyFailed, &[&key_path, &e]))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(protocol_versions(min_version))
+        .map_err(|e| tf(Msg::TlsKeyMismatch, &[&e]))?;
+    let builder = match client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            let cas = read_certs(path).map_err(|e| tf(Msg::ReadClientCaFailed, &[&path, &e]))?;
+            let (added, _) = roots.add_parsable_certificates(cas);
+            if added == 0 {
+                return Err(tf(Msg::NoClientCa, &[&path]));
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|e| tf(Msg::InvalidClientCa, &[&path, &e]))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| tf(Msg::TlsKeyMismatch, &[&e]))?;
+    Ok(config)
+}
+
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, rustls::pki_types::pem::Error> {
+    CertificateDer::pem_file_iter(path).and_then(|iter| iter.collect())
+}
+
+// HTTPS 监听所需的服务器设置，与 main 中 HttpServer 的设置相同
+#[derive(Clone)]
+pub struct ServeOptions {
+    pub workers: usize,
+    pub maxconn: usize,
+    pub shutdown_timeout: Seconds,
+    pub request_timeout: Seconds,
+    pub keep_alive: KeepAlive,
+    pub http2: bool,
+}
+
+// HTTPS 不使用 ntex 的 HttpServer::listen_rustls：它总是通过 ALPN 提供 h2，也无法在握手后取得 rustls 连接。
+// 这里用自己的 TLS 过滤层构建服务，指定 --http2 时 ALPN 提供 h2 和 http/1.1，否则只声明 http/1.1；
+// 超时等设置与 HttpServer 的默认值保持一致
+pub fn serve<F, I, S, B>(factory: F, listener: TcpListener, mut config: ServerConfig, options: ServeOptions) -> io::Result<Server>
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S, Request, AppConfig>,
+    S: ServiceFactory<Request, AppConfig> + 'static,
+    S::Error: ResponseError,
+    S::InitError: fmt::Debug,
+    S::Response: Into<Response<B>>,
+    B: MessageBody + 'static,
+{
+    config.alpn_protocols = if options.http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    let config = Arc::new(config);
+    let addr = listener.local_addr()?;
+    let server = ntex::server::build()
+        .workers(options.workers)
+        .maxconn(options.maxconn)
+        .disable_signals()
+        .shutdown_timeout(options.shutdown_timeout);
+    let server = if options.http2 {
+        server.listen("fileshare-https", listener, move |_| {
+            let app_config = AppConfig::new(true, addr, addr.to_string());
+            let service = HttpService::build_with_config(service_config(&options)).finish(map_config(factory(), move |_| app_config.clone()));
+            chain_factory(acceptor(config.clone()))
+                .map_err(|e| SslError::Ssl(Box::new(e)))
+                .and_then(chain_factory(service).map_err(SslError::Service))
+        })?
+    } else {
+        server.listen("fileshare-https", listener, move |_| {
+            let app_config = AppConfig::new(true, addr, addr.to_string());
+            let service = HttpService::build_with_config(service_config(&options)).h1(map_config(factory(), move |_| app_config.clone()));
+            chain_factory(acceptor(config.clone()))
+                .map_err(|e| SslError::Ssl(Box::new(e)))
+                .and_then(chain_factory(service).map_err(SslError::Service))
+        })?
+    };
+    Ok(server.run())
+}
+
+fn service_config(options: &ServeOptions) -> ServiceConfig {
+    let mut service_config = ServiceConfig::default();
+    service_config.keepalive(options.keep_alive);
+    service_config.disconnect_timeout(Seconds(1));
+    if options.request_timeout.is_zero() {
+        service_config.headers_read_rate(Seconds(1), Seconds(13), 256);
+    } else {
+        service_config.headers_read_rate(options.request_timeout, options.request_timeout, u16::MAX);
+    }
+    service_config
+}
+
+// 接受连接时完成 TLS 握手，超过5秒未完成时断开；握手完成后在 debug 级别记录协商的协议版本
+fn acceptor<F: Filter, C>(
+    config: Arc<ServerConfig>,
+) -> impl ServiceFactory<Io<F>, C, Response = Io<Layer<TlsFilter, F>>, Error = io::Error, InitError = ()> {
+    fn_service(move |io: Io<F>| {
+        let config = config.clone();
+        async move {
+            let session = ServerConnection::new(config).map_err(io::Error::other)?;
+            let io = io.add_filter(TlsFilter {
+                session: RefCell::new(session),
+            });
+            ntex::time::timeout(Seconds(5), handshake(&io))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, t(Msg::TlsHandshakeTimeout)))??;
+            match io.filter().session.borrow().protocol_version() {
+                Some(ProtocolVersion::TLSv1_3) => log::debug!("{}", tf(Msg::TlsHandshakeVersion, &[&"TLSv1.3"])),
+                Some(ProtocolVersion::TLSv1_2) => log::debug!("{}", tf(Msg::TlsHandshakeVersion, &[&"TLSv1.2"])),
+                version => log::debug!("{}", tf(Msg::TlsHandshakeVersion, &[&format!("{:?}", version)])),
+            }
+            Ok(io)
+        }
+    })
+}
+
+// 基于 rustls 的 TLS 过滤层，处理方式与 ntex-tls 的 TlsServerFilter 相同，
+// 只是保留了对 rustls 连接的访问，以便握手后读取协议版本
+#[derive(Debug)]
+struct TlsFilter {
+    session: RefCell<ServerConnection>,
+}
+
+impl FilterLayer for TlsFilter {
+    // 与 ntex-tls 一样提供 ALPN 协商的协议和客户端证书，HttpService 据此选择 HTTP/2，访问日志据此记录证书 CN
+    fn query(&self, id: TypeId) -> Option<Box<dyn Any>> {
+        let session = self.session.borrow();
+        if id == TypeId::of::<HttpProtocol>() {
+            let protocol = if session.alpn_protocol() == Some(b"h2") {
+                HttpProtocol::Http2
+            } else {
+                HttpProtocol::Http1
+            };
+            Some(Box::new(protocol))
+        } else if id == TypeId::of::<PeerCert<'static>>() {
+            let cert = session.peer_certificates()?.first()?;
+            Some(Box::new(PeerCert(cert.clone().into_owned())))
+        } else {
+            None
+        }
+    }
+
+    // 把收到的密文交给 rustls，解密出的明文写入上层的读缓冲区，返回新增的字节数
+    fn process_read_buf(&self, buf: &ReadBuf<'_>) -> io::Result<usize> {
+        let mut session = self.session.borrow_mut();
+        buf.with_src(|src| {
+            let Some(src) = src else {
+                return Ok(0);
+            };
+            buf.with_dst(|dst| {
+                let mut new_bytes = 0;
+                loop {
+                    let n = match session.read_tls(&mut io::Cursor::new(&src[..])) {
+                        Ok(n) => n,
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(e) => return Err(e),
+                    };
+                    src.split_to(n);
+                    let state = session.process_new_packets().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    let pending = state.plaintext_bytes_to_read();
+                    if pending > 0 {
+                        let mut plaintext = vec![0; pending];
+                        session.reader().read_exact(&mut plaintext)?;
+                        dst.extend_from_slice(&plaintext);
+                        new_bytes += pending;
+                    } else if src.is_empty() {
+                        break;
+                    }
+                }
+                Ok(new_bytes)
+            })
+        })
+    }
+
+    // 把上层写入的明文交给 rustls 加密，密文写入下层的写缓冲区
+    fn process_write_buf(&self, buf: &WriteBuf<'_>) -> io::Result<()> {
+        let mut session = self.session.borrow_mut();
+        buf.with_src(|src| {
+            let Some(src) = src else {
+                return Ok(());
+            };
+            let mut io = Wrapper(buf);
+            while !src.is_empty() {
+                let n = session.writer().write(src)?;
+                src.split_to(n);
+                while session.wants_write() {
+                    session.write_tls(&mut io)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+// 与 ntex-tls 相同的握手过程：有数据时交给 rustls 处理，把要发送的数据写出，直到握手完成
+async fn handshake<F: Filter>(io: &Io<Layer<TlsFilter, F>>) -> io::Result<()> {
+    loop {
+        let handshaking = io.with_buf(|buf| {
+            let mut session = io.filter().session.borrow_mut();
+            let mut wrapper = Wrapper(buf);
+            while session.wants_read() {
+                let has_data = buf.with_read_buf(|read_buf| read_buf.with_src(|src| src.as_ref().is_some_and(|src| !src.is_empty())));
+                if !has_data {
+                    break;
+                }
+                if session.read_tls(&mut wrapper)? == 0 {
+                    return Err(io::Error::new(io::ErrorKind::NotConnected, "disconnected"));
+                }
+                session.process_new_packets().map_err(|e| {
+                    // 尽量把描述错误的警报发给客户端，再返回握手失败的原因
+                    let _ = session.write_tls(&mut wrapper);
+                    io::Error::new(io::ErrorKind::InvalidData, e)
+                })?;
+            }
+            while session.wants_write() {
+                session.write_tls(&mut wrapper)?;
+            }
+            Ok(session.is_handshaking())
+        })??;
+        if !handshaking {
+            return Ok(());
+        }
+        poll_fn(|cx| {
+            Poll::Ready(match ready!(io.poll_read_notify(cx))? {
+                Some(()) => Ok(()),
+                None => Err(io::Error::new(io::ErrorKind::NotConnected, "disconnected")),
+            })
+        })
+        .await?;
+    }
+}
+
+// 让 rustls 从下层的读缓冲区读取密文，并把密文写入下层的写缓冲区
+struct Wrapper<'a, 'b>(&'a WriteBuf<'b>);
+
+impl Read for Wrapper<'_, '_> {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        self.0.with_read_buf(|buf| {
+            buf.with_src(|src| match src {
+                Some(src) if !src.is_empty() => {
+                    let len = src.len().min(dst.len());
+                    dst[..len].copy_from_slice(&src.split_to(len));
+                    Ok(len)
+                }
+                _ => Err(io::Error::new(io::ErrorKind::WouldBlock, "")),
+            })
+        })
+    }
+}
+
+impl Write for Wrapper<'_, '_> {
+    fn write(&mut self, src: &[u8]) -> io::Result<usize> {
+        self.0.with_dst(|dst| dst.extend_from_slice(src));
+        Ok(src.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// 已验证的客户端证书中主题的 CN，用于访问日志；未使用客户端证书时返回 None
+pub fn client_common_name(io: Option<&IoRef>) -> Option<String> {
+    let cert = io?.query::<PeerCert<'static>>();
+    subject_common_name(cert.as_ref()?.0.as_ref())
+}
+
+// 读取一个 DER 编码的 TLV，返回标签、内容和剩余部分
+fn read_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n].iter().fold(0usize, |len, &b| (len << 8) | b as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+// 从 X.509 证书中取出主题的 commonName(2.5.4.3)，只需要这一项，不引入完整的证书解析库
+fn subject_common_name(der: &[u8]) -> Option<String> {
+    const SEQUENCE: u8 = 0x30;
+    const SET: u8 = 0x31;
+    const OID_CN: [u8; 3] = [0x55, 0x04, 0x03];
+
+    let (_, cert, _) = read_tlv(der).filter(|(tag, _, _)| *tag == SEQUENCE)?;
+    let (_, mut tbs, _) = read_tlv(cert).filter(|(tag, _, _)| *tag == SEQUENCE)?;
+    // 跳过可选的版本号 [0]，再跳过序列号、签名算法、颁发者和有效期
+    if tbs.first() == Some(&0xa0) {
+        tbs = read_tlv(tbs)?.2;
+    }
+    for _ in 0..4 {
+        tbs = read_tlv(tbs)?.2;
+    }
+    let (_, mut subject, _) = read_tlv(tbs).filter(|(tag, _, _)| *tag == SEQUENCE)?;
+    while !subject.is_empty() {
+        let (tag, mut set, rest) = read_tlv(subject)?;
+        subject = rest;
+        if tag != SET {
+            continue;
+        }
+        while !set.is_empty() {
+            let (_, attribute, rest) = read_tlv(set)?;
+            set = rest;
+            let (oid_tag, oid, value) = read_tlv(attribute)?;
+            if oid_tag == 0x06 && oid == OID_CN {
+                let (_, value, _) = read_tlv(value)?;
+                return Some(String::from_utf8_lossy(value).into_owned());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::mpsc;
+
+    use ntex::http::Version;
+    use ntex::http::client::{Client, Connector};
+    use ntex::rt::System;
+    use ntex::web;
+
+    use super::*;
+    use crate::mount::Mount;
+    use crate::testutil::{self, TempDir};
+
+    const CA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/testdata/ca.pem");
+    const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/testdata/localhost.pem");
+    const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/testdata/localhost.key");
+
+    // 与 main 相同用 serve 监听，在独立线程的运行时中运行，返回端口
+    fn start(dir: &Path, http2: bool) -> (System, u16) {
+        let dir = dir.to_str().unwrap().to_string();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            System::new("tls-test").run(move || {
+                let config = load_rustls_config(CERT, KEY, None, "1.2").unwrap();
+                let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+                let port = listener.local_addr().unwrap().port();
+                let factory = move || web::App::new().service(testutil::files(&Mount::new("/download/files", &dir)));
+                let options = ServeOptions {
+                    workers: 1,
+                    maxconn: 25_000,
+                    shutdown_timeout: Seconds(1),
+                    request_timeout: Seconds::ZERO,
+                    keep_alive: KeepAlive::Timeout(Seconds(5)),
+                    http2,
+                };
+                serve(factory, listener, config, options)?;
+                tx.send((System::current(), port)).unwrap();
+                Ok(())
+            })
+        });
+        rx.recv().unwrap()
+    }
+
+    // 客户端通过 ALPN 同时提供 h2 和 http/1.1，由服务端决定使用哪个
+    fn client() -> Client {
+        let mut roots = RootCertStore::empty();
+        roots.add_parsable_certificates(read_certs(CA).unwrap());
+        let mut config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Client::build().connector(Connector::default().rustls(config).finish()).finish()
+    }
+
+    async fn download(port: u16) -> (Version, Vec<u8>) {
+        let mut response = client()
+            .get(format!("https://localhost:{}/download/files/a.txt", port))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        (response.version(), response.body().await.unwrap().to_vec())
+    }
+
+    #[ntex::test]
+    async fn negotiates_h2_only_with_http2() {
+        let dir = TempDir::new();
+        dir.write("a.txt", "hello");
+
+        let (system, port) = start(dir.path(), true);
+        assert_eq!(download(port).await, (Version::HTTP_2, b"hello".to_vec()));
+        system.stop();
+
+        let (system, port) = start(dir.path(), false);
+        assert_eq!(download(port).await, (Version::HTTP_11, b"hello".to_vec()));
+        system.stop();
+    }
+}
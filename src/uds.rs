@@ -0,0 +1,37 @@
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::path::Path;
+
+use crate::i18n::{Msg, tf};
+
+// 解析八进制的套接字权限，如 660 或 0o660
+pub fn parse_mode(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| tf(Msg::InvalidSocketMode, &[&value]))
+}
+
+// 绑定 Unix 套接字，mode 不为空时设置套接字文件的权限；标准库无法指定 backlog，用 socket2 创建
+#[cfg(unix)]
+pub fn bind(path: &Path, mode: Option<u32>, backlog: i32) -> io::Result<UnixListener> {
+    // 上次异常退出残留的套接字文件会导致绑定失败，先删除；同名的普通文件保持原样
+    if let Ok(metadata) = std::fs::symlink_metadata(path)
+        && metadata.file_type().is_socket()
+    {
+        std::fs::remove_file(path)?;
+    }
+    let socket = socket2::Socket::new(socket2::Domain::UNIX, socket2::Type::STREAM, None)?;
+    socket.bind(&socket2::SockAddr::unix(path)?)?;
+    socket.listen(backlog)?;
+    let listener: UnixListener = socket.into();
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(listener)
+}
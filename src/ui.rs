@@ -0,0 +1,98 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use ntex::http::header::CACHE_CONTROL;
+use ntex::util::Bytes;
+use ntex::web::HttpResponse;
+use ntex::web::types::Path as UrlPath;
+
+use crate::i18n::{Msg, tf};
+
+// 列表页引用的静态资源路径前缀，不受挂载点和认证影响
+pub const PREFIX: &str = "/_fileshare";
+
+// 编译进程序的列表页模板、样式和脚本，--ui-dir 中的同名文件优先
+const LISTING_HTML: &str = include_str!("templates/listing.html");
+const LISTING_CSS: &str = include_str!("templates/listing.css");
+const WATCH_JS: &str = include_str!("templates/watch.js");
+const SELECT_JS: &str = include_str!("templates/select.js");
+
+// 资源地址带有内容摘要，内容变化后地址随之变化，因此可以长期缓存
+const CACHE_FOREVER: &str = "public, max-age=31536000, immutable";
+
+// 启动时加载的列表页资源
+pub struct Ui {
+    pub listing_html: String,
+    css: Bytes,
+    watch_js: Bytes,
+    select_js: Bytes,
+    pub css_url: String,
+    pub watch_js_url: String,
+    pub select_js_url: String,
+}
+
+static CURRENT: OnceLock<Ui> = OnceLock::new();
+
+// 读取 --ui-dir 中的 listing.html、listing.css、watch.js、select.js，不存在的文件使用内置版本
+pub fn load(dir: Option<&str>) -> Result<Ui, String> {
+    let read = |name: &str, embedded: &'static str| -> Result<String, String> {
+        let path = match dir {
+            Some(dir) => Path::new(dir).join(name),
+            None => return Ok(embedded.to_string()),
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(embedded.to_string()),
+            Err(e) => Err(tf(Msg::ReadUiFileFailed, &[&path.display(), &e])),
+        }
+    };
+    if let Some(dir) = dir
+        && !Path::new(dir).is_dir()
+    {
+        return Err(tf(Msg::InvalidUiDir, &[&dir]));
+    }
+    let css = read("listing.css", LISTING_CSS)?;
+    let watch_js = read("watch.js", WATCH_JS)?;
+    let select_js = read("select.js", SELECT_JS)?;
+    Ok(Ui {
+        listing_html: read("listing.html", LISTING_HTML)?,
+        css_url: url("listing.css", &css),
+        watch_js_url: url("watch.js", &watch_js),
+        select_js_url: url("select.js", &select_js),
+        css: Bytes::from(css),
+        watch_js: Bytes::from(watch_js),
+        select_js: Bytes::from(select_js),
+    })
+}
+
+fn url(name: &str, content: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("{}/{}?v={:016x}", PREFIX, name, hasher.finish())
+}
+
+// 启动时设置一次，之后所有线程共用
+pub fn init(ui: Ui) {
+    let _ = CURRENT.set(ui);
+}
+
+// 未调用 init 时(如测试中)使用内置资源
+pub fn current() -> &'static Ui {
+    CURRENT.get_or_init(|| load(None).expect("内置资源总是可用"))
+}
+
+// GET /_fileshare/<name>
+pub async fn asset(name: UrlPath<String>) -> HttpResponse {
+    let ui = current();
+    let (content_type, content) = match name.as_str() {
+        "listing.css" => ("text/css; charset=utf-8", ui.css.clone()),
+        "watch.js" => ("text/javascript; charset=utf-8", ui.watch_js.clone()),
+        "select.js" => ("text/javascript; charset=utf-8", ui.select_js.clone()),
+        _ => return HttpResponse::NotFound().finish(),
+    };
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .header(CACHE_CONTROL, CACHE_FOREVER)
+        .body(content)
+}
@@ -0,0 +1,677 @@
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::StreamExt;
+use ntex::http::RequestHead;
+use ntex::http::error::BlockingError;
+use ntex::http::header::{CONTENT_DISPOSITION, CONTENT_LENGTH};
+use ntex::web::types::{Payload, Query, State};
+use ntex::web::{self, HttpRequest, HttpResponse};
+use ntex_multipart::{Field, Multipart};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::disk;
+use crate::i18n::{Msg, t, tf};
+use crate::mount::Mount;
+use crate::query;
+
+// 上传页面模板
+const TEMPLATE: &str = include_str!("templates/upload.html");
+
+// 上传目标目录，每个挂载点一份
+pub struct UploadTarget {
+    pub mount: Mount,
+    pub enabled: bool,
+    pub read_only: bool,
+    pub chunks: ChunkLocks,
+    // 单次上传的大小上限(--max-upload-size)，0 表示不限制
+    pub max_size: u64,
+    // 磁盘可用空间低于该值(--min-free-space)时拒绝上传
+    pub min_free: u64,
+}
+
+impl UploadTarget {
+    fn limit(&self) -> u64 {
+        if self.max_size == 0 { u64::MAX } else { self.max_size }
+    }
+
+    // 按请求声明的大小提前拒绝：超过上限返回 413；写入后剩余空间会低于 --min-free-space 时返回 507，
+    // 不必等到写入一半才失败。未声明大小时只检查当前的剩余空间
+    fn reject(&self, total: Option<u64>) -> Option<HttpResponse> {
+        let total = total.unwrap_or(0);
+        if total > self.limit() {
+            return Some(too_large(self.max_size));
+        }
+        match disk::available(&self.mount.dir) {
+            Some(available) if available < self.min_free.saturating_add(total) => Some(insufficient_storage()),
+            _ => None,
+        }
+    }
+
+    // 与列表相同，隐藏文件和被通配符、忽略文件、--deny-ext 过滤掉的文件名不允许上传，
+    // 避免替换 .fileshareignore 或放入列表中看不到的文件
+    fn reject_name(&self, name: &str, path: &Path) -> Option<HttpResponse> {
+        if self.mount.is_visible(name) && self.mount.allows(path, false) {
+            return None;
+        }
+        log::warn!("{}", tf(Msg::UploadNameRejected, &[&path.display()]));
+        Some(HttpResponse::Forbidden().body(tf(Msg::UploadNotAllowed, &[&name])))
+    }
+}
+
+fn too_large(max_size: u64) -> HttpResponse {
+    HttpResponse::PayloadTooLarge().body(tf(Msg::UploadTooLarge, &[&max_size]))
+}
+
+fn insufficient_storage() -> HttpResponse {
+    HttpResponse::InsufficientStorage().body(t(Msg::DiskFull))
+}
+
+// 保存失败时的响应，已写入的临时文件由调用方删除
+fn save_failed(name: &str, path: &Path, max_size: u64, e: io::Error) -> HttpResponse {
+    match e.kind() {
+        ErrorKind::FileTooLarge => too_large(max_size),
+        ErrorKind::StorageFull => {
+            log::error!("{}", tf(Msg::SaveUploadError, &[&path.display(), &t(Msg::DiskFull)]));
+            insufficient_storage()
+        }
+        _ => {
+            log::error!("{}", tf(Msg::SaveUploadError, &[&path.display(), &e]));
+            HttpResponse::InternalServerError().body(tf(Msg::SaveFailed, &[&name]))
+        }
+    }
+}
+
+// 请求头中声明的请求体大小
+fn content_length(req: &HttpRequest) -> Option<u64> {
+    req.headers().get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+// 在线程池中写入，保留IO错误的类型以便区分磁盘已满
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> io::Result<T> + Send + Sync + 'static) -> io::Result<T> {
+    match web::block(f).await {
+        Ok(value) => Ok(value),
+        Err(BlockingError::Error(e)) => Err(e),
+        Err(BlockingError::Canceled) => Err(io::Error::other(t(Msg::WriteInterrupted))),
+    }
+}
+
+// 正在写入分块的临时文件，所有工作线程共享，同一文件的分块请求不能同时进行
+#[derive(Clone, Default)]
+pub struct ChunkLocks {
+    busy: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl ChunkLocks {
+    pub fn new() -> Self {
+        ChunkLocks::default()
+    }
+
+    // 占用临时文件，已被其他请求占用时返回 None，返回值释放时解除占用
+    fn lock(&self, path: &Path) -> Option<ChunkLock> {
+        let mut busy = self.busy.lock().unwrap_or_else(|e| e.into_inner());
+        if !busy.insert(path.to_path_buf()) {
+            return None;
+        }
+        Some(ChunkLock {
+            locks: self.clone(),
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+struct ChunkLock {
+    locks: ChunkLocks,
+    path: PathBuf,
+}
+
+impl Drop for ChunkLock {
+    fn drop(&mut self) {
+        self.locks.busy.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.path);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    #[serde(default)]
+    overwrite: bool,
+}
+
+#[derive(Serialize)]
+struct SavedFile {
+    name: String,
+    size: u64,
+}
+
+// 处理 multipart/form-data 上传，每个文件字段保存到目标目录；一次请求中所有文件合计不超过 --max-upload-size
+pub async fn upload(req: HttpRequest, target: State<UploadTarget>, query: Query<UploadQuery>, mut payload: Multipart) -> HttpResponse {
+    if !target.enabled {
+        return HttpResponse::Forbidden().body(t(Msg::UploadDisabled));
+    }
+    if let Some(res) = target.reject(content_length(&req)) {
+        return res;
+    }
+
+    let mut saved = Vec::new();
+    let mut total = 0u64;
+    while let Some(field) = payload.next().await {
+        let mut field = match field {
+            Ok(field) => field,
+            Err(e) => return HttpResponse::BadRequest().body(tf(Msg::ParseUploadFailed, &[&e])),
+        };
+
+        // 没有文件名的普通表单字段直接跳过
+        let name = match field_filename(&field) {
+            Some(name) => name,
+            None => continue,
+        };
+        let name = match sanitize_filename(&name) {
+            Some(name) => name,
+            None => return HttpResponse::BadRequest().body(tf(Msg::InvalidFileName, &[&name])),
+        };
+
+        let path = target.mount.dir.join(&name);
+        if let Some(res) = target.reject_name(&name, &path) {
+            return res;
+        }
+        if path.exists() && !query.overwrite {
+            return HttpResponse::Conflict().body(tf(Msg::FileExists, &[&name]));
+        }
+
+        // 同名文件同时只允许一个请求上传
+        let _lock = match target.chunks.lock(&path) {
+            Some(lock) => lock,
+            None => return HttpResponse::Conflict().body(tf(Msg::UploadInProgress, &[&name])),
+        };
+        let temp = TempUpload::new(&target.mount.dir);
+        let result = match save_field(&temp.path, &mut field, target.limit() - total).await {
+            Ok(size) => temp.persist(&path, query.overwrite).map(|_| size),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(size) => {
+                log::info!("{}", tf(Msg::UploadSaved, &[&path.display(), &size]));
+                total += size;
+                saved.push(SavedFile { name, size });
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                return HttpResponse::Conflict().body(tf(Msg::FileExists, &[&name]));
+            }
+            Err(e) => return save_failed(&name, &path, target.max_size, e),
+        }
+    }
+
+    HttpResponse::Ok().json(&serde_json::json!({ "files": saved }))
+}
+
+// 带 ?name= 的上传请求按分块协议处理，请求体为文件的原始数据而不是 multipart
+pub fn is_chunk(head: &RequestHead) -> bool {
+    head.uri.query().is_some_and(|query| query::get(query, "name").is_some())
+}
+
+// 分块上传的临时文件名取文件名的哈希，续传时能找到同一个文件，长度又与文件名无关，
+// 文件名接近系统上限时同样能保存
+fn part_name(name: &str) -> String {
+    let key: String = Sha256::digest(name.as_bytes()).iter().take(16).map(|byte| format!("{:02x}", byte)).collect();
+    format!(".upload-{}.part", key)
+}
+
+// 已写入的字节数，客户端中断后据此从该位置继续上传
+fn committed(offset: u64) -> HttpResponse {
+    HttpResponse::Ok().json(&serde_json::json!({ "offset": offset }))
+}
+
+fn offset_conflict(offset: u64) -> HttpResponse {
+    HttpResponse::Conflict().json(&serde_json::json!({ "error": t(Msg::OffsetMismatch), "offset": offset }))
+}
+
+// 分块上传：POST upload?name=big.iso&offset=N 将请求体追加到隐藏的临时文件 .upload-<文件名哈希>.part，
+// offset 必须等于已上传的大小；全部上传后 POST upload?name=big.iso&finalize=1&size=总大小
+// 校验大小并改名为正式文件。同一文件的并发请求返回 409，响应中的 offset 为已上传的大小
+pub async fn upload_chunk(req: HttpRequest, target: State<UploadTarget>, mut payload: Payload) -> HttpResponse {
+    if !target.enabled {
+        return HttpResponse::Forbidden().body(t(Msg::UploadDisabled));
+    }
+    let query = req.query_string();
+    let raw_name = query::get(query, "name").unwrap_or_default();
+    let name = match sanitize_filename(&raw_name) {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().body(tf(Msg::InvalidFileName, &[&raw_name])),
+    };
+    let overwrite = query::flag(query, "overwrite");
+    let path = target.mount.dir.join(&name);
+    if let Some(res) = target.reject_name(&name, &path) {
+        return res;
+    }
+    let part = target.mount.dir.join(part_name(&name));
+    let _lock = match target.chunks.lock(&part) {
+        Some(lock) => lock,
+        None => return HttpResponse::Conflict().body(tf(Msg::ChunkInProgress, &[&name])),
+    };
+    let uploaded = std::fs::metadata(&part).map_or(0, |metadata| metadata.len());
+
+    if query::flag(query, "finalize") {
+        let size = match query::get(query, "size").and_then(|size| size.parse::<u64>().ok()) {
+            Some(size) => size,
+            None => return HttpResponse::BadRequest().body(t(Msg::FinalizeSizeRequired)),
+        };
+        if !part.is_file() || uploaded != size {
+            return offset_conflict(uploaded);
+        }
+        if path.exists() && !overwrite {
+            return HttpResponse::Conflict().body(tf(Msg::FileExists, &[&name]));
+        }
+        match move_into_place(&part, &path, overwrite) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                return HttpResponse::Conflict().body(tf(Msg::FileExists, &[&name]));
+            }
+            Err(e) => {
+                log::error!("{}", tf(Msg::SaveUploadError, &[&path.display(), &e]));
+                return HttpResponse::InternalServerError().body(tf(Msg::SaveFailed, &[&name]));
+            }
+        }
+        log::info!("{}", tf(Msg::UploadSaved, &[&path.display(), &size]));
+        return HttpResponse::Ok().json(&serde_json::json!({ "files": [SavedFile { name, size }] }));
+    }
+
+    let offset = match query::get(query, "offset").map(|offset| offset.parse::<u64>()) {
+        Some(Ok(offset)) => offset,
+        None => 0,
+        Some(Err(_)) => return HttpResponse::BadRequest().body(t(Msg::InvalidOffset)),
+    };
+    if offset != uploaded {
+        return offset_conflict(uploaded);
+    }
+    if offset == 0 && path.exists() && !overwrite {
+        return HttpResponse::Conflict().body(tf(Msg::FileExists, &[&name]));
+    }
+    // 分块上传按整个文件的大小计算上限，超过后这个文件不可能完成，已上传的部分一并删除
+    let len = content_length(&req);
+    // offset 和 Content-Length 都来自客户端，相加溢出时同样视为超过上限
+    if offset.checked_add(len.unwrap_or(0)).is_none_or(|total| total > target.limit()) {
+        let _ = std::fs::remove_file(&part);
+        return too_large(target.max_size);
+    }
+    if let Some(res) = target.reject(len) {
+        return res;
+    }
+
+    let mut file = match OpenOptions::new().create(true).append(true).open(&part) {
+        Ok(file) => file,
+        Err(e) => {
+            log::error!("{}", tf(Msg::OpenTempFileFailed, &[&part.display(), &e]));
+            return HttpResponse::InternalServerError().body(tf(Msg::SaveFailed, &[&name]));
+        }
+    };
+    // 中途断开时已写入的部分保留，客户端可以从返回的 offset 继续
+    let mut written = uploaded;
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                log::debug!("{}", tf(Msg::ChunkInterrupted, &[&name, &e]));
+                break;
+            }
+        };
+        written += chunk.len() as u64;
+        let result = if written > target.limit() {
+            Err(io::Error::from(ErrorKind::FileTooLarge))
+        } else {
+            blocking(move || file.write_all(&chunk).map(|_| file)).await
+        };
+        file = match result {
+            Ok(file) => file,
+            Err(e) => {
+                let _ = std::fs::remove_file(&part);
+                return save_failed(&name, &part, target.max_size, e);
+            }
+        };
+    }
+    if let Err(e) = blocking(move || file.sync_all()).await {
+        let _ = std::fs::remove_file(&part);
+        return save_failed(&name, &part, target.max_size, e);
+    }
+    committed(written)
+}
+
+// GET 上传接口时返回拖放上传页面；只读模式下禁用上传区域并给出提示，返回链接用相对地址，经反向代理访问时同样有效
+pub async fn upload_page(target: State<UploadTarget>) -> HttpResponse {
+    let (notice, disabled) = if target.read_only {
+        (format!("<p class=\"notice\">{}</p>", t(Msg::UploadReadOnly)), " disabled")
+    } else {
+        (String::new(), "")
+    };
+    let html = TEMPLATE
+        .replace("{{title}}", t(Msg::UploadTitle))
+        .replace("{{back}}", "./")
+        .replace("{{back_label}}", t(Msg::BackToListing))
+        .replace("{{notice}}", &notice)
+        .replace("{{disabled_class}}", if target.read_only { " disabled" } else { "" })
+        .replace("{{disabled}}", disabled)
+        .replace("{{drop_label}}", t(Msg::UploadDrop))
+        .replace("{{overwrite_label}}", t(Msg::UploadOverwrite))
+        .replace("{{done_label}}", t(Msg::UploadDone))
+        .replace("{{failed_label}}", t(Msg::UploadFailed));
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(html)
+}
+
+// 从 Content-Disposition 中取出文件名，优先使用 RFC 5987 编码的 filename*
+fn field_filename(field: &Field) -> Option<String> {
+    let value = field.headers().get(CONTENT_DISPOSITION)?.to_str().ok()?;
+    let mut filename = None;
+    for param in value.split(';').map(str::trim) {
+        if let Some(encoded) = param.strip_prefix("filename*=") {
+            let encoded = encoded.trim_matches('"');
+            let encoded = encoded.split_once("''").map_or(encoded, |(_, rest)| rest);
+            return percent_encoding::percent_decode_str(encoded)
+                .decode_utf8()
+                .ok()
+                .map(|name| name.into_owned());
+        } else if let Some(name) = param.strip_prefix("filename=") {
+            filename = Some(name.trim_matches('"').to_string());
+        }
+    }
+    filename
+}
+
+// 只保留最后一段文件名，去掉路径分隔符和 .. 等
+fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    if name.is_empty() || name == "." || name == ".." || name.contains('\0') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+// 上传中的文件先写入同一目录下隐藏的 .upload-<进程号>.<序号>.tmp，全部写入并落盘后再改名为正式文件，
+// 临时文件名的长度与上传的文件名无关，文件名接近系统上限时同样能保存；
+// 同一文件系统中改名是原子的，读取方不会看到写了一半的文件；出错或客户端断开(处理函数被丢弃)时临时文件随守卫删除
+struct TempUpload {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl TempUpload {
+    fn new(dir: &Path) -> Self {
+        static SEQ: AtomicUsize = AtomicUsize::new(0);
+        let path = dir.join(format!(".upload-{}.{}.tmp", std::process::id(), SEQ.fetch_add(1, Ordering::Relaxed)));
+        TempUpload { path, persisted: false }
+    }
+
+    fn persist(mut self, path: &Path, overwrite: bool) -> io::Result<()> {
+        move_into_place(&self.path, path, overwrite)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+// 把写完的临时文件改名为正式文件，覆盖时替换已存在的同名文件。不覆盖时用硬链接代替改名，
+// 目标已存在(包括检查之后才被创建的同名文件)时返回 AlreadyExists 而不是替换它；
+// 文件系统不支持硬链接时退回到先检查再改名
+fn move_into_place(from: &Path, to: &Path, overwrite: bool) -> io::Result<()> {
+    if overwrite {
+        return std::fs::rename(from, to);
+    }
+    match std::fs::hard_link(from, to) {
+        Ok(()) => {
+            if let Err(e) = std::fs::remove_file(from) {
+                log::warn!("{}", tf(Msg::RemoveTempFileFailed, &[&from.display(), &e]));
+            }
+            Ok(())
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(e),
+        Err(_) if to.exists() => Err(io::Error::from(ErrorKind::AlreadyExists)),
+        Err(_) => std::fs::rename(from, to),
+    }
+}
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        if !self.persisted
+            && let Err(e) = std::fs::remove_file(&self.path)
+            && e.kind() != ErrorKind::NotFound
+        {
+            log::warn!("{}", tf(Msg::RemoveTempFileFailed, &[&self.path.display(), &e]));
+        }
+    }
+}
+
+// 逐块写入文件，返回写入的字节数；超过 limit 时立即停止并返回 FileTooLarge
+async fn save_field(path: &Path, field: &mut Field, limit: u64) -> io::Result<u64> {
+    let mut file = File::create(path)?;
+    let mut size = 0u64;
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk.map_err(|e| io::Error::other(e.to_string()))?;
+        size += chunk.len() as u64;
+        if size > limit {
+            return Err(io::Error::from(ErrorKind::FileTooLarge));
+        }
+        file = blocking(move || file.write_all(&chunk).map(|_| file)).await?;
+    }
+    blocking(move || file.sync_all()).await?;
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::http::{Method, StatusCode};
+    use ntex::web::test;
+
+    use super::*;
+    use crate::glob::{IGNORE_FILE, PathFilter};
+    use crate::testutil::TempDir;
+
+    #[ntex::test]
+    async fn chunked_upload_resumes_and_finalizes() {
+        let dir = TempDir::new();
+        let target = UploadTarget {
+            mount: dir.mount("/download/files"),
+            enabled: true,
+            read_only: false,
+            chunks: ChunkLocks::new(),
+            max_size: 16,
+            min_free: 0,
+        };
+        let app = test::init_service(
+            web::App::new().service(
+                web::resource("/download/files/upload")
+                    .state(target)
+                    .route(web::post().guard(web::guard::fn_guard(is_chunk)).to(upload_chunk)),
+            ),
+        )
+        .await;
+        let post = |query: &str, body: &'static str| {
+            test::TestRequest::with_uri(&format!("/download/files/upload?{}", query)).method(Method::POST).set_payload(body).to_request()
+        };
+
+        let res = test::call_service(&app, post("name=big.iso&offset=0", "hello ")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, r#"{"offset":6}"#);
+
+        // 客户端以为第一块没有成功，重发时返回已上传的位置
+        let res = test::call_service(&app, post("name=big.iso&offset=0", "hello ")).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert!(String::from_utf8_lossy(&test::read_body(res).await).contains(r#""offset":6"#));
+
+        let res = test::call_service(&app, post("name=big.iso&offset=6", "world")).await;
+        assert_eq!(test::read_body(res).await, r#"{"offset":11}"#);
+
+        let res = test::call_service(&app, post("name=big.iso&finalize=1&size=12", "")).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let res = test::call_service(&app, post("name=big.iso&finalize=1&size=11", "")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(dir.path().join("big.iso")).unwrap(), "hello world");
+        assert!(!dir.path().join(part_name("big.iso")).exists());
+
+        // 超过 --max-upload-size 时返回 413 并删除已上传的部分
+        let res = test::call_service(&app, post("name=huge.iso&offset=0", "0123456789")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = test::call_service(&app, post("name=huge.iso&offset=10", "0123456789")).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(!dir.path().join(part_name("huge.iso")).exists());
+
+        let res = test::call_service(&app, post("name=huge.iso&offset=0", "0123456789")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let req = test::TestRequest::with_uri("/download/files/upload?name=huge.iso&offset=10")
+            .method(Method::POST)
+            .header(CONTENT_LENGTH, u64::MAX.to_string())
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[ntex::test]
+    async fn failed_upload_leaves_existing_file_untouched() {
+        let dir = TempDir::new();
+        let target = UploadTarget {
+            mount: dir.mount("/download/files"),
+            enabled: true,
+            read_only: false,
+            chunks: ChunkLocks::new(),
+            max_size: 16,
+            min_free: 0,
+        };
+        let app = test::init_service(web::App::new().service(web::resource("/download/files/upload").state(target).route(web::post().to(upload)))).await;
+        let post = |query: &str, content: &str| {
+            let body = format!(
+                "--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\n{}\r\n--b--\r\n",
+                content
+            );
+            test::TestRequest::with_uri(&format!("/download/files/upload?{}", query))
+                .method(Method::POST)
+                .header(ntex::http::header::CONTENT_TYPE, "multipart/form-data; boundary=b")
+                .set_payload(body)
+                .to_request()
+        };
+
+        let res = test::call_service(&app, post("", "hello")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(dir.path().join("a.txt")).unwrap(), "hello");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // 覆盖时超过大小上限，原文件保持不变，临时文件被删除
+        let res = test::call_service(&app, post("overwrite=true", "0123456789abcdefghij")).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(std::fs::read_to_string(dir.path().join("a.txt")).unwrap(), "hello");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[ntex::test]
+    async fn saves_names_up_to_the_filesystem_limit() {
+        let dir = TempDir::new();
+        let target = UploadTarget {
+            mount: dir.mount("/download/files"),
+            enabled: true,
+            read_only: false,
+            chunks: ChunkLocks::new(),
+            max_size: 0,
+            min_free: 0,
+        };
+        let app = test::init_service(
+            web::App::new().service(
+                web::resource("/download/files/upload")
+                    .state(target)
+                    .route(web::post().guard(web::guard::fn_guard(is_chunk)).to(upload_chunk))
+                    .route(web::post().to(upload)),
+            ),
+        )
+        .await;
+        let name = format!("{}.txt", "a".repeat(251));
+        let body = format!(
+            "--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\r\nhello\r\n--b--\r\n",
+            name
+        );
+        let req = test::TestRequest::with_uri("/download/files/upload")
+            .method(Method::POST)
+            .header(ntex::http::header::CONTENT_TYPE, "multipart/form-data; boundary=b")
+            .set_payload(body)
+            .to_request();
+
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(dir.path().join(&name)).unwrap(), "hello");
+
+        // 分块上传的临时文件名同样与文件名的长度无关
+        let name = format!("{}.txt", "b".repeat(251));
+        let post = |query: String, body: &'static str| test::TestRequest::with_uri(&format!("/download/files/upload?{}", query)).method(Method::POST).set_payload(body).to_request();
+        let res = test::call_service(&app, post(format!("name={}&offset=0", name), "hello")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = test::call_service(&app, post(format!("name={}&finalize=1&size=5", name), "")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(dir.path().join(&name)).unwrap(), "hello");
+    }
+
+    #[test]
+    fn move_into_place_does_not_replace_without_overwrite() {
+        let dir = TempDir::new();
+        let temp = dir.write(".upload-1.0.tmp", "new");
+        let target = dir.write("a.txt", "old");
+
+        let err = move_into_place(&temp, &target, false).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "old");
+
+        move_into_place(&temp, &target, true).unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+        assert!(!temp.exists());
+
+        let temp = dir.write(".upload-1.1.tmp", "b");
+        move_into_place(&temp, &dir.path().join("b.txt"), false).unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[ntex::test]
+    async fn rejects_hidden_and_filtered_names() {
+        let dir = TempDir::new();
+        dir.write(IGNORE_FILE, "*.log\n");
+        let mut mount = dir.mount("/download/files");
+        mount.show_hidden = true;
+        mount.filter = PathFilter::default()
+            .with_denied_extensions(&["pem".to_string()])
+            .with_ignore_file(dir.path().join(IGNORE_FILE));
+        let target = UploadTarget {
+            mount,
+            enabled: true,
+            read_only: false,
+            chunks: ChunkLocks::new(),
+            max_size: 0,
+            min_free: 0,
+        };
+        let app = test::init_service(
+            web::App::new().service(
+                web::resource("/download/files/upload")
+                    .state(target)
+                    .route(web::post().guard(web::guard::fn_guard(is_chunk)).to(upload_chunk))
+                    .route(web::post().to(upload)),
+            ),
+        )
+        .await;
+        let multipart = |name: &str| {
+            let body = format!("--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\r\nx\r\n--b--\r\n", name);
+            test::TestRequest::with_uri("/download/files/upload?overwrite=true")
+                .method(Method::POST)
+                .header(ntex::http::header::CONTENT_TYPE, "multipart/form-data; boundary=b")
+                .set_payload(body)
+                .to_request()
+        };
+        let chunk = |name: &str| {
+            test::TestRequest::with_uri(&format!("/download/files/upload?name={}&overwrite=true", name))
+                .method(Method::POST)
+                .set_payload("x")
+                .to_request()
+        };
+
+        for name in [IGNORE_FILE, "key.pem", "debug.log"] {
+            assert_eq!(test::call_service(&app, multipart(name)).await.status(), StatusCode::FORBIDDEN, "{}", name);
+            assert_eq!(test::call_service(&app, chunk(name)).await.status(), StatusCode::FORBIDDEN, "{}", name);
+        }
+        assert_eq!(std::fs::read_to_string(dir.path().join(IGNORE_FILE)).unwrap(), "*.log\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert_eq!(test::call_service(&app, multipart("a.txt")).await.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,23 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use ntex::web::HttpResponse;
+
+// 版本信息接口路径，不受挂载点和认证影响
+pub const PATH: &str = "/version";
+
+// 构建时由 build.rs 写入
+pub const GIT_COMMIT: &str = env!("FILESHARE_GIT_COMMIT");
+const BUILD_TIMESTAMP: &str = env!("FILESHARE_BUILD_TIMESTAMP");
+
+// 返回版本号、git 提交和构建时间(RFC 3339)
+pub async fn version() -> HttpResponse {
+    let built_at = BUILD_TIMESTAMP
+        .parse::<u64>()
+        .map(|secs| humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(secs)).to_string())
+        .unwrap_or_default();
+    HttpResponse::Ok().json(&serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_commit": GIT_COMMIT,
+        "build_timestamp": built_at,
+    }))
+}
@@ -0,0 +1,227 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use ntex::service::{fn_factory_with_config, fn_service};
+use ntex::util::{Either, select};
+use ntex::web::types::State;
+use ntex::web::{self, HttpRequest, HttpResponse, ws};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::glob::PathFilter;
+use crate::i18n::{Msg, tf};
+use crate::mount::Mount;
+
+// 挂载点下的 WebSocket 接口路径
+pub const SUFFIX: &str = "/ws";
+
+// 最后一个事件之后等待这么久没有新事件才推送，批量复制时合并为一次推送
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// 事件持续不断时最多积攒这么久也要推送一次
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+// 每个客户端来不及接收时最多缓存的事件数，超出的旧事件丢弃
+const CHANNEL_CAPACITY: usize = 256;
+
+// 推送给客户端的变化，path 为解码后的URL路径
+#[derive(Clone, Debug, Serialize)]
+pub struct Change {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    path: String,
+}
+
+// 监视所有挂载点目录，所有工作线程共享同一个广播通道
+#[derive(Clone)]
+pub struct Hub {
+    sender: broadcast::Sender<Arc<Change>>,
+}
+
+// 被监视的目录和它对应的挂载点
+struct Root {
+    dir: PathBuf,
+    url_path: String,
+    show_hidden: bool,
+    filter: PathFilter,
+}
+
+impl Root {
+    // 文件路径对应的URL路径，隐藏文件和被通配符过滤掉的文件不推送
+    fn url(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        if !relative.as_os_str().is_empty() && !self.filter.allows(relative, path.is_dir()) {
+            return None;
+        }
+        let mut url = self.url_path.trim_end_matches('/').to_string();
+        for component in relative.components() {
+            let name = match component {
+                Component::Normal(name) => name.to_string_lossy(),
+                _ => return None,
+            };
+            if !self.show_hidden && name.starts_with('.') {
+                return None;
+            }
+            url.push('/');
+            url.push_str(&name);
+        }
+        Some(url)
+    }
+}
+
+impl Hub {
+    // 启动监视线程，目录无法监视时返回错误
+    pub fn start(mounts: &[Mount]) -> Result<Self, String> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).map_err(|e| tf(Msg::CreateWatcherFailed, &[&e]))?;
+        let mut roots = Vec::new();
+        for mount in mounts {
+            let dir = mount.dir.canonicalize().unwrap_or_else(|_| mount.dir.clone());
+            watcher
+                .watch(&dir, RecursiveMode::Recursive)
+                .map_err(|e| tf(Msg::WatchDirFailed, &[&dir.display(), &e]))?;
+            roots.push(Root {
+                dir,
+                url_path: mount.external(&mount.url_path),
+                show_hidden: mount.show_hidden,
+                filter: mount.filter.clone(),
+            });
+        }
+
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let hub = Hub { sender };
+        let events = hub.sender.clone();
+        std::thread::spawn(move || debounce(watcher, rx, roots, events));
+        Ok(hub)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Arc<Change>> {
+        self.sender.subscribe()
+    }
+}
+
+// 合并同一路径的连续事件：新建后又修改仍算新建，其余以最后一次为准
+fn merge(pending: &mut Vec<Change>, change: Change) {
+    match pending.iter_mut().find(|pending| pending.path == change.path) {
+        Some(existing) if existing.kind == "created" && change.kind == "modified" => {}
+        Some(existing) => existing.kind = change.kind,
+        None => pending.push(change),
+    }
+}
+
+// 把 notify 的事件转换为 created/removed/modified，重命名拆成旧路径删除和新路径新建
+fn changes(event: notify::Event, roots: &[Root]) -> Vec<Change> {
+    let kinds: Vec<&'static str> = match event.kind {
+        EventKind::Create(_) => vec!["created"; event.paths.len()],
+        EventKind::Remove(_) => vec!["removed"; event.paths.len()],
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => vec!["removed"],
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => vec!["created"],
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => vec!["removed", "created"],
+        EventKind::Modify(_) => vec!["modified"; event.paths.len()],
+        _ => return Vec::new(),
+    };
+    event
+        .paths
+        .iter()
+        .zip(kinds)
+        .filter_map(|(path, kind)| {
+            let url = roots.iter().find_map(|root| root.url(path))?;
+            Some(Change { kind, path: url })
+        })
+        .collect()
+}
+
+// 在独立线程中接收文件系统事件，防抖后广播给所有连接；watcher 需一直持有，释放后停止监视
+fn debounce(_watcher: RecommendedWatcher, rx: mpsc::Receiver<notify::Result<notify::Event>>, roots: Vec<Root>, events: broadcast::Sender<Arc<Change>>) {
+    let mut pending = Vec::new();
+    let mut since = Instant::now();
+    loop {
+        let received = if pending.is_empty() {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            rx.recv_timeout(DEBOUNCE.min(MAX_DELAY.saturating_sub(since.elapsed())))
+        };
+        match received {
+            Ok(Ok(event)) => {
+                if pending.is_empty() {
+                    since = Instant::now();
+                }
+                for change in changes(event, &roots) {
+                    merge(&mut pending, change);
+                }
+                if pending.is_empty() || since.elapsed() < MAX_DELAY {
+                    continue;
+                }
+            }
+            Ok(Err(e)) => {
+                log::warn!("{}", tf(Msg::WatchError, &[&e]));
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        // 没有客户端连接时发送失败，直接丢弃
+        for change in pending.drain(..) {
+            let _ = events.send(Arc::new(change));
+        }
+    }
+}
+
+// 每个挂载点的监视接口，只推送该挂载点下的变化；url_path 为浏览器中看到的地址，包含 --base-path
+#[derive(Clone)]
+pub struct WatchTarget {
+    pub hub: Hub,
+    pub url_path: String,
+}
+
+// 将广播的变化转发给一个 WebSocket 客户端，客户端断开后结束
+async fn forward(mut rx: broadcast::Receiver<Arc<Change>>, prefix: String, sink: ws::WsSink) {
+    let disconnect = sink.on_disconnect();
+    let mut disconnect = std::pin::pin!(disconnect);
+    loop {
+        let change = match select(rx.recv(), &mut disconnect).await {
+            Either::Left(Ok(change)) => change,
+            Either::Left(Err(broadcast::error::RecvError::Lagged(skipped))) => {
+                log::debug!("{}", tf(Msg::WatchLagged, &[&skipped]));
+                continue;
+            }
+            Either::Left(Err(broadcast::error::RecvError::Closed)) | Either::Right(()) => return,
+        };
+        if !change.path.starts_with(&prefix) {
+            continue;
+        }
+        let json = match serde_json::to_string(&*change) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+        if sink.send(ws::Message::Text(json.into())).await.is_err() {
+            return;
+        }
+    }
+}
+
+// 建立 WebSocket 连接，之后推送 {"type":"created","path":"/download/files/a.txt"} 格式的事件
+pub async fn watch(req: HttpRequest, target: State<WatchTarget>) -> Result<HttpResponse, web::Error> {
+    let hub = target.hub.clone();
+    let prefix = format!("{}/", target.url_path.trim_end_matches('/'));
+    ws::start(
+        req,
+        fn_factory_with_config(move |sink: ws::WsSink| {
+            ntex::rt::spawn(forward(hub.subscribe(), prefix.clone(), sink));
+            async {
+                Ok::<_, web::Error>(fn_service(|frame: ws::Frame| async move {
+                    Ok::<_, web::Error>(match frame {
+                        ws::Frame::Ping(data) => Some(ws::Message::Pong(data)),
+                        ws::Frame::Close(reason) => Some(ws::Message::Close(reason)),
+                        _ => None,
+                    })
+                }))
+            }
+        }),
+    )
+    .await
+}